@@ -1,6 +1,8 @@
+use serde::{Deserialize, Serialize};
 use std::collections::VecDeque;
 
 // APU (Audio Processing Unit) - Game Boy 音訊處理器
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Apu {
     // 方波通道 A (Pulse A) - 0xFF10-0xFF14
     pulse_a: PulseChannel,
@@ -20,6 +22,7 @@ pub struct Apu {
     frame_sequencer: FrameSequencer,
 
     // 音訊緩衝區
+    #[serde(skip)]
     pub audio_buffer: VecDeque<f32>,
     sample_counter: u32,
 }
@@ -313,6 +316,7 @@ impl Apu {
 }
 
 // 幀序列器 - 512Hz 時鐘
+#[derive(Clone, Serialize, Deserialize)]
 struct FrameSequencer {
     timer: u16,
     step: u8,
@@ -348,6 +352,7 @@ impl FrameSequencer {
 }
 
 // 方波通道
+#[derive(Clone, Serialize, Deserialize)]
 struct PulseChannel {
     enabled: bool,
     dac_enabled: bool,
@@ -663,6 +668,7 @@ impl PulseChannel {
 }
 
 // 波形通道
+#[derive(Clone, Serialize, Deserialize)]
 struct WaveChannel {
     enabled: bool,
     dac_enabled: bool,
@@ -882,6 +888,7 @@ impl WaveChannel {
 }
 
 // 噪音通道
+#[derive(Clone, Serialize, Deserialize)]
 struct NoiseChannel {
     enabled: bool,
     dac_enabled: bool,
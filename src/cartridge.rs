@@ -0,0 +1,502 @@
+//! 卡帶標頭 (0x0100-0x014F)
+//!
+//! 解析標題、製造商代碼、CGB/SGB 旗標、授權商、ROM/RAM 大小、目的地與版本，
+//! 並驗證 Nintendo logo、標頭檢查碼 (0x014D) 與全域檢查碼 (0x014E-0x014F)。
+
+/// 開機 ROM 比對的 Nintendo logo (0x0104-0x0133)
+pub const NINTENDO_LOGO: [u8; 48] = [
+    0xCE, 0xED, 0x66, 0x66, 0xCC, 0x0D, 0x00, 0x0B, 0x03, 0x73, 0x00, 0x83, 0x00, 0x0C, 0x00, 0x0D,
+    0x00, 0x08, 0x11, 0x1F, 0x88, 0x89, 0x00, 0x0E, 0xDC, 0xCC, 0x6E, 0xE6, 0xDD, 0xDD, 0xD9, 0x99,
+    0xBB, 0xBB, 0x67, 0x63, 0x6E, 0x0E, 0xEC, 0xCC, 0xDD, 0xDC, 0x99, 0x9F, 0xBB, 0xB9, 0x33, 0x3E,
+];
+
+// 標頭結束位置 (含全域檢查碼)
+const HEADER_END: usize = 0x150;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HeaderError {
+    TooShort(usize),
+}
+
+impl std::fmt::Display for HeaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HeaderError::TooShort(len) => write!(f, "ROM 只有 {} bytes，不足以包含卡帶標頭", len),
+        }
+    }
+}
+
+impl std::error::Error for HeaderError {}
+
+/// CGB 旗標 (0x0143)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CgbSupport {
+    None,
+    Compatible, // 0x80：支援 CGB，也可在 DMG 執行
+    Only,       // 0xC0：僅限 CGB
+}
+
+/// 授權商代碼：舊代碼 (0x014B)，或舊代碼為 0x33 時改用 0x0144-0x0145 的新代碼 (兩個 ASCII 字元)
+/// 兩種代碼的對照表不同，例如舊代碼 0x38 為 Capcom，新代碼 "38" 為 Hudson Soft
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Licensee {
+    Old(u8),
+    New(String),
+}
+
+impl std::fmt::Display for Licensee {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Licensee::Old(code) => write!(f, "{:02X}", code),
+            Licensee::New(code) => write!(f, "\"{}\"", code),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CartridgeHeader {
+    pub title: String,
+    pub manufacturer: Option<String>, // 0x013F-0x0142，僅較新的卡帶有
+    pub cgb: CgbSupport,
+    pub sgb: bool,
+    pub licensee: Licensee,
+    pub cartridge_type: u8,
+    pub rom_size_code: u8,
+    pub ram_size_code: u8,
+    pub destination: u8, // 0 = 日本，1 = 海外
+    pub version: u8,
+    pub header_checksum: u8,
+    pub global_checksum: u16,
+    logo_valid: bool,
+    computed_header_checksum: u8,
+    computed_global_checksum: u16,
+}
+
+impl CartridgeHeader {
+    pub fn parse(rom: &[u8]) -> Result<Self, HeaderError> {
+        if rom.len() < HEADER_END {
+            return Err(HeaderError::TooShort(rom.len()));
+        }
+
+        let cgb = match rom[0x143] {
+            0xC0 => CgbSupport::Only,
+            0x80 => CgbSupport::Compatible,
+            _ => CgbSupport::None,
+        };
+        // 製造商代碼為 4 個大寫英數字，出現時標題縮短為 11 字元
+        let manufacturer = &rom[0x13F..0x143];
+        let manufacturer = (cgb != CgbSupport::None
+            && manufacturer
+                .iter()
+                .all(|&c| c.is_ascii_uppercase() || c.is_ascii_digit()))
+        .then(|| String::from_utf8_lossy(manufacturer).into_owned());
+        let title_end = match (&manufacturer, cgb) {
+            (Some(_), _) => 0x13F,
+            (None, CgbSupport::None) => 0x144,
+            (None, _) => 0x143,
+        };
+        let title: String = rom[0x134..title_end]
+            .iter()
+            .take_while(|&&c| c != 0)
+            .map(|&c| {
+                if c.is_ascii_graphic() || c == b' ' {
+                    c as char
+                } else {
+                    '?'
+                }
+            })
+            .collect();
+
+        let licensee = if rom[0x14B] == 0x33 {
+            Licensee::New(String::from_utf8_lossy(&rom[0x144..0x146]).into_owned())
+        } else {
+            Licensee::Old(rom[0x14B])
+        };
+
+        Ok(CartridgeHeader {
+            title: title.trim_end().to_string(),
+            manufacturer,
+            cgb,
+            sgb: rom[0x146] == 0x03,
+            licensee,
+            cartridge_type: rom[0x147],
+            rom_size_code: rom[0x148],
+            ram_size_code: rom[0x149],
+            destination: rom[0x14A],
+            version: rom[0x14C],
+            header_checksum: rom[0x14D],
+            global_checksum: u16::from_be_bytes([rom[0x14E], rom[0x14F]]),
+            logo_valid: rom[0x104..0x134] == NINTENDO_LOGO,
+            computed_header_checksum: header_checksum(rom),
+            computed_global_checksum: global_checksum(rom),
+        })
+    }
+
+    /// 標頭宣告的 ROM 大小 (0x0148)
+    pub fn rom_size(&self) -> Option<usize> {
+        match self.rom_size_code {
+            0x00..=0x08 => Some(0x8000 << self.rom_size_code),
+            0x52 => Some(72 * 0x4000),
+            0x53 => Some(80 * 0x4000),
+            0x54 => Some(96 * 0x4000),
+            _ => None,
+        }
+    }
+
+    /// 標頭宣告的外部 RAM 大小 (0x0149)
+    pub fn ram_size(&self) -> usize {
+        match self.ram_size_code {
+            0x01 => 2 * 1024,
+            0x02 => 8 * 1024,
+            0x03 => 32 * 1024,
+            0x04 => 128 * 1024,
+            0x05 => 64 * 1024,
+            _ => 0,
+        }
+    }
+
+    pub fn logo_valid(&self) -> bool {
+        self.logo_valid
+    }
+
+    pub fn header_checksum_valid(&self) -> bool {
+        self.computed_header_checksum == self.header_checksum
+    }
+
+    pub fn global_checksum_valid(&self) -> bool {
+        self.computed_global_checksum == self.global_checksum
+    }
+
+    pub fn cartridge_type_name(&self) -> &'static str {
+        match self.cartridge_type {
+            0x00 => "ROM ONLY",
+            0x01 => "MBC1",
+            0x02 => "MBC1+RAM",
+            0x03 => "MBC1+RAM+BATTERY",
+            0x05 => "MBC2",
+            0x06 => "MBC2+BATTERY",
+            0x08 => "ROM+RAM",
+            0x09 => "ROM+RAM+BATTERY",
+            0x0B => "MMM01",
+            0x0C => "MMM01+RAM",
+            0x0D => "MMM01+RAM+BATTERY",
+            0x0F => "MBC3+TIMER+BATTERY",
+            0x10 => "MBC3+TIMER+RAM+BATTERY",
+            0x11 => "MBC3",
+            0x12 => "MBC3+RAM",
+            0x13 => "MBC3+RAM+BATTERY",
+            0x19 => "MBC5",
+            0x1A => "MBC5+RAM",
+            0x1B => "MBC5+RAM+BATTERY",
+            0x1C => "MBC5+RUMBLE",
+            0x1D => "MBC5+RUMBLE+RAM",
+            0x1E => "MBC5+RUMBLE+RAM+BATTERY",
+            0x20 => "MBC6",
+            0x22 => "MBC7+SENSOR+RUMBLE+RAM+BATTERY",
+            0xFC => "POCKET CAMERA",
+            0xFD => "BANDAI TAMA5",
+            0xFE => "HuC3",
+            0xFF => "HuC1+RAM+BATTERY",
+            _ => "未知",
+        }
+    }
+
+    /// 卡帶類型是否有外接 RAM (大小由 0x0149 宣告)；MBC2 的 RAM 內建在晶片中，標頭應宣告為 0
+    pub fn has_external_ram(&self) -> bool {
+        matches!(
+            self.cartridge_type,
+            0x02 | 0x03 // MBC1
+                | 0x08 | 0x09 // ROM+RAM
+                | 0x0C | 0x0D // MMM01
+                | 0x10 | 0x12 | 0x13 // MBC3
+                | 0x1A | 0x1B | 0x1D | 0x1E // MBC5
+                | 0x22 // MBC7
+                | 0xFC // POCKET CAMERA
+                | 0xFE // HuC3
+                | 0xFF // HuC1
+        )
+    }
+
+    pub fn licensee_name(&self) -> &'static str {
+        match &self.licensee {
+            Licensee::Old(code) => old_licensee_name(*code),
+            Licensee::New(code) => new_licensee_name(code),
+        }
+    }
+
+    /// `--info` 輸出：所有欄位、驗證結果與大小警告
+    pub fn report(&self, rom_len: usize) -> String {
+        let ok = |valid: bool| if valid { "OK" } else { "不符" };
+        let cgb = match self.cgb {
+            CgbSupport::None => "否",
+            CgbSupport::Compatible => "支援 (可在 DMG 執行)",
+            CgbSupport::Only => "僅限 CGB",
+        };
+        let rom_size = match self.rom_size() {
+            Some(size) => format!("{} KiB", size / 1024),
+            None => "未知".to_string(),
+        };
+
+        let mut lines = vec![
+            format!("標題:         {}", self.title),
+            format!(
+                "製造商代碼:   {}",
+                self.manufacturer.as_deref().unwrap_or("-")
+            ),
+            format!("CGB:          {}", cgb),
+            format!("SGB:          {}", if self.sgb { "支援" } else { "否" }),
+            format!("授權商:       {} ({})", self.licensee, self.licensee_name()),
+            format!(
+                "卡帶類型:     {:02X} ({})",
+                self.cartridge_type,
+                self.cartridge_type_name()
+            ),
+            format!("ROM 大小:     {:02X} ({})", self.rom_size_code, rom_size),
+            format!(
+                "RAM 大小:     {:02X} ({} KiB)",
+                self.ram_size_code,
+                self.ram_size() / 1024
+            ),
+            format!(
+                "目的地:       {}",
+                if self.destination == 0 {
+                    "日本"
+                } else {
+                    "海外"
+                }
+            ),
+            format!("版本:         {}", self.version),
+            format!("Logo:         {}", ok(self.logo_valid())),
+            format!(
+                "標頭檢查碼:   {:02X} (計算值 {:02X}) {}",
+                self.header_checksum,
+                self.computed_header_checksum,
+                ok(self.header_checksum_valid())
+            ),
+            format!(
+                "全域檢查碼:   {:04X} (計算值 {:04X}) {}",
+                self.global_checksum,
+                self.computed_global_checksum,
+                ok(self.global_checksum_valid())
+            ),
+        ];
+        lines.extend(
+            self.warnings(rom_len)
+                .into_iter()
+                .map(|w| format!("警告: {}", w)),
+        );
+        lines.join("\n")
+    }
+
+    /// 標頭與實際 ROM 內容不一致之處
+    pub fn warnings(&self, rom_len: usize) -> Vec<String> {
+        let mut warnings = Vec::new();
+        match self.rom_size() {
+            Some(size) if size != rom_len => warnings.push(format!(
+                "標頭宣告 ROM 大小 {} bytes，實際檔案為 {} bytes",
+                size, rom_len
+            )),
+            None => warnings.push(format!(
+                "無法辨識的 ROM 大小代碼 {:02X}",
+                self.rom_size_code
+            )),
+            _ => {}
+        }
+        let has_ram = self.has_external_ram();
+        if has_ram && self.ram_size() == 0 {
+            warnings.push("卡帶類型包含外接 RAM，但標頭的 RAM 大小為 0".to_string());
+        } else if !has_ram && self.ram_size() > 0 {
+            warnings.push("卡帶類型沒有外接 RAM，但標頭宣告了 RAM 大小".to_string());
+        }
+        if !self.logo_valid() {
+            warnings.push("Nintendo logo 不符，實機無法開機".to_string());
+        }
+        if !self.header_checksum_valid() {
+            warnings.push("標頭檢查碼不符，實機無法開機".to_string());
+        }
+        warnings
+    }
+}
+
+// 舊授權商代碼 (0x014B)
+fn old_licensee_name(code: u8) -> &'static str {
+    match code {
+        0x00 => "None",
+        0x01 | 0x31 => "Nintendo",
+        0x08 | 0x38 => "Capcom",
+        0x0A | 0xE0 => "Jaleco",
+        0x13 | 0x69 => "Electronic Arts",
+        0x18 => "Hudson Soft",
+        0x1F | 0x4A | 0x61 => "Virgin",
+        0x28 | 0x7F | 0x97 | 0xC2 => "Kemco",
+        0x30 | 0x70 => "Infogrames",
+        0x32 | 0xA2 | 0xB2 => "Bandai",
+        0x34 | 0xA4 => "Konami",
+        0x41 => "Ubi Soft",
+        0x42 | 0xEB => "Atlus",
+        0x51 | 0xB0 => "Acclaim",
+        0x52 => "Activision",
+        0x54 => "GameTek",
+        0x56 | 0xDB | 0xFF => "LJN",
+        0x60 => "Titus",
+        0x67 => "Ocean",
+        0x78 => "THQ",
+        0x79 => "Accolade",
+        0x9B => "Tecmo",
+        0xAF => "Namco",
+        0xB6 => "HAL Laboratory",
+        0xB7 => "SNK",
+        0xBB => "Sunsoft",
+        0xC0 | 0xD0 => "Taito",
+        0xC3 => "Squaresoft",
+        0xC5 => "Data East",
+        0xC8 => "Koei",
+        0xE9 => "Natsume",
+        _ => "未知",
+    }
+}
+
+// 新授權商代碼 (0x0144-0x0145)
+fn new_licensee_name(code: &str) -> &'static str {
+    match code {
+        "00" => "None",
+        "01" | "31" => "Nintendo",
+        "08" => "Capcom",
+        "13" | "69" => "Electronic Arts",
+        "18" | "38" => "Hudson Soft",
+        "28" => "Kemco",
+        "30" => "Viacom",
+        "32" => "Bandai",
+        "33" | "93" => "Ocean/Acclaim",
+        "34" | "54" | "A4" => "Konami",
+        "37" => "Taito",
+        "41" => "Ubi Soft",
+        "42" => "Atlus",
+        "51" => "Acclaim",
+        "52" => "Activision",
+        "56" => "LJN",
+        "60" => "Titus",
+        "61" => "Virgin",
+        "64" => "LucasArts",
+        "67" => "Ocean",
+        "70" => "Infogrames",
+        "78" => "THQ",
+        "79" => "Accolade",
+        _ => "未知",
+    }
+}
+
+/// 0x0134-0x014C 的標頭檢查碼 (開機 ROM 會驗證)
+pub fn header_checksum(rom: &[u8]) -> u8 {
+    rom[0x134..0x14D]
+        .iter()
+        .fold(0u8, |sum, &byte| sum.wrapping_sub(byte).wrapping_sub(1))
+}
+
+/// 全部位元組的和 (不含 0x014E-0x014F 本身)
+pub fn global_checksum(rom: &[u8]) -> u16 {
+    rom.iter()
+        .enumerate()
+        .filter(|&(index, _)| index != 0x14E && index != 0x14F)
+        .fold(0u16, |sum, (_, &byte)| sum.wrapping_add(byte as u16))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 建立標頭與檢查碼都正確的 32 KiB ROM
+    fn build_rom(title: &[u8], cgb_flag: u8) -> Vec<u8> {
+        let mut rom = vec![0u8; 0x8000];
+        rom[0x104..0x134].copy_from_slice(&NINTENDO_LOGO);
+        rom[0x134..0x134 + title.len()].copy_from_slice(title);
+        rom[0x143] = cgb_flag;
+        rom[0x146] = 0x03;
+        rom[0x147] = 0x03; // MBC1+RAM+BATTERY
+        rom[0x149] = 0x02;
+        rom[0x14A] = 0x01;
+        rom[0x14B] = 0x33;
+        rom[0x144..0x146].copy_from_slice(b"01");
+        rom[0x14C] = 0x02;
+        rom[0x14D] = header_checksum(&rom);
+        let global = global_checksum(&rom);
+        rom[0x14E..0x150].copy_from_slice(&global.to_be_bytes());
+        rom
+    }
+
+    #[test]
+    fn parses_header_fields() {
+        let header = CartridgeHeader::parse(&build_rom(b"TETRIS", 0x00)).unwrap();
+        assert_eq!(header.title, "TETRIS");
+        assert_eq!(header.manufacturer, None);
+        assert_eq!(header.cgb, CgbSupport::None);
+        assert!(header.sgb);
+        assert_eq!(header.licensee_name(), "Nintendo");
+        assert_eq!(header.cartridge_type_name(), "MBC1+RAM+BATTERY");
+        assert_eq!(header.rom_size(), Some(0x8000));
+        assert_eq!(header.ram_size(), 8 * 1024);
+        assert_eq!((header.destination, header.version), (1, 2));
+        assert!(
+            header.logo_valid() && header.header_checksum_valid() && header.global_checksum_valid()
+        );
+        assert!(header.warnings(0x8000).is_empty());
+    }
+
+    #[test]
+    fn splits_manufacturer_code_from_cgb_title() {
+        let header = CartridgeHeader::parse(&build_rom(b"POKEMON YELAPSE", 0x80)).unwrap();
+        assert_eq!(header.title, "POKEMON YEL");
+        assert_eq!(header.manufacturer.as_deref(), Some("APSE"));
+        assert_eq!(header.cgb, CgbSupport::Compatible);
+    }
+
+    #[test]
+    fn reports_checksum_and_size_mismatches() {
+        let mut rom = build_rom(b"HACK", 0x00);
+        rom[0x134] = b'X';
+        rom.truncate(0x4000);
+        let header = CartridgeHeader::parse(&rom).unwrap();
+        assert!(!header.header_checksum_valid());
+        assert!(!header.global_checksum_valid());
+
+        let report = header.report(rom.len());
+        assert!(report.contains("實際檔案為 16384 bytes"));
+        assert!(report.contains("標頭檢查碼不符"));
+        assert_eq!(
+            CartridgeHeader::parse(&[0; 0x100]),
+            Err(HeaderError::TooShort(0x100))
+        );
+    }
+
+    #[test]
+    fn old_and_new_licensee_codes_use_separate_tables() {
+        let mut rom = build_rom(b"GAME", 0x00);
+        rom[0x144..0x146].copy_from_slice(b"38");
+        let header = CartridgeHeader::parse(&rom).unwrap();
+        assert_eq!(header.licensee, Licensee::New("38".to_string()));
+        assert_eq!(header.licensee_name(), "Hudson Soft");
+
+        rom[0x14B] = 0x38;
+        let header = CartridgeHeader::parse(&rom).unwrap();
+        assert_eq!(header.licensee, Licensee::Old(0x38));
+        assert_eq!(header.licensee_name(), "Capcom");
+    }
+
+    #[test]
+    fn ram_warnings_follow_cartridge_type() {
+        let header_with = |cartridge_type: u8, ram_size_code: u8| {
+            let mut rom = build_rom(b"GAME", 0x00);
+            rom[0x147] = cartridge_type;
+            rom[0x149] = ram_size_code;
+            rom[0x14D] = header_checksum(&rom);
+            CartridgeHeader::parse(&rom).unwrap()
+        };
+        // MBC2 的 RAM 內建，標頭宣告為 0 時不需要警告
+        assert!(header_with(0x06, 0x00).warnings(0x8000).is_empty());
+        assert!(!header_with(0x06, 0x02).warnings(0x8000).is_empty());
+        // POCKET CAMERA 與 HuC3 的名稱沒有 "RAM"，但有外接 RAM
+        assert!(header_with(0xFC, 0x04).warnings(0x8000).is_empty());
+        assert!(header_with(0xFE, 0x02).warnings(0x8000).is_empty());
+        assert!(!header_with(0xFE, 0x00).warnings(0x8000).is_empty());
+    }
+}
@@ -0,0 +1,320 @@
+//! 金手指
+//!
+//! - GameShark `ttVVAAAA`：每次進入 VBlank 時將 VV 寫入 RAM 位址 AAAA (位址為 little-endian，
+//!   例如 `01FF38CD` 寫入 0xCD38)。tt 為類型/bank，DMG 一律視為 01。
+//! - Game Genie `ABC-DEF` 或 `ABC-DEF-GHI`：`Mmu::read_byte_ppu` 讀取 ROM 時以 AB 取代原值，
+//!   有比較值 (GHI) 時只在原值相符才取代，避免在錯誤的 bank 生效。
+//!
+//! 每個 ROM 的金手指存於同名的 `.cht` 檔，每行為 `on|off 代碼 名稱`，`#` 開頭為註解。
+
+use std::path::Path;
+
+/// 解析後的金手指代碼
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheatCode {
+    GameShark {
+        kind: u8,
+        address: u16,
+        value: u8,
+    },
+    GameGenie {
+        address: u16,
+        value: u8,
+        compare: Option<u8>,
+    },
+}
+
+#[derive(Debug)]
+pub enum CheatError {
+    Io(std::io::Error),
+    InvalidCode(String),
+    Parse { line: usize, text: String },
+}
+
+impl std::fmt::Display for CheatError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CheatError::Io(err) => write!(f, "無法讀寫金手指檔: {}", err),
+            CheatError::InvalidCode(code) => write!(f, "無效的金手指代碼: {}", code),
+            CheatError::Parse { line, text } => write!(f, "金手指檔第 {} 行格式錯誤: {}", line, text),
+        }
+    }
+}
+
+impl std::error::Error for CheatError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            CheatError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for CheatError {
+    fn from(err: std::io::Error) -> Self {
+        CheatError::Io(err)
+    }
+}
+
+impl std::str::FromStr for CheatCode {
+    type Err = CheatError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || CheatError::InvalidCode(text.to_string());
+        let digits: String = text.chars().filter(|&c| c != '-').collect();
+        if !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let hex = |range: std::ops::Range<usize>| u16::from_str_radix(&digits[range], 16).unwrap();
+
+        match (digits.len(), text.contains('-')) {
+            (8, false) => Ok(CheatCode::GameShark {
+                kind: hex(0..2) as u8,
+                value: hex(2..4) as u8,
+                address: hex(4..6) | (hex(6..8) << 8),
+            }),
+            (6 | 9, true) => {
+                // ABC-DEF-GHI：位址為 (F ^ 0xF) CDE，比較值為 GI 右旋 2 位元後 XOR 0xBA
+                let address = ((hex(5..6) ^ 0xF) << 12) | hex(2..5);
+                let compare = (digits.len() == 9).then(|| {
+                    let raw = ((hex(6..7) << 4) | hex(8..9)) as u8;
+                    raw.rotate_right(2) ^ 0xBA
+                });
+                Ok(CheatCode::GameGenie {
+                    address,
+                    value: hex(0..2) as u8,
+                    compare,
+                })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+/// 一個金手指：原始代碼文字、名稱與是否啟用
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cheat {
+    pub code: CheatCode,
+    pub text: String,
+    pub name: String,
+    pub enabled: bool,
+}
+
+impl Cheat {
+    pub fn new(text: &str, name: &str) -> Result<Self, CheatError> {
+        Ok(Cheat {
+            code: text.parse()?,
+            text: text.to_ascii_uppercase(),
+            name: name.to_string(),
+            enabled: true,
+        })
+    }
+}
+
+/// 目前載入的金手指
+#[derive(Debug, Clone, Default)]
+pub struct Cheats {
+    cheats: Vec<Cheat>,
+    modified: bool, // 結束時是否需要寫回金手指檔
+}
+
+impl Cheats {
+    pub fn load(path: &Path) -> Result<Self, CheatError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, CheatError> {
+        let mut cheats = Vec::new();
+        for (index, raw) in text.lines().enumerate() {
+            let line = raw.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let error = || CheatError::Parse {
+                line: index + 1,
+                text: raw.to_string(),
+            };
+
+            let mut words = line.splitn(3, char::is_whitespace);
+            let enabled = match words.next() {
+                Some("on") => true,
+                Some("off") => false,
+                _ => return Err(error()),
+            };
+            let code = words.next().ok_or_else(error)?;
+            let name = words.next().unwrap_or("").trim();
+            let mut cheat = Cheat::new(code, name).map_err(|_| error())?;
+            cheat.enabled = enabled;
+            cheats.push(cheat);
+        }
+        Ok(Cheats {
+            cheats,
+            modified: false,
+        })
+    }
+
+    pub fn save(&mut self, path: &Path) -> Result<(), CheatError> {
+        std::fs::write(path, self.to_file_string())?;
+        self.modified = false;
+        Ok(())
+    }
+
+    pub fn to_file_string(&self) -> String {
+        self.cheats
+            .iter()
+            .map(|cheat| {
+                let state = if cheat.enabled { "on" } else { "off" };
+                format!("{} {} {}", state, cheat.text, cheat.name).trim_end().to_string() + "\n"
+            })
+            .collect()
+    }
+
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Cheat> {
+        self.cheats.iter()
+    }
+
+    pub fn add(&mut self, cheat: Cheat) -> usize {
+        self.cheats.push(cheat);
+        self.modified = true;
+        self.cheats.len() - 1
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Cheat> {
+        (index < self.cheats.len()).then(|| {
+            self.modified = true;
+            self.cheats.remove(index)
+        })
+    }
+
+    /// 啟用或停用指定的金手指，編號不存在時回傳 false
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) -> bool {
+        match self.cheats.get_mut(index) {
+            Some(cheat) => {
+                cheat.enabled = enabled;
+                self.modified = true;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 套用 Game Genie 代碼到 ROM 讀取結果
+    #[inline]
+    pub fn patch_rom(&self, address: u16, original: u8) -> u8 {
+        for cheat in self.cheats.iter().filter(|cheat| cheat.enabled) {
+            if let CheatCode::GameGenie {
+                address: target,
+                value,
+                compare,
+            } = cheat.code
+                && target == address
+                && compare.is_none_or(|compare| compare == original)
+            {
+                return value;
+            }
+        }
+        original
+    }
+
+    /// 啟用中的 GameShark 寫入 (位址, 數值)，由 `Mmu` 在進入 VBlank 時套用
+    pub fn ram_writes(&self) -> impl Iterator<Item = (u16, u8)> + '_ {
+        self.cheats
+            .iter()
+            .filter(|cheat| cheat.enabled)
+            .filter_map(|cheat| match cheat.code {
+                CheatCode::GameShark { address, value, .. } => Some((address, value)),
+                CheatCode::GameGenie { .. } => None,
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::mmu::Mmu;
+
+    #[test]
+    fn decodes_gameshark_codes() {
+        assert_eq!(
+            "01FF38CD".parse::<CheatCode>().unwrap(),
+            CheatCode::GameShark {
+                kind: 0x01,
+                address: 0xCD38,
+                value: 0xFF
+            }
+        );
+        assert!("01FF38C".parse::<CheatCode>().is_err());
+        assert!("01FG38CD".parse::<CheatCode>().is_err());
+    }
+
+    #[test]
+    fn decodes_game_genie_codes() {
+        assert_eq!(
+            "00A-17B".parse::<CheatCode>().unwrap(),
+            CheatCode::GameGenie {
+                address: 0x4A17,
+                value: 0x00,
+                compare: None
+            }
+        );
+        // GI = 0xC9 右旋 2 位元為 0x72，XOR 0xBA 得到 0xC8
+        assert_eq!(
+            "3EA-17B-C49".parse::<CheatCode>().unwrap(),
+            CheatCode::GameGenie {
+                address: 0x4A17,
+                value: 0x3E,
+                compare: Some(0xC8)
+            }
+        );
+    }
+
+    #[test]
+    fn game_genie_patches_rom_reads_when_compare_matches() {
+        let mut mmu = Mmu::new();
+        mmu.rom[0x4A17] = 0xC8;
+        mmu.rom[0x0150] = 0x12;
+        mmu.cheats.add(Cheat::new("3EA-17B-C49", "").unwrap());
+        mmu.cheats.add(Cheat::new("001-50F-E6E", "").unwrap()); // 比較值不符
+
+        assert_eq!(mmu.read_byte_ppu(0x4A17), 0x3E);
+        assert_eq!(mmu.read_byte_ppu(0x0150), 0x12);
+
+        mmu.cheats.set_enabled(0, false);
+        assert_eq!(mmu.read_byte_ppu(0x4A17), 0xC8);
+    }
+
+    #[test]
+    fn gameshark_pokes_ram_on_vblank() {
+        let mut gb = crate::gameboy::GameBoy::new();
+        gb.mmu.rom[0x0100] = 0x18; // JR -2
+        gb.mmu.rom[0x0101] = 0xFE;
+        gb.mmu.cheats.add(Cheat::new("016338CD", "lives").unwrap());
+
+        gb.mmu.write_byte(0xCD38, 0x01);
+        gb.run_frame();
+        assert_eq!(gb.mmu.read_byte_ppu(0xCD38), 0x63);
+
+        gb.mmu.write_byte(0xCD38, 0x01);
+        gb.mmu.cheats.set_enabled(0, false);
+        gb.run_frame();
+        assert_eq!(gb.mmu.read_byte_ppu(0xCD38), 0x01);
+    }
+
+    #[test]
+    fn cheat_file_round_trips() {
+        let text = "# Tetris\non 016338CD Infinite lives\noff 00A-17B-C49\n";
+        let cheats = Cheats::parse(text).unwrap();
+        let list: Vec<_> = cheats.iter().collect();
+        assert_eq!(list.len(), 2);
+        assert_eq!(list[0].name, "Infinite lives");
+        assert!(list[0].enabled && !list[1].enabled);
+        assert_eq!(cheats.to_file_string(), "on 016338CD Infinite lives\noff 00A-17B-C49\n");
+
+        let err = Cheats::parse("on 016338CD\nmaybe 00A-17B\n").unwrap_err();
+        assert!(matches!(err, CheatError::Parse { line: 2, .. }));
+    }
+}
@@ -0,0 +1,380 @@
+//! 命令列參數解析
+
+use crate::config::{
+    Config, GameConfig, GamepadConfig, KeyBindings, MacroConfig, SpeedConfig, TurboConfig,
+};
+use crate::gameboy::Model;
+use crate::palette::Palette;
+use crate::rom::RomOptions;
+use crate::sdl3::DebugOptions;
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+用法: rust_gb [選項] <ROM 路徑>
+
+顯示:
+  --scale <n>          視窗放大倍率 (1-16)
+  --fullscreen         全螢幕
+  --windowed           視窗模式 (覆蓋設定檔的 video.fullscreen)
+  --palette <name>     調色盤: gray、green、pocket 或 4 個 RRGGBB (以逗號分隔)
+
+模擬:
+  --model <model>      機種: dmg、mgb、sgb (預設 dmg)
+  --boot-rom <file>    從 256 bytes 的開機 ROM 開始執行
+  --speed <x>          模擬速度倍率 (0.1-10)
+  --state <file>       啟動時載入即時存檔 (F5 儲存、F8 載入)
+  --mute               不開啟音訊
+  --no-mute            開啟音訊 (覆蓋設定檔的 audio.enabled)
+  --headless           不開啟視窗，以最快速度執行 (需搭配 --frames 或 --play)
+  --frames <n>         執行 n 幀後結束
+  --record <file>      錄製輸入影片 (從開機或 --state 開始)
+  --play <file>        播放輸入影片，不同步時顯示警告
+
+ROM 與存檔:
+  --info               顯示卡帶標頭資訊後結束
+  --save-dir <dir>     外部 RAM 存檔目錄 (預設與 ROM 同目錄)
+  --patch <file>       套用 IPS/BPS/UPS 修補檔
+  --entry <name>       壓縮檔內的 ROM 檔名
+  --screenshot-dir <dir>  螢幕截圖目錄 (F12，預設與 ROM 同目錄)
+  --config <file>      使用指定的設定檔 (TOML 或 JSON)
+
+除錯:
+  --debug              stdin 除錯命令列
+  --debug-windows      記憶體與 VRAM 檢視視窗
+  --gdb <port>         GDB 遠端除錯連接埠
+  --trace <file>       輸出 Gameboy Doctor 格式的追蹤紀錄
+  --trace-labels       追蹤紀錄在有標籤的位址前插入 `label:` 行
+  --profile <prefix>   結束時輸出效能分析
+  -h, --help           顯示此說明";
+
+/// 所有啟動選項
+#[derive(Debug, Default)]
+pub struct Options {
+    pub rom_path: String,
+    pub info: bool,
+    pub scale: Option<u32>,
+    pub fullscreen: Option<bool>, // None 時使用設定檔
+    pub palette: Option<Palette>,
+    pub model: Option<Model>,
+    pub boot_rom: Option<PathBuf>,
+    pub speed: Option<f64>,
+    pub state: Option<PathBuf>,
+    pub mute: Option<bool>, // None 時使用設定檔
+    pub headless: bool,
+    pub frames: Option<u64>,
+    pub record: Option<PathBuf>,
+    pub play: Option<PathBuf>,
+    pub config: Option<PathBuf>,
+    pub screenshot_dir: Option<PathBuf>,
+    // 以下只能由設定檔指定
+    pub volume: Option<f32>,
+    pub key_bindings: KeyBindings, // 未列出的按鍵使用預設綁定
+    pub gamepad: GamepadConfig,
+    pub turbo: TurboConfig,
+    pub macros: MacroConfig,
+    pub speed_control: SpeedConfig,
+    pub rom: RomOptions,
+    pub debug: DebugOptions,
+}
+
+impl Options {
+    /// 以設定檔補上命令列未指定的選項 (個別遊戲設定優先於全域設定)
+    pub fn apply_config(&mut self, config: &Config, game: &GameConfig) {
+        self.scale = self.scale.or(game.scale).or(config.video.scale);
+        self.palette = self.palette.or(game.palette).or(config.video.palette);
+        self.model = self.model.or(game.model);
+        self.speed = self.speed.or(game.speed);
+        self.fullscreen = self.fullscreen.or(Some(config.video.fullscreen));
+        self.mute = self.mute.or(Some(!config.audio.enabled));
+        self.volume = self.volume.or(Some(config.audio.volume));
+        if self.rom.save_dir.is_none() {
+            self.rom.save_dir = config.paths.save_dir.clone();
+        }
+        if self.screenshot_dir.is_none() {
+            self.screenshot_dir = config.paths.screenshot_dir.clone();
+        }
+        self.key_bindings = config.key_bindings(game);
+        self.gamepad = config.gamepad.clone();
+        self.turbo = config.turbo.clone();
+        self.macros = config.macros.clone();
+        self.speed_control = config.speed.clone();
+    }
+
+    // 即時存檔路徑：未指定 --state 時與 ROM 同名
+    pub fn state_path(&self) -> PathBuf {
+        self.state
+            .clone()
+            .unwrap_or_else(|| PathBuf::from(&self.rom_path).with_extension("state"))
+    }
+}
+
+#[derive(Debug)]
+pub enum Parsed {
+    Run(Box<Options>),
+    Help,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CliError {
+    MissingValue {
+        option: String,
+        expected: &'static str,
+    },
+    InvalidValue {
+        option: String,
+        message: String,
+    },
+    UnknownOption(String),
+    MissingRom,
+    ExtraArgument(String),
+}
+
+impl std::fmt::Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::MissingValue { option, expected } => write!(f, "{} 需要{}", option, expected),
+            CliError::InvalidValue { option, message } => write!(f, "{}: {}", option, message),
+            CliError::UnknownOption(option) => write!(f, "未知的選項: {}", option),
+            CliError::MissingRom => write!(f, "未指定 ROM 路徑"),
+            CliError::ExtraArgument(arg) => write!(f, "多餘的參數: {}", arg),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+/// 解析程式名稱之後的參數
+pub fn parse<I>(args: I) -> Result<Parsed, CliError>
+where
+    I: IntoIterator<Item = String>,
+{
+    let mut options = Options::default();
+    let mut rom_path = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = |expected: &'static str| {
+            args.next().ok_or_else(|| CliError::MissingValue {
+                option: arg.clone(),
+                expected,
+            })
+        };
+        match arg.as_str() {
+            "-h" | "--help" => return Ok(Parsed::Help),
+            "--info" => options.info = true,
+            "--fullscreen" => options.fullscreen = Some(true),
+            "--windowed" => options.fullscreen = Some(false),
+            "--mute" => options.mute = Some(true),
+            "--no-mute" => options.mute = Some(false),
+            "--headless" => options.headless = true,
+            "--debug" => options.debug.repl = true,
+            "--debug-windows" => options.debug.debug_windows = true,
+            "--trace-labels" => options.debug.trace_labels = true,
+            "--scale" => {
+                let scale = value("放大倍率")?;
+                options.scale = Some(parse_in_range(&arg, &scale, 1..=16)?);
+            }
+            "--palette" => {
+                let palette = value("調色盤名稱")?;
+                options.palette = Some(parse_value(&arg, &palette)?);
+            }
+            "--model" => {
+                let model = value("機種名稱")?;
+                options.model = Some(parse_value(&arg, &model)?);
+            }
+            "--speed" => {
+                let speed = value("速度倍率")?;
+                options.speed = Some(parse_in_range(&arg, &speed, 0.1..=10.0)?);
+            }
+            "--frames" => {
+                let frames = value("幀數")?;
+                options.frames = Some(parse_in_range(&arg, &frames, 1..=u64::MAX)?);
+            }
+            "--gdb" => {
+                let port = value("有效的連接埠號")?;
+                options.debug.gdb_port = Some(parse_value(&arg, &port)?);
+            }
+            "--boot-rom" => options.boot_rom = Some(value("開機 ROM 路徑")?.into()),
+            "--state" => options.state = Some(value("即時存檔路徑")?.into()),
+            "--record" => options.record = Some(value("影片路徑")?.into()),
+            "--play" => options.play = Some(value("影片路徑")?.into()),
+            "--save-dir" => options.rom.save_dir = Some(value("存檔目錄")?.into()),
+            "--screenshot-dir" => options.screenshot_dir = Some(value("截圖目錄")?.into()),
+            "--config" => options.config = Some(value("設定檔路徑")?.into()),
+            "--patch" => options.rom.patch_path = Some(value("修補檔路徑")?.into()),
+            "--entry" => options.rom.entry = Some(value("壓縮檔內的檔名")?),
+            "--trace" => options.debug.trace_path = Some(value("輸出檔案路徑")?),
+            "--profile" => options.debug.profile_path = Some(value("輸出檔案路徑")?),
+            _ if arg.starts_with('-') && arg.len() > 1 => return Err(CliError::UnknownOption(arg)),
+            _ if rom_path.is_some() => return Err(CliError::ExtraArgument(arg)),
+            _ => rom_path = Some(arg),
+        }
+    }
+
+    options.rom_path = rom_path.ok_or(CliError::MissingRom)?;
+    // 影片自帶起始狀態
+    if options.play.is_some()
+        && (options.record.is_some() || options.state.is_some() || options.boot_rom.is_some())
+    {
+        return Err(CliError::InvalidValue {
+            option: "--play".to_string(),
+            message: "不能與 --record、--state 或 --boot-rom 同時使用".to_string(),
+        });
+    }
+    // 無視窗執行沒有關閉視窗的方式，必須有結束條件
+    if options.headless && options.frames.is_none() && options.play.is_none() {
+        return Err(CliError::InvalidValue {
+            option: "--headless".to_string(),
+            message: "需要 --frames 或 --play 指定何時結束".to_string(),
+        });
+    }
+    Ok(Parsed::Run(Box::new(options)))
+}
+
+fn parse_value<T>(option: &str, text: &str) -> Result<T, CliError>
+where
+    T: std::str::FromStr,
+    T::Err: std::fmt::Display,
+{
+    text.parse().map_err(|e: T::Err| CliError::InvalidValue {
+        option: option.to_string(),
+        message: e.to_string(),
+    })
+}
+
+fn parse_in_range<T>(
+    option: &str,
+    text: &str,
+    range: std::ops::RangeInclusive<T>,
+) -> Result<T, CliError>
+where
+    T: std::str::FromStr + PartialOrd + std::fmt::Display,
+    T::Err: std::fmt::Display,
+{
+    let value: T = parse_value(option, text)?;
+    if range.contains(&value) {
+        Ok(value)
+    } else {
+        Err(CliError::InvalidValue {
+            option: option.to_string(),
+            message: format!("{} 超出範圍 {}-{}", text, range.start(), range.end()),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_args(args: &str) -> Result<Parsed, CliError> {
+        parse(args.split_whitespace().map(String::from))
+    }
+
+    fn options(args: &str) -> Options {
+        match parse_args(args) {
+            Ok(Parsed::Run(options)) => *options,
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parses_frontend_and_emulation_flags() {
+        let options = options(
+            "--scale 3 --fullscreen --palette green --model mgb --boot-rom dmg.bin \
+             --speed 1.5 --state a.state --mute --save-dir saves game.gb",
+        );
+        assert_eq!(options.rom_path, "game.gb");
+        assert_eq!(options.scale, Some(3));
+        assert_eq!(options.fullscreen, Some(true));
+        assert_eq!(options.mute, Some(true));
+        assert!(!options.headless);
+        assert_eq!(options.palette, Some(Palette::GREEN));
+        assert_eq!(options.model, Some(Model::Mgb));
+        assert_eq!(options.boot_rom, Some(PathBuf::from("dmg.bin")));
+        assert_eq!(options.speed, Some(1.5));
+        assert_eq!(options.state_path(), PathBuf::from("a.state"));
+        assert_eq!(options.rom.save_dir, Some(PathBuf::from("saves")));
+    }
+
+    #[test]
+    fn parses_headless_and_debug_flags() {
+        let options = options(
+            "--headless --frames 600 --trace out.log --trace-labels --gdb 2345 game.zip --entry a.gb",
+        );
+        assert!(options.headless);
+        assert_eq!(options.frames, Some(600));
+        assert_eq!(options.debug.trace_path.as_deref(), Some("out.log"));
+        assert!(options.debug.trace_labels);
+        assert_eq!(options.debug.gdb_port, Some(2345));
+        assert_eq!(options.rom.entry.as_deref(), Some("a.gb"));
+        assert_eq!(options.state_path(), PathBuf::from("game.state"));
+    }
+
+    #[test]
+    fn command_line_overrides_config() {
+        let config: Config = toml::from_str(
+            "[video]\nscale = 2\npalette = \"green\"\n[audio]\nenabled = false\n[paths]\nsave_dir = \"saves\"\nscreenshot_dir = \"shots\"",
+        )
+        .unwrap();
+        let game = GameConfig {
+            palette: Some(Palette::POCKET),
+            ..GameConfig::default()
+        };
+        let mut options = options("--scale 4 --save-dir mine --screenshot-dir pics game.gb");
+        options.apply_config(&config, &game);
+        assert_eq!(options.scale, Some(4));
+        assert_eq!(options.palette, Some(Palette::POCKET));
+        assert_eq!(options.fullscreen, Some(false));
+        assert_eq!(options.mute, Some(true));
+        assert_eq!(options.volume, Some(1.0));
+        assert_eq!(options.rom.save_dir, Some(PathBuf::from("mine")));
+        assert_eq!(options.screenshot_dir, Some(PathBuf::from("pics")));
+
+        // 設定檔開啟的選項也能從命令列關閉
+        let config: Config =
+            toml::from_str("[video]\nfullscreen = true\n[audio]\nenabled = false").unwrap();
+        let mut overridden = self::options("--windowed --no-mute game.gb");
+        overridden.apply_config(&config, &GameConfig::default());
+        assert_eq!(overridden.fullscreen, Some(false));
+        assert_eq!(overridden.mute, Some(false));
+    }
+
+    #[test]
+    fn reports_invalid_arguments() {
+        assert!(matches!(parse_args("game.gb --help"), Ok(Parsed::Help)));
+        assert_eq!(parse_args("--mute").unwrap_err(), CliError::MissingRom);
+        assert_eq!(
+            parse_args("game.gb --scale").unwrap_err(),
+            CliError::MissingValue {
+                option: "--scale".to_string(),
+                expected: "放大倍率"
+            }
+        );
+        assert!(matches!(
+            parse_args("--scale 0 game.gb"),
+            Err(CliError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse_args("--model cgb game.gb"),
+            Err(CliError::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            parse_args("--headless game.gb"),
+            Err(CliError::InvalidValue { .. })
+        ));
+        assert!(options("--headless --play run.gbm game.gb").headless);
+        assert_eq!(
+            parse_args("--turbo game.gb").unwrap_err(),
+            CliError::UnknownOption("--turbo".to_string())
+        );
+        let recording = options("--record run.gbm --state a.state game.gb");
+        assert_eq!(recording.record, Some(PathBuf::from("run.gbm")));
+        assert!(matches!(
+            parse_args("--play run.gbm --state a.state game.gb"),
+            Err(CliError::InvalidValue { .. })
+        ));
+        assert_eq!(
+            parse_args("a.gb b.gb").unwrap_err(),
+            CliError::ExtraArgument("b.gb".to_string())
+        );
+    }
+}
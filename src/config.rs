@@ -0,0 +1,545 @@
+//! 設定檔
+//!
+//! 預設位置為 `$XDG_CONFIG_HOME/rust_gb/config.toml` (未設定時為 `~/.config/rust_gb`，Windows 為
+//! `%APPDATA%\rust_gb`)，也接受同目錄的 `config.json`。檔案不存在時使用預設值。
+//! 優先順序：命令列參數 > 個別遊戲設定 (`[games.checksum."全域檢查碼"]` > `[games.title."標題"]`) > 全域設定。
+
+use crate::cartridge::CartridgeHeader;
+use crate::gameboy::Model;
+use crate::joypad::{DEFAULT_KEYBOARD, JoypadKey};
+use crate::palette::Palette;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// 按鍵綁定：Game Boy 按鍵對應的 SDL 按鍵名稱 (例如 `a = ["Z", "J"]`)
+pub type KeyBindings = HashMap<JoypadKey, Vec<String>>;
+
+/// 固定的快速鍵：結束、儲存與載入即時存檔、螢幕截圖
+const FIXED_HOTKEYS: [(&str, &str); 4] = [
+    ("Escape", "結束"),
+    ("F5", "儲存即時存檔"),
+    ("F8", "載入即時存檔"),
+    ("F12", "螢幕截圖"),
+];
+
+/// 手把按鈕綁定：Game Boy 按鍵對應的 SDL 手把按鈕名稱 (例如 `a = ["b"]`、`up = ["dpup"]`)
+pub type ButtonBindings = HashMap<JoypadKey, Vec<String>>;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub keys: KeyBindings,
+    pub gamepad: GamepadConfig,
+    pub turbo: TurboConfig,
+    pub macros: MacroConfig,
+    pub speed: SpeedConfig,
+    pub video: VideoConfig,
+    pub audio: AudioConfig,
+    pub paths: PathsConfig,
+    pub games: GamesConfig,
+}
+
+/// 個別遊戲設定：依卡帶標題 (不分大小寫) 或全域檢查碼 (十六進位，可加 `0x` 前綴) 比對
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GamesConfig {
+    pub title: HashMap<String, GameConfig>,
+    pub checksum: HashMap<String, GameConfig>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VideoConfig {
+    pub scale: Option<u32>,
+    pub fullscreen: bool,
+    pub palette: Option<Palette>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AudioConfig {
+    pub enabled: bool,
+    pub volume: f32, // 0.0-1.0
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        AudioConfig {
+            enabled: true,
+            volume: 1.0,
+        }
+    }
+}
+
+/// 手把設定，所有連接的手把共用
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GamepadConfig {
+    pub enabled: bool,
+    pub buttons: ButtonBindings,
+    pub analog: bool,  // 左類比搖桿當作方向鍵
+    pub deadzone: f32, // 0.0-1.0，搖桿偏移小於此比例時視為放開
+}
+
+impl Default for GamepadConfig {
+    fn default() -> Self {
+        GamepadConfig {
+            enabled: true,
+            buttons: ButtonBindings::new(),
+            analog: true,
+            deadzone: 0.3,
+        }
+    }
+}
+
+/// 連發按鍵：按住時每隔 `rate` 幀切換一次按下/放開
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TurboConfig {
+    pub rate: u32,
+    pub keys: KeyBindings,
+    pub buttons: ButtonBindings,
+}
+
+impl Default for TurboConfig {
+    fn default() -> Self {
+        TurboConfig {
+            rate: 2,
+            keys: KeyBindings::from([
+                (JoypadKey::A, vec!["A".to_string()]),
+                (JoypadKey::B, vec!["S".to_string()]),
+            ]),
+            buttons: ButtonBindings::from([
+                (JoypadKey::A, vec!["y".to_string()]),
+                (JoypadKey::B, vec!["x".to_string()]),
+            ]),
+        }
+    }
+}
+
+/// 巨集按鍵：按下播放，Ctrl+按鍵開始/停止錄製
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct MacroConfig {
+    pub keys: Vec<String>,
+}
+
+impl Default for MacroConfig {
+    fn default() -> Self {
+        MacroConfig {
+            keys: ["1", "2", "3", "4"].map(String::from).to_vec(),
+        }
+    }
+}
+
+/// 快轉與慢動作：按住或切換快轉，慢動作依序切換 50%、25%、關閉
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SpeedConfig {
+    pub fast_forward: f64, // 快轉倍率，0 表示不限速
+    pub hold_key: String,
+    pub toggle_key: String,
+    pub slow_key: String,
+}
+
+impl Default for SpeedConfig {
+    fn default() -> Self {
+        SpeedConfig {
+            fast_forward: 0.0,
+            hold_key: "Tab".to_string(),
+            toggle_key: "`".to_string(),
+            slow_key: "Backspace".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PathsConfig {
+    pub save_dir: Option<PathBuf>,
+    pub screenshot_dir: Option<PathBuf>,
+}
+
+/// 個別遊戲的設定，覆蓋全域設定
+#[derive(Debug, Default, Clone, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GameConfig {
+    pub keys: KeyBindings,
+    pub scale: Option<u32>,
+    pub palette: Option<Palette>,
+    pub model: Option<Model>,
+    pub speed: Option<f64>,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    Toml {
+        path: PathBuf,
+        source: toml::de::Error,
+    },
+    Json {
+        path: PathBuf,
+        source: serde_json::Error,
+    },
+    Invalid(String),
+    UnknownKey(String),
+    UnknownButton(String),
+}
+
+impl std::fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ConfigError::Io { path, source } => {
+                write!(f, "無法讀取設定檔 {}: {}", path.display(), source)
+            }
+            ConfigError::Toml { path, source } => {
+                write!(f, "設定檔 {} 格式錯誤: {}", path.display(), source)
+            }
+            ConfigError::Json { path, source } => {
+                write!(f, "設定檔 {} 格式錯誤: {}", path.display(), source)
+            }
+            ConfigError::Invalid(msg) => write!(f, "設定值無效: {}", msg),
+            ConfigError::UnknownKey(name) => write!(f, "設定檔中未知的按鍵名稱: {}", name),
+            ConfigError::UnknownButton(name) => write!(f, "設定檔中未知的手把按鈕名稱: {}", name),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ConfigError::Io { source, .. } => Some(source),
+            ConfigError::Toml { source, .. } => Some(source),
+            ConfigError::Json { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// 設定檔所在的目錄
+pub fn config_dir() -> Option<PathBuf> {
+    let base = if cfg!(windows) {
+        std::env::var_os("APPDATA").map(PathBuf::from)
+    } else {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+    };
+    base.map(|dir| dir.join("rust_gb"))
+}
+
+/// 預設設定檔：`config.toml` 優先，其次 `config.json`
+pub fn default_path() -> Option<PathBuf> {
+    let dir = config_dir()?;
+    ["config.toml", "config.json"]
+        .iter()
+        .map(|name| dir.join(name))
+        .find(|path| path.exists())
+}
+
+impl Config {
+    /// 依副檔名以 TOML 或 JSON 解析
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Io {
+            path: path.to_path_buf(),
+            source,
+        })?;
+        let config: Config = if path.extension().is_some_and(|ext| ext == "json") {
+            serde_json::from_str(&text).map_err(|source| ConfigError::Json {
+                path: path.to_path_buf(),
+                source,
+            })?
+        } else {
+            toml::from_str(&text).map_err(|source| ConfigError::Toml {
+                path: path.to_path_buf(),
+                source,
+            })?
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    // 個別遊戲設定的表格名稱與內容
+    fn game_entries(&self) -> impl Iterator<Item = (String, &GameConfig)> {
+        let titles = self.games.title.iter();
+        let checksums = self.games.checksum.iter();
+        titles
+            .map(|(name, game)| (format!("games.title.{}", name), game))
+            .chain(checksums.map(|(name, game)| (format!("games.checksum.{}", name), game)))
+    }
+
+    // 數值範圍與命令列參數相同；個別遊戲的鍵必須唯一對應一個標題或檢查碼
+    fn validate(&self) -> Result<(), ConfigError> {
+        let scales = std::iter::once(("video.scale".to_string(), self.video.scale)).chain(
+            self.game_entries()
+                .map(|(name, game)| (format!("{}.scale", name), game.scale)),
+        );
+        for (key, scale) in scales {
+            if scale.is_some_and(|scale| !(1..=16).contains(&scale)) {
+                return Err(ConfigError::Invalid(format!("{} 應為 1-16", key)));
+            }
+        }
+        for (name, game) in self.game_entries() {
+            if game
+                .speed
+                .is_some_and(|speed| !(0.1..=10.0).contains(&speed))
+            {
+                return Err(ConfigError::Invalid(format!("{}.speed 應為 0.1-10", name)));
+            }
+        }
+        let mut titles = std::collections::HashSet::new();
+        for name in self.games.title.keys() {
+            if !titles.insert(name.to_ascii_uppercase()) {
+                return Err(ConfigError::Invalid(format!(
+                    "games.title 中的 {} 與其他標題只差在大小寫",
+                    name
+                )));
+            }
+        }
+        let mut checksums = std::collections::HashSet::new();
+        for name in self.games.checksum.keys() {
+            let Some(checksum) = parse_checksum(name) else {
+                return Err(ConfigError::Invalid(format!(
+                    "games.checksum.{} 應為 4 位十六進位的全域檢查碼",
+                    name
+                )));
+            };
+            if !checksums.insert(checksum) {
+                return Err(ConfigError::Invalid(format!(
+                    "games.checksum 中的 {} 重複",
+                    name
+                )));
+            }
+        }
+        if !(0.0..=1.0).contains(&self.audio.volume) {
+            return Err(ConfigError::Invalid(
+                "audio.volume 應為 0.0-1.0".to_string(),
+            ));
+        }
+        let fast_forward = self.speed.fast_forward;
+        if fast_forward != 0.0 && !(fast_forward > 1.0 && fast_forward <= 16.0) {
+            return Err(ConfigError::Invalid(
+                "speed.fast_forward 應為 0 (不限速) 或 1-16".to_string(),
+            ));
+        }
+        if !(1..=30).contains(&self.turbo.rate) {
+            return Err(ConfigError::Invalid("turbo.rate 應為 1-30".to_string()));
+        }
+        if !(0.0..1.0).contains(&self.gamepad.deadzone) {
+            return Err(ConfigError::Invalid(
+                "gamepad.deadzone 應為 0.0-1.0 (不含 1.0)".to_string(),
+            ));
+        }
+        self.check_hotkey_overlap()
+    }
+
+    // 快速鍵以按鍵碼、Game Boy 按鍵以掃描碼處理，兩邊各自檢查不到重疊，這裡以 SDL 按鍵名稱比對
+    fn check_hotkey_overlap(&self) -> Result<(), ConfigError> {
+        // 未被設定檔取代的預設綁定、全域與個別遊戲的綁定，以及連發按鍵
+        let mut joypad: Vec<(JoypadKey, &str)> = DEFAULT_KEYBOARD
+            .into_iter()
+            .filter(|(_, _, key)| !self.keys.contains_key(key))
+            .map(|(_, name, key)| (key, name))
+            .collect();
+        let games = self.game_entries().map(|(_, game)| &game.keys);
+        for bindings in [&self.keys, &self.turbo.keys].into_iter().chain(games) {
+            for (&key, names) in bindings {
+                joypad.extend(names.iter().map(|name| (key, name.as_str())));
+            }
+        }
+
+        let macros = self.macros.keys.iter().map(|name| (name.as_str(), "巨集"));
+        let speed = [
+            (self.speed.hold_key.as_str(), "speed.hold_key"),
+            (self.speed.toggle_key.as_str(), "speed.toggle_key"),
+            (self.speed.slow_key.as_str(), "speed.slow_key"),
+        ];
+        let hotkeys = FIXED_HOTKEYS.into_iter().chain(macros).chain(speed);
+        for (hotkey, action) in hotkeys {
+            if let Some((key, _)) = joypad
+                .iter()
+                .find(|(_, name)| name.eq_ignore_ascii_case(hotkey))
+            {
+                return Err(ConfigError::Invalid(format!(
+                    "按鍵 {} 同時綁定到 {} 與 Game Boy 的 {:?} 鍵",
+                    hotkey, action, key
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// 符合 ROM 的個別遊戲設定：標題與全域檢查碼都符合時，檢查碼的設定優先
+    pub fn game(&self, header: &CartridgeHeader) -> GameConfig {
+        let by_title = self
+            .games
+            .title
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(&header.title))
+            .map(|(_, game)| game);
+        let by_checksum = self
+            .games
+            .checksum
+            .iter()
+            .find(|(name, _)| parse_checksum(name) == Some(header.global_checksum))
+            .map(|(_, game)| game);
+
+        let mut game = GameConfig::default();
+        for config in by_title.into_iter().chain(by_checksum) {
+            game.keys.extend(config.keys.clone());
+            game.scale = config.scale.or(game.scale);
+            game.palette = config.palette.or(game.palette);
+            game.model = config.model.or(game.model);
+            game.speed = config.speed.or(game.speed);
+        }
+        game
+    }
+
+    /// 全域按鍵綁定加上個別遊戲的覆蓋 (以 Game Boy 按鍵為單位取代)
+    pub fn key_bindings(&self, game: &GameConfig) -> KeyBindings {
+        let mut keys = self.keys.clone();
+        keys.extend(game.keys.clone());
+        keys
+    }
+}
+
+// 十六進位的全域檢查碼，可加 0x 或 0X 前綴
+fn parse_checksum(text: &str) -> Option<u16> {
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+    if digits.is_empty() || digits.len() > 4 {
+        return None;
+    }
+    u16::from_str_radix(digits, 16).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLE: &str = r#"
+        [keys]
+        a = ["Z", "J"]
+        start = ["Return"]
+
+        [video]
+        scale = 3
+        palette = "green"
+
+        [audio]
+        volume = 0.5
+
+        [gamepad]
+        buttons = { a = ["a"], b = ["b", "x"] }
+        deadzone = 0.5
+
+        [paths]
+        save_dir = "saves"
+
+        [games.title.TETRIS]
+        palette = "pocket"
+        scale = 3
+        keys = { a = ["K"] }
+
+        [games.checksum."0X16BF"]
+        scale = 2
+    "#;
+
+    fn header(title: &str, global_checksum: u16) -> CartridgeHeader {
+        let mut rom = vec![0u8; 0x150];
+        rom[0x134..0x134 + title.len()].copy_from_slice(title.as_bytes());
+        rom[0x14E..0x150].copy_from_slice(&global_checksum.to_be_bytes());
+        CartridgeHeader::parse(&rom).unwrap()
+    }
+
+    #[test]
+    fn parses_toml_and_json() {
+        let config: Config = toml::from_str(SAMPLE).unwrap();
+        assert_eq!(config.keys[&JoypadKey::A], ["Z", "J"]);
+        assert_eq!(config.video.scale, Some(3));
+        assert_eq!(config.video.palette, Some(Palette::GREEN));
+        assert!(config.audio.enabled);
+        assert_eq!(config.audio.volume, 0.5);
+        assert_eq!(config.paths.save_dir, Some(PathBuf::from("saves")));
+        assert_eq!(config.gamepad.buttons[&JoypadKey::B], ["b", "x"]);
+        assert_eq!(config.gamepad.deadzone, 0.5);
+        assert!(config.gamepad.enabled && config.gamepad.analog);
+
+        let json: Config = serde_json::from_str(r#"{"keys": {"select": ["Tab"]}}"#).unwrap();
+        assert_eq!(json.keys[&JoypadKey::Select], ["Tab"]);
+        assert!(toml::from_str::<Config>("[video]\nzoom = 2").is_err());
+        assert!(toml::from_str::<Config>("[keys]\nturbo = [\"T\"]").is_err());
+    }
+
+    #[test]
+    fn merges_game_overrides_by_title_and_checksum() {
+        let config: Config = toml::from_str(SAMPLE).unwrap();
+
+        let game = config.game(&header("Tetris", 0x16BF));
+        assert_eq!(game.palette, Some(Palette::POCKET));
+        assert_eq!(game.scale, Some(2));
+        let keys = config.key_bindings(&game);
+        assert_eq!(keys[&JoypadKey::A], ["K"]);
+        assert_eq!(keys[&JoypadKey::Start], ["Return"]);
+
+        assert_eq!(config.game(&header("ZELDA", 0x1234)), GameConfig::default());
+        assert_eq!(config.game(&header("TETRIS", 0x1234)).scale, Some(3));
+        assert_eq!(config.game(&header("ZELDA", 0x16BF)).scale, Some(2));
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_ambiguous_game_keys() {
+        let config: Config = toml::from_str("[games.title.Tetris]\n[games.title.TETRIS]").unwrap();
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+        let config: Config =
+            toml::from_str("[games.checksum.16BF]\n[games.checksum.0x16bf]").unwrap();
+        assert!(config.validate().is_err());
+        let config: Config = toml::from_str("[games.checksum.TETRIS]").unwrap();
+        assert!(config.validate().is_err());
+        assert!(toml::from_str::<Config>("[games.TETRIS]\nscale = 2").is_err());
+    }
+
+    #[test]
+    fn rejects_out_of_range_values() {
+        let config: Config = toml::from_str("[video]\nscale = 40").unwrap();
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+        let config: Config = toml::from_str("[audio]\nvolume = 2.0").unwrap();
+        assert!(config.validate().is_err());
+        let config: Config = toml::from_str("[gamepad]\ndeadzone = 1.0").unwrap();
+        assert!(config.validate().is_err());
+        let config: Config = toml::from_str("[turbo]\nrate = 0").unwrap();
+        assert!(config.validate().is_err());
+        let config: Config = toml::from_str("[speed]\nfast_forward = 0.5").unwrap();
+        assert!(config.validate().is_err());
+        let config: Config = toml::from_str("[speed]\nfast_forward = 4").unwrap();
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn rejects_hotkeys_bound_to_joypad_keys() {
+        assert!(Config::default().validate().is_ok());
+        // 與預設綁定 (Z = A) 重疊
+        let config: Config = toml::from_str("[macros]\nkeys = [\"z\"]").unwrap();
+        assert!(matches!(config.validate(), Err(ConfigError::Invalid(_))));
+        // 取代預設綁定後就能使用
+        let config: Config =
+            toml::from_str("[keys]\na = [\"K\"]\n[macros]\nkeys = [\"Z\"]").unwrap();
+        assert!(config.validate().is_ok());
+        let config: Config = toml::from_str("[keys]\nselect = [\"Tab\"]").unwrap();
+        assert!(config.validate().is_err());
+        let config: Config = toml::from_str("[turbo]\nkeys = { a = [\"F5\"] }").unwrap();
+        assert!(config.validate().is_err());
+        let config: Config =
+            toml::from_str("[games.title.TETRIS]\nkeys = { b = [\"Backspace\"] }").unwrap();
+        assert!(config.validate().is_err());
+    }
+}
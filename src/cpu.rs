@@ -1,11 +1,30 @@
+use crate::interrupt::InterruptType;
+use crate::mmu::Memory;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
 use std::sync::LazyLock;
 
-/// 全域操作碼快取 (Rust 1.80.0+)
-pub static OPCODES: LazyLock<Opcodes> =
-    LazyLock::new(|| load_opcodes().expect("無法載入 Opcodes.json 檔案。請確保檔案存在且格式正確。"));
+// Opcodes.json 的載入結果，只讀取一次
+static LOADED_OPCODES: LazyLock<Result<Opcodes, String>> =
+    LazyLock::new(|| load_opcodes().map_err(|e| format!("Opcodes.json: {}", e)));
+
+/// 載入操作碼表，失敗時回傳錯誤訊息而不是 panic
+/// 前端啟動時先呼叫此函式，把錯誤往上傳遞
+pub fn opcodes() -> Result<&'static Opcodes, &'static str> {
+    LOADED_OPCODES.as_ref().map_err(String::as_str)
+}
+
+/// 查詢單一操作碼；操作碼表無法載入時與未定義的操作碼一樣回傳 None
+pub fn opcode(cb_prefixed: bool, code: u8) -> Option<&'static Opcode> {
+    let table = opcodes().ok()?;
+    let entries = if cb_prefixed {
+        &table.cbprefixed
+    } else {
+        &table.unprefixed
+    };
+    entries.get(code as usize)?.as_ref()
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct Operand {
@@ -49,14 +68,14 @@ pub struct Opcodes {
 }
 
 /// CPU 運行狀態
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum CpuState {
     Running, // 正常運行
     Halted,  // 暫停 (HALT)
 }
 
 /// 中斷主啟用狀態 (IME)
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum InterruptMasterState {
     Disabled, // 已禁用
     Pending,  // 準備啟用 (EI 指令後的延遲週期)
@@ -64,13 +83,14 @@ pub enum InterruptMasterState {
 }
 
 /// 旗標狀態
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum FlagState {
     Set,
     Clear,
 }
 
 // CPU 結構 - 包含 MMU
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Cpu {
     pub pc: u16,                   // 程式計數器
     pub sp: u16,                   // 堆疊指標
@@ -83,7 +103,7 @@ pub struct Cpu {
     pub halt_bug: bool,            // HALT bug 標誌：下一次 fetch 不增加 PC
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Registers {
     pub a: u8,
     pub f: u8,
@@ -95,7 +115,7 @@ pub struct Registers {
     pub l: u8,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CpuFlags {
     pub z: FlagState, // Zero
     pub n: FlagState, // Negative
@@ -133,7 +153,7 @@ impl Cpu {
     }
 
     // 讀取下一個位元組並前進 PC
-    pub fn fetch_byte(&mut self, mmu: &mut crate::mmu::Mmu) -> u8 {
+    pub fn fetch_byte<M: Memory>(&mut self, mmu: &mut M) -> u8 {
         let byte = mmu.read_byte(self.pc);
         // HALT bug: 如果設置了 halt_bug 標誌，不遞增 PC
         if self.halt_bug {
@@ -145,64 +165,44 @@ impl Cpu {
     }
 
     // 讀取下一個字並前進 PC
-    pub fn fetch_word(&mut self, mmu: &mut crate::mmu::Mmu) -> u16 {
+    pub fn fetch_word<M: Memory>(&mut self, mmu: &mut M) -> u16 {
         let word = mmu.read_word(self.pc);
         self.pc = self.pc.wrapping_add(2);
         word
     }
 
     // 堆疊操作
-    pub fn push_word(&mut self, mmu: &mut crate::mmu::Mmu, value: u16) {
-        self.sp = self.sp.wrapping_sub(2);
-        mmu.write_word(self.sp, value);
+    // 與硬體相同，先寫入高位元組 (SP-1)，再寫入低位元組 (SP-2)
+    pub fn push_word<M: Memory>(&mut self, mmu: &mut M, value: u16) {
+        self.sp = self.sp.wrapping_sub(1);
+        mmu.write_byte(self.sp, (value >> 8) as u8);
+        self.sp = self.sp.wrapping_sub(1);
+        mmu.write_byte(self.sp, value as u8);
     }
 
-    pub fn pop_word(&mut self, mmu: &crate::mmu::Mmu) -> u16 {
+    pub fn pop_word<M: Memory>(&mut self, mmu: &mut M) -> u16 {
         let value = mmu.read_word(self.sp);
         self.sp = self.sp.wrapping_add(2);
         value
     }
 
     // 執行一個指令
-    pub fn step(&mut self, mmu: &mut crate::mmu::Mmu) -> u32 {
+    pub fn step<M: Memory>(&mut self, mmu: &mut M) -> u32 {
         // --- 處理中斷 ---
-        let ie = mmu.read_byte(0xFFFF);
-        let mut iff = mmu.read_byte(0xFF0F);
-        let interrupts = ie & iff & 0x1F;
-
-        if interrupts != 0 {
-            // 只要有中斷就會喚醒 CPU
-            self.state = CpuState::Running;
+        let pending = mmu.read_internal(0xFFFF) & mmu.read_internal(0xFF0F) & 0x1F;
 
-            if self.ime == InterruptMasterState::Enabled {
-                self.ime = InterruptMasterState::Disabled;
-
-                // 找到最高優先級的中斷
-                let interrupt_bit = interrupts.trailing_zeros() as u8;
-                let vector = match interrupt_bit {
-                    0 => 0x40, // VBlank
-                    1 => 0x48, // LCD STAT
-                    2 => 0x50, // Timer
-                    3 => 0x58, // Serial
-                    4 => 0x60, // Joypad
-                    _ => unreachable!(),
-                };
-
-                // 清除對應的中斷標誌
-                iff &= !(1 << interrupt_bit);
-                mmu.write_byte(0xFF0F, iff);
-
-                // 推入當前 PC 到堆疊並跳轉
-                let current_pc = self.pc;
-                self.push_word(mmu, current_pc);
-                self.pc = vector as u16;
-
-                return 20; // 中斷處理固定消耗 20 週期
+        if self.state == CpuState::Halted {
+            if pending == 0 {
+                return 4; // Halted 時每個指令週期消耗 4 週期
             }
+            // 有中斷待處理時喚醒 CPU，離開 HALT 需額外 1 個 M-cycle；
+            // 若 IME 已啟用，下一次 step 才進行中斷分派
+            self.state = CpuState::Running;
+            return 4;
         }
 
-        if self.state == CpuState::Halted {
-            return 4; // Halted 時每個指令週期消耗 4 週期
+        if pending != 0 && self.ime == InterruptMasterState::Enabled {
+            return self.dispatch_interrupt(mmu);
         }
 
         // 處理 EI 延遲生效：在 EI 指令之後的一個指令週期後啟用 IME
@@ -218,12 +218,12 @@ impl Cpu {
         let first_byte = self.fetch_byte(mmu);
         let pc_before = self.pc.wrapping_sub(1);
 
-        // 查找操作碼 (直接引用全域靜態變數，避免借用 mmu)
+        // 查找操作碼 (引用全域操作碼表，避免借用 mmu)
         let opcode_opt = if first_byte == 0xCB {
             let second_byte = self.fetch_byte(mmu);
-            OPCODES.cbprefixed[second_byte as usize].as_ref()
+            opcode(true, second_byte)
         } else {
-            OPCODES.unprefixed[first_byte as usize].as_ref()
+            opcode(false, first_byte)
         };
 
         // 跟蹤指令計數器 - 全局可訪問
@@ -312,6 +312,35 @@ impl Cpu {
         }
     }
 
+    // 中斷分派，共 5 個 M-cycle：
+    // M1-M2 內部延遲 (SP 遞減)，M3 推入 PC 高位元組，M4 推入 PC 低位元組，
+    // M5 跳轉到向量。中斷來源在推入高位元組之後才決定，因此若高位元組
+    // 寫入 IE (SP=0x0000) 使待處理中斷消失，分派會被取消並跳到 0x0000。
+    fn dispatch_interrupt<M: Memory>(&mut self, mmu: &mut M) -> u32 {
+        self.ime = InterruptMasterState::Disabled;
+
+        let pc = self.pc;
+        self.sp = self.sp.wrapping_sub(1);
+        mmu.write_byte(self.sp, (pc >> 8) as u8);
+
+        let iff = mmu.read_internal(0xFF0F);
+        let pending = mmu.read_internal(0xFFFF) & iff & 0x1F;
+
+        self.sp = self.sp.wrapping_sub(1);
+        mmu.write_byte(self.sp, pc as u8);
+
+        if pending == 0 {
+            self.pc = 0x0000;
+        } else {
+            // 找到最高優先級的中斷並清除對應的中斷標誌
+            let interrupt = InterruptType::ALL[pending.trailing_zeros() as usize];
+            mmu.write_internal(0xFF0F, iff & !interrupt.mask());
+            self.pc = interrupt.vector();
+        }
+
+        20
+    }
+
     // 寄存器訪問方法
     pub fn get_af(&self) -> u16 {
         ((self.registers.a as u16) << 8) | (self.f() as u16)
@@ -533,4 +562,85 @@ pub fn load_opcodes() -> std::io::Result<Opcodes> {
         unprefixed,
         cbprefixed,
     })
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Cpu, CpuState, InterruptMasterState};
+    use crate::mmu::Mmu;
+
+    fn cpu_with_pending(mmu: &mut Mmu, pc: u16, sp: u16, ie: u8, iff: u8) -> Cpu {
+        let mut cpu = Cpu::new();
+        cpu.pc = pc;
+        cpu.sp = sp;
+        cpu.ime = InterruptMasterState::Enabled;
+        mmu.write_byte(0xFFFF, ie);
+        mmu.write_byte(0xFF0F, iff);
+        cpu
+    }
+
+    #[test]
+    fn dispatch_pushes_pc_and_jumps_to_vector() {
+        let mut mmu = Mmu::new();
+        let mut cpu = cpu_with_pending(&mut mmu, 0xC123, 0xD000, 0x04, 0x04);
+
+        assert_eq!(cpu.step(&mut mmu), 20);
+        assert_eq!(cpu.pc, 0x0050);
+        assert_eq!(cpu.sp, 0xCFFE);
+        assert_eq!(mmu.read_byte(0xCFFF), 0xC1);
+        assert_eq!(mmu.read_byte(0xCFFE), 0x23);
+        assert_eq!(mmu.read_byte(0xFF0F), 0xE0);
+        assert_eq!(cpu.ime, InterruptMasterState::Disabled);
+    }
+
+    #[test]
+    fn ie_overwritten_by_push_cancels_dispatch() {
+        let mut mmu = Mmu::new();
+        // SP=0x0000：PC 高位元組 0x02 會寫入 IE，使 VBlank 不再啟用
+        let mut cpu = cpu_with_pending(&mut mmu, 0x0234, 0x0000, 0x01, 0x01);
+
+        assert_eq!(cpu.step(&mut mmu), 20);
+        assert_eq!(cpu.pc, 0x0000);
+        assert_eq!(mmu.read_byte(0xFFFF), 0x02);
+        assert_eq!(mmu.read_byte(0xFFFE), 0x34);
+        assert_eq!(mmu.read_byte(0xFF0F), 0xE1);
+    }
+
+    #[test]
+    fn ie_overwritten_by_push_selects_remaining_interrupt() {
+        let mut mmu = Mmu::new();
+        // 高位元組 0x04 只保留 Timer，應改為分派 Timer 並保留 VBlank 標誌
+        let mut cpu = cpu_with_pending(&mut mmu, 0x0456, 0x0000, 0x05, 0x05);
+
+        assert_eq!(cpu.step(&mut mmu), 20);
+        assert_eq!(cpu.pc, 0x0050);
+        assert_eq!(mmu.read_byte(0xFF0F), 0xE1);
+    }
+
+    #[test]
+    fn halt_wake_up_takes_an_extra_m_cycle() {
+        let mut mmu = Mmu::new();
+        let mut cpu = cpu_with_pending(&mut mmu, 0xC000, 0xD000, 0x01, 0x01);
+        cpu.state = CpuState::Halted;
+
+        assert_eq!(cpu.step(&mut mmu), 4);
+        assert_eq!(cpu.state, CpuState::Running);
+        assert_eq!(cpu.pc, 0xC000);
+        assert_eq!(cpu.step(&mut mmu), 20);
+        assert_eq!(cpu.pc, 0x0040);
+    }
+
+    #[test]
+    fn halt_with_ime_disabled_resumes_after_wake_up() {
+        let mut mmu = Mmu::new();
+        let mut cpu = cpu_with_pending(&mut mmu, 0xC000, 0xD000, 0x01, 0x01);
+        cpu.ime = InterruptMasterState::Disabled;
+        cpu.state = CpuState::Halted;
+
+        assert_eq!(cpu.step(&mut mmu), 4);
+        // WRAM 內容為 0x00 (NOP)，喚醒後繼續執行下一個指令且不清除 IF
+        assert_eq!(cpu.step(&mut mmu), 4);
+        assert_eq!(cpu.pc, 0xC001);
+        assert_eq!(mmu.read_byte(0xFF0F), 0xE1);
+    }
+}
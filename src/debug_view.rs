@@ -0,0 +1,397 @@
+//! 除錯檢視
+//!
+//! 將記憶體、VRAM 圖塊、背景圖與 OAM 轉成 RGBA 影像，供前端的除錯視窗顯示。
+//! 這裡不依賴 SDL；讀取一律經由 `Mmu::read_byte_ppu` 與 `Ppu` 欄位，不影響模擬狀態。
+
+use crate::mmu::Mmu;
+use crate::ppu::{Ppu, Sprite};
+
+/// 與主畫面相同的四階灰度
+pub const SHADES: [[u8; 4]; 4] = [
+    [255, 255, 255, 255],
+    [170, 170, 170, 255],
+    [85, 85, 85, 255],
+    [0, 0, 0, 255],
+];
+
+const BACKGROUND: [u8; 4] = [32, 32, 48, 255];
+const TEXT: [u8; 4] = [224, 224, 224, 255];
+const VIEWPORT: [u8; 4] = [255, 48, 48, 255]; // 目前使用中的背景圖
+const VIEWPORT_INACTIVE: [u8; 4] = [64, 128, 255, 255];
+
+/// 字元格大小 (3x5 字形加 1 像素間距)
+pub const CHAR_WIDTH: usize = 4;
+pub const CHAR_HEIGHT: usize = 6;
+
+/// 每列顯示的位元組數
+pub const HEX_BYTES_PER_ROW: u16 = 16;
+
+const GAP: usize = 4;
+
+/// RGBA 影像
+#[derive(Debug, Clone)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize) -> Self {
+        let mut pixels = Vec::with_capacity(width * height * 4);
+        for _ in 0..width * height {
+            pixels.extend_from_slice(&BACKGROUND);
+        }
+        Image {
+            width,
+            height,
+            pixels,
+        }
+    }
+
+    /// 每列的位元組數 (給 `Texture::update` 的 pitch)
+    pub fn pitch(&self) -> usize {
+        self.width * 4
+    }
+
+    pub fn pixel(&self, x: usize, y: usize) -> [u8; 4] {
+        let offset = (y * self.width + x) * 4;
+        self.pixels[offset..offset + 4].try_into().unwrap()
+    }
+
+    fn set_pixel(&mut self, x: usize, y: usize, color: [u8; 4]) {
+        if x < self.width && y < self.height {
+            let offset = (y * self.width + x) * 4;
+            self.pixels[offset..offset + 4].copy_from_slice(&color);
+        }
+    }
+
+    fn blit(&mut self, source: &Image, x: usize, y: usize) {
+        for row in 0..source.height {
+            for col in 0..source.width {
+                self.set_pixel(x + col, y + row, source.pixel(col, row));
+            }
+        }
+    }
+
+    fn draw_text(&mut self, x: usize, y: usize, text: &str) {
+        for (index, ch) in text.chars().enumerate() {
+            let Some(rows) = glyph(ch) else {
+                continue;
+            };
+            let left = x + index * CHAR_WIDTH;
+            for (dy, bits) in rows.iter().enumerate() {
+                for dx in 0..3 {
+                    if bits & (0b100 >> dx) != 0 {
+                        self.set_pixel(left + dx, y + dy, TEXT);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// 將多行文字排成影像
+pub fn text_image(lines: &[String]) -> Image {
+    let columns = lines.iter().map(|line| line.chars().count()).max().unwrap_or(0);
+    let mut image = Image::new(columns * CHAR_WIDTH + 1, lines.len() * CHAR_HEIGHT + 1);
+    for (row, line) in lines.iter().enumerate() {
+        image.draw_text(1, 1 + row * CHAR_HEIGHT, line);
+    }
+    image
+}
+
+/// 十六進位檢視：`C000 00 01 02 ...`，每列 16 位元組
+pub fn hex_lines(mmu: &Mmu, start: u16, rows: usize) -> Vec<String> {
+    let start = start & !(HEX_BYTES_PER_ROW - 1);
+    (0..rows)
+        .map(|row| {
+            let base = start.wrapping_add(row as u16 * HEX_BYTES_PER_ROW);
+            let mut line = format!("{:04X}", base);
+            for offset in 0..HEX_BYTES_PER_ROW {
+                line.push_str(&format!(" {:02X}", mmu.read_byte_ppu(base.wrapping_add(offset))));
+            }
+            line
+        })
+        .collect()
+}
+
+/// 依 `bgp` 繪製 0x8000-0x97FF 的 384 個圖塊 (16 x 24 格)
+pub fn tile_sheet(ppu: &Ppu) -> Image {
+    let mut image = Image::new(16 * 8, 24 * 8);
+    for tile in 0..384 {
+        let left = (tile % 16) * 8;
+        let top = (tile / 16) * 8;
+        draw_tile(&mut image, ppu, tile * 16, left, top);
+    }
+    image
+}
+
+/// 繪製 32x32 的背景圖 (`map_base` 為 0x9800 或 0x9C00)，並框出 SCX/SCY 的可視範圍
+pub fn background_map(ppu: &Ppu, map_base: u16) -> Image {
+    let mut image = Image::new(256, 256);
+    let map_offset = (map_base - 0x8000) as usize;
+    let tile_data_8000 = (ppu.lcdc & 0x10) != 0;
+
+    for index in 0..32 * 32 {
+        let tile_index = ppu.vram[map_offset + index];
+        // 與 render_scanline 相同的圖塊定址模式
+        let data_offset = if tile_data_8000 {
+            tile_index as usize * 16
+        } else {
+            (0x1000 + tile_index as i8 as isize * 16) as usize
+        };
+        draw_tile(&mut image, ppu, data_offset, (index % 32) * 8, (index / 32) * 8);
+    }
+
+    let active_base = if (ppu.lcdc & 0x08) != 0 { 0x9C00 } else { 0x9800 };
+    let color = if map_base == active_base {
+        VIEWPORT
+    } else {
+        VIEWPORT_INACTIVE
+    };
+    outline_viewport(&mut image, ppu.scx, ppu.scy, color);
+    image
+}
+
+// 可視範圍在 256x256 背景圖上環繞
+fn outline_viewport(image: &mut Image, scx: u8, scy: u8, color: [u8; 4]) {
+    let bottom = scy.wrapping_add(143);
+    let right = scx.wrapping_add(159);
+    for dx in 0..160u8 {
+        let x = scx.wrapping_add(dx) as usize;
+        image.set_pixel(x, scy as usize, color);
+        image.set_pixel(x, bottom as usize, color);
+    }
+    for dy in 0..144u8 {
+        let y = scy.wrapping_add(dy) as usize;
+        image.set_pixel(scx as usize, y, color);
+        image.set_pixel(right as usize, y, color);
+    }
+}
+
+fn draw_tile(image: &mut Image, ppu: &Ppu, data_offset: usize, left: usize, top: usize) {
+    for row in 0..8 {
+        let low = ppu.vram[data_offset + row * 2];
+        let high = ppu.vram[data_offset + row * 2 + 1];
+        for col in 0..8 {
+            let bit = 7 - col;
+            let color_idx = (((high >> bit) & 0x01) << 1) | ((low >> bit) & 0x01);
+            let shade = (ppu.bgp >> (color_idx * 2)) & 0x03;
+            image.set_pixel(left + col, top + row, SHADES[shade as usize]);
+        }
+    }
+}
+
+/// 解碼後的 OAM 項目
+#[derive(Debug, Clone, Copy)]
+pub struct OamEntry {
+    pub index: usize,
+    pub sprite: Sprite,
+    pub selected: bool, // 是否被選入目前掃描線的 10 個精靈
+}
+
+impl OamEntry {
+    pub fn behind_background(&self) -> bool {
+        self.sprite.attributes & 0x80 != 0
+    }
+
+    pub fn y_flip(&self) -> bool {
+        self.sprite.attributes & 0x40 != 0
+    }
+
+    pub fn x_flip(&self) -> bool {
+        self.sprite.attributes & 0x20 != 0
+    }
+
+    pub fn palette(&self) -> u8 {
+        (self.sprite.attributes >> 4) & 0x01
+    }
+}
+
+pub fn oam_entries(ppu: &Ppu) -> Vec<OamEntry> {
+    (0..40)
+        .map(|index| {
+            let base = index * 4;
+            OamEntry {
+                index,
+                sprite: Sprite {
+                    y_pos: ppu.oam[base],
+                    x_pos: ppu.oam[base + 1],
+                    tile_index: ppu.oam[base + 2],
+                    attributes: ppu.oam[base + 3],
+                },
+                selected: ppu.oam_sprites.iter().any(|(selected, _)| *selected == index),
+            }
+        })
+        .collect()
+}
+
+/// OAM 表格：編號、Y、X、圖塊、調色板、翻轉、優先級與是否被選入目前掃描線
+pub fn oam_lines(entries: &[OamEntry]) -> Vec<String> {
+    let mut lines = vec!["NO   Y   X TL P XY B L".to_string()];
+    for entry in entries {
+        lines.push(format!(
+            "{:02} {:3} {:3} {:02X} {} {}{} {} {}",
+            entry.index,
+            entry.sprite.y_pos,
+            entry.sprite.x_pos,
+            entry.sprite.tile_index,
+            entry.palette(),
+            if entry.x_flip() { 'X' } else { '-' },
+            if entry.y_flip() { 'Y' } else { '-' },
+            if entry.behind_background() { 'B' } else { '-' },
+            if entry.selected { '*' } else { ' ' },
+        ));
+    }
+    lines
+}
+
+/// VRAM 視窗：圖塊表、兩張背景圖，下方為分兩欄的 OAM 表格
+pub fn vram_view(ppu: &Ppu) -> Image {
+    let tiles = tile_sheet(ppu);
+    let map0 = background_map(ppu, 0x9800);
+    let map1 = background_map(ppu, 0x9C00);
+
+    let entries = oam_entries(ppu);
+    let (first, second) = entries.split_at(20);
+    let left = text_image(&oam_lines(first));
+    let right = text_image(&oam_lines(second));
+
+    let width = tiles.width + map0.width + map1.width + GAP * 4;
+    let height = map0.height + left.height + GAP * 3;
+    let mut image = Image::new(width, height);
+
+    image.blit(&tiles, GAP, GAP);
+    image.blit(&map0, tiles.width + GAP * 2, GAP);
+    image.blit(&map1, tiles.width + map0.width + GAP * 3, GAP);
+    let table_top = map0.height + GAP * 2;
+    image.blit(&left, GAP, table_top);
+    image.blit(&right, GAP * 2 + left.width, table_top);
+    image
+}
+
+// 3x5 點陣字形，每列 3 位元 (高位在左)
+fn glyph(ch: char) -> Option<[u8; 5]> {
+    let rows = match ch.to_ascii_uppercase() {
+        '0' => [0b111, 0b101, 0b101, 0b101, 0b111],
+        '1' => [0b010, 0b110, 0b010, 0b010, 0b111],
+        '2' => [0b111, 0b001, 0b111, 0b100, 0b111],
+        '3' => [0b111, 0b001, 0b111, 0b001, 0b111],
+        '4' => [0b101, 0b101, 0b111, 0b001, 0b001],
+        '5' => [0b111, 0b100, 0b111, 0b001, 0b111],
+        '6' => [0b111, 0b100, 0b111, 0b101, 0b111],
+        '7' => [0b111, 0b001, 0b001, 0b001, 0b001],
+        '8' => [0b111, 0b101, 0b111, 0b101, 0b111],
+        '9' => [0b111, 0b101, 0b111, 0b001, 0b111],
+        'A' => [0b010, 0b101, 0b111, 0b101, 0b101],
+        'B' => [0b110, 0b101, 0b110, 0b101, 0b110],
+        'C' => [0b011, 0b100, 0b100, 0b100, 0b011],
+        'D' => [0b110, 0b101, 0b101, 0b101, 0b110],
+        'E' => [0b111, 0b100, 0b110, 0b100, 0b111],
+        'F' => [0b111, 0b100, 0b110, 0b100, 0b100],
+        'G' => [0b011, 0b100, 0b101, 0b101, 0b011],
+        'H' => [0b101, 0b101, 0b111, 0b101, 0b101],
+        'I' => [0b111, 0b010, 0b010, 0b010, 0b111],
+        'J' => [0b001, 0b001, 0b001, 0b101, 0b010],
+        'K' => [0b101, 0b101, 0b110, 0b101, 0b101],
+        'L' => [0b100, 0b100, 0b100, 0b100, 0b111],
+        'M' => [0b101, 0b111, 0b111, 0b101, 0b101],
+        'N' => [0b110, 0b101, 0b101, 0b101, 0b101],
+        'O' => [0b010, 0b101, 0b101, 0b101, 0b010],
+        'P' => [0b110, 0b101, 0b110, 0b100, 0b100],
+        'Q' => [0b010, 0b101, 0b101, 0b110, 0b011],
+        'R' => [0b110, 0b101, 0b110, 0b101, 0b101],
+        'S' => [0b011, 0b100, 0b010, 0b001, 0b110],
+        'T' => [0b111, 0b010, 0b010, 0b010, 0b010],
+        'U' => [0b101, 0b101, 0b101, 0b101, 0b111],
+        'V' => [0b101, 0b101, 0b101, 0b101, 0b010],
+        'W' => [0b101, 0b101, 0b111, 0b111, 0b101],
+        'X' => [0b101, 0b101, 0b010, 0b101, 0b101],
+        'Y' => [0b101, 0b101, 0b010, 0b010, 0b010],
+        'Z' => [0b111, 0b001, 0b010, 0b100, 0b111],
+        ':' => [0b000, 0b010, 0b000, 0b010, 0b000],
+        '-' => [0b000, 0b000, 0b111, 0b000, 0b000],
+        '.' => [0b000, 0b000, 0b000, 0b000, 0b010],
+        '*' => [0b000, 0b101, 0b010, 0b101, 0b000],
+        _ => return None,
+    };
+    Some(rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_lines_align_to_row_start() {
+        let mut mmu = Mmu::new();
+        mmu.write_byte(0xC010, 0xAB);
+        mmu.write_byte(0xC01F, 0x42);
+
+        let lines = hex_lines(&mmu, 0xC015, 2);
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("C010 AB 00"));
+        assert!(lines[0].ends_with(" 42"));
+        assert!(lines[1].starts_with("C020 "));
+    }
+
+    #[test]
+    fn tile_sheet_uses_background_palette() {
+        let mut ppu = Ppu::new();
+        // 圖塊 1 的第一列：左邊像素顏色 3，其餘 0
+        ppu.vram[16] = 0x80;
+        ppu.vram[17] = 0x80;
+        ppu.bgp = 0b11_10_01_00;
+        let sheet = tile_sheet(&ppu);
+        assert_eq!((sheet.width, sheet.height), (128, 192));
+        assert_eq!(sheet.pixel(8, 0), SHADES[3]);
+        assert_eq!(sheet.pixel(9, 0), SHADES[0]);
+
+        // 反轉調色板
+        ppu.bgp = 0b00_01_10_11;
+        let sheet = tile_sheet(&ppu);
+        assert_eq!(sheet.pixel(8, 0), SHADES[0]);
+        assert_eq!(sheet.pixel(9, 0), SHADES[3]);
+    }
+
+    #[test]
+    fn background_map_outlines_wrapping_viewport() {
+        let mut ppu = Ppu::new();
+        ppu.lcdc = 0x91; // BG 圖 0x9800
+        ppu.scx = 200;
+        ppu.scy = 0;
+
+        let active = background_map(&ppu, 0x9800);
+        assert_eq!(active.pixel(200, 0), VIEWPORT);
+        // 右邊界環繞到 (200 + 159) % 256 = 103
+        assert_eq!(active.pixel(103, 50), VIEWPORT);
+        assert_eq!(active.pixel(150, 50), SHADES[(ppu.bgp & 0x03) as usize]);
+
+        let inactive = background_map(&ppu, 0x9C00);
+        assert_eq!(inactive.pixel(200, 0), VIEWPORT_INACTIVE);
+    }
+
+    #[test]
+    fn oam_entries_mark_selected_sprites() {
+        let mut ppu = Ppu::new();
+        ppu.oam[4..8].copy_from_slice(&[16, 8, 0x2A, 0xB0]);
+        let sprite = oam_entries(&ppu)[1].sprite;
+        ppu.oam_sprites.push((1, sprite));
+
+        let entries = oam_entries(&ppu);
+        assert_eq!(entries.len(), 40);
+        let entry = entries[1];
+        assert!(entry.selected && !entries[0].selected);
+        assert!(entry.behind_background() && !entry.y_flip() && entry.x_flip());
+        assert_eq!(entry.palette(), 1);
+        assert_eq!(oam_lines(&entries)[2], "01  16   8 2A 1 X- B *");
+    }
+
+    #[test]
+    fn vram_view_fits_all_panels() {
+        let view = vram_view(&Ppu::new());
+        assert_eq!(view.width, 128 + 256 + 256 + GAP * 4);
+        assert_eq!(view.pixels.len(), view.width * view.height * 4);
+    }
+}
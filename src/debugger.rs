@@ -0,0 +1,1252 @@
+//! 除錯器子系統
+//!
+//! 包裝 `GameBoy` 的單步執行，提供 PC 中斷點 (可指定 ROM bank)、記憶體監看點、
+//! step-in/over/out、執行到 VBlank 與暫存器編輯。命令以文字輸入，
+//! 可由 stdin REPL 搭配 SDL 視窗一起使用。
+
+use crate::cheats::Cheat;
+use crate::cpu;
+use crate::disasm;
+use crate::gameboy::GameBoy;
+use crate::ram_search::{Filter, RamSearch, Width};
+use crate::symbols::SymbolTable;
+use crossbeam::channel::Receiver;
+use std::io::BufRead;
+
+/// 監看點觸發的存取類型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WatchKind {
+    Read,
+    Write,
+    Access, // 讀取或寫入
+}
+
+impl WatchKind {
+    fn matches(self, write: bool) -> bool {
+        match self {
+            WatchKind::Read => !write,
+            WatchKind::Write => write,
+            WatchKind::Access => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Watchpoint {
+    pub address: u16,
+    pub kind: WatchKind,
+}
+
+/// 一次監看點命中的記錄
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WatchHit {
+    pub address: u16,
+    pub value: u8,
+    pub write: bool,
+}
+
+/// 由 `Mmu` 持有的監看點清單，只在 CPU 執行指令經由匯流排存取時檢查
+/// (中斷輪詢與分派對 IE/IF 的內部存取不算)
+#[derive(Debug, Clone, Default)]
+pub struct Watchpoints {
+    list: Vec<Watchpoint>,
+    pub hit: Option<WatchHit>,
+}
+
+impl Watchpoints {
+    #[inline]
+    pub fn check(&mut self, address: u16, value: u8, write: bool) {
+        if self.list.is_empty() || self.hit.is_some() {
+            return;
+        }
+        if self
+            .list
+            .iter()
+            .any(|w| w.address == address && w.kind.matches(write))
+        {
+            self.hit = Some(WatchHit {
+                address,
+                value,
+                write,
+            });
+        }
+    }
+
+    pub fn add(&mut self, watchpoint: Watchpoint) {
+        self.remove(watchpoint.address);
+        self.list.push(watchpoint);
+    }
+
+    pub fn remove(&mut self, address: u16) -> bool {
+        let before = self.list.len();
+        self.list.retain(|w| w.address != address);
+        self.list.len() != before
+    }
+
+    pub fn kind_at(&self, address: u16) -> Option<WatchKind> {
+        self.list
+            .iter()
+            .find(|w| w.address == address)
+            .map(|w| w.kind)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Watchpoint> {
+        self.list.iter()
+    }
+}
+
+/// PC 中斷點；`bank` 為 None 時不論目前的 ROM bank 都會觸發
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Breakpoint {
+    pub address: u16,
+    pub bank: Option<u16>,
+}
+
+impl Breakpoint {
+    fn matches(&self, gb: &GameBoy) -> bool {
+        if gb.cpu.pc != self.address {
+            return false;
+        }
+        match self.bank {
+            Some(bank) => gb.mmu.rom_bank_at(self.address) == Some(bank),
+            None => true,
+        }
+    }
+}
+
+impl std::fmt::Display for Breakpoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.bank {
+            Some(bank) => write!(f, "{:02X}:{:04X}", bank, self.address),
+            None => write!(f, "{:04X}", self.address),
+        }
+    }
+}
+
+/// 中斷點位置：數值位址或符號檔中的標籤
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Location {
+    Address(Breakpoint), // `bank:addr`、`$` 前綴或以數字開頭的位址
+    Label(String),       // 先查符號表，找不到時才當作十六進位位址 (`Add`、`BEEF` 等標籤優先)
+}
+
+impl Location {
+    // 標籤解析為其所在的 bank；ROM 以外的區域不限制 bank
+    fn resolve(&self, symbols: &SymbolTable) -> Option<Breakpoint> {
+        match self {
+            Location::Address(breakpoint) => Some(*breakpoint),
+            Location::Label(name) => match symbols.lookup(name) {
+                Some(symbol) => Some(Breakpoint {
+                    address: symbol.address,
+                    bank: (symbol.address < 0x8000).then_some(symbol.bank),
+                }),
+                None => parse_number(name).ok().map(|address| Breakpoint {
+                    address,
+                    bank: None,
+                }),
+            },
+        }
+    }
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Location::Address(breakpoint) => write!(f, "{}", breakpoint),
+            Location::Label(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+/// 可編輯的暫存器
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Register {
+    A,
+    F,
+    B,
+    C,
+    D,
+    E,
+    H,
+    L,
+    AF,
+    BC,
+    DE,
+    HL,
+    SP,
+    PC,
+}
+
+impl Register {
+    fn parse(name: &str) -> Option<Register> {
+        Some(match name.to_ascii_lowercase().as_str() {
+            "a" => Register::A,
+            "f" => Register::F,
+            "b" => Register::B,
+            "c" => Register::C,
+            "d" => Register::D,
+            "e" => Register::E,
+            "h" => Register::H,
+            "l" => Register::L,
+            "af" => Register::AF,
+            "bc" => Register::BC,
+            "de" => Register::DE,
+            "hl" => Register::HL,
+            "sp" => Register::SP,
+            "pc" => Register::PC,
+            _ => return None,
+        })
+    }
+}
+
+/// 可由 `layer` 命令切換的圖層
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    Background,
+    Window,
+    Sprites,
+    Sprite(usize), // 單一 OAM 項目 (0-39)
+}
+
+/// 除錯命令
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    Break(Location),
+    Delete(usize),
+    Watch(Watchpoint),
+    Unwatch(u16),
+    List,
+    StepIn,
+    StepOver,
+    StepOut,
+    Continue,
+    Pause,
+    RunToVBlank,
+    Registers,
+    Set(Register, u16),
+    Examine(u16, u16),
+    Disassemble(Option<Location>, u16),
+    Layers,
+    SetLayer(Layer, bool),
+    Cheats,
+    AddCheat(Cheat),
+    EnableCheat(usize, bool),
+    DeleteCheat(usize),
+    SearchStart(Width),
+    SearchFilter(Filter),
+    SearchList(usize),
+    SearchFreeze(usize, String),
+    Help,
+    Quit,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    Empty,
+    UnknownCommand(String),
+    MissingArgument(&'static str),
+    InvalidNumber(String),
+    UnknownRegister(String),
+    InvalidCheat(String),
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::Empty => write!(f, "空白命令"),
+            ParseError::UnknownCommand(cmd) => write!(f, "未知命令: {} (輸入 help 查看說明)", cmd),
+            ParseError::MissingArgument(arg) => write!(f, "缺少參數: {}", arg),
+            ParseError::InvalidNumber(text) => write!(f, "無效的數值: {}", text),
+            ParseError::UnknownRegister(name) => write!(f, "未知暫存器: {}", name),
+            ParseError::InvalidCheat(code) => write!(f, "無效的金手指代碼: {}", code),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// 解析十六進位數值，接受 `$`、`0x` 前綴或 `h` 後綴
+fn parse_number(text: &str) -> Result<u16, ParseError> {
+    let digits = text
+        .trim_start_matches('$')
+        .trim_start_matches("0x")
+        .trim_start_matches("0X")
+        .trim_end_matches(['h', 'H']);
+    u16::from_str_radix(digits, 16).map_err(|_| ParseError::InvalidNumber(text.to_string()))
+}
+
+// 解析 `bank:addr` 或帶前綴的位址；其他文字可能是標籤，等有符號表時再解析
+fn parse_location(text: &str) -> Location {
+    let breakpoint = match text.split_once(':') {
+        Some((bank, address)) => {
+            parse_number(address).and_then(|address| Ok((address, Some(parse_number(bank)?))))
+        }
+        // RGBDS 的標籤不會以數字開頭
+        None if text.starts_with(|c: char| c == '$' || c.is_ascii_digit()) => {
+            parse_number(text).map(|address| (address, None))
+        }
+        None => return Location::Label(text.to_string()),
+    };
+    match breakpoint {
+        Ok((address, bank)) => Location::Address(Breakpoint { address, bank }),
+        Err(_) => Location::Label(text.to_string()),
+    }
+}
+
+impl std::str::FromStr for Command {
+    type Err = ParseError;
+
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let mut words = line.split_whitespace();
+        let name = words.next().ok_or(ParseError::Empty)?;
+        let mut arg = |what: &'static str| words.next().ok_or(ParseError::MissingArgument(what));
+
+        Ok(match name {
+            "b" | "break" => Command::Break(parse_location(arg("位址")?)),
+            "d" | "delete" => {
+                let text = arg("中斷點編號")?;
+                let index = text
+                    .parse()
+                    .map_err(|_| ParseError::InvalidNumber(text.to_string()))?;
+                Command::Delete(index)
+            }
+            "w" | "watch" => {
+                let address = parse_number(arg("位址")?)?;
+                let kind = match words.next() {
+                    None | Some("rw") => WatchKind::Access,
+                    Some("r") => WatchKind::Read,
+                    Some("w") => WatchKind::Write,
+                    Some(other) => return Err(ParseError::UnknownCommand(other.to_string())),
+                };
+                Command::Watch(Watchpoint { address, kind })
+            }
+            "unwatch" => Command::Unwatch(parse_number(arg("位址")?)?),
+            "l" | "list" => Command::List,
+            "s" | "step" => Command::StepIn,
+            "n" | "next" => Command::StepOver,
+            "finish" | "out" => Command::StepOut,
+            "c" | "continue" => Command::Continue,
+            "p" | "pause" => Command::Pause,
+            "v" | "vblank" => Command::RunToVBlank,
+            "r" | "regs" => Command::Registers,
+            "set" => {
+                let name = arg("暫存器")?;
+                let register = Register::parse(name)
+                    .ok_or_else(|| ParseError::UnknownRegister(name.to_string()))?;
+                Command::Set(register, parse_number(arg("數值")?)?)
+            }
+            "x" => {
+                let address = parse_number(arg("位址")?)?;
+                let length = match words.next() {
+                    Some(text) => parse_number(text)?,
+                    None => 0x40,
+                };
+                Command::Examine(address, length)
+            }
+            "dis" => {
+                let location = words.next().map(parse_location);
+                let count = match words.next() {
+                    Some(text) => parse_number(text)?,
+                    None => 8,
+                };
+                Command::Disassemble(location, count)
+            }
+            "layer" => {
+                let Some(name) = words.next() else {
+                    return Ok(Command::Layers);
+                };
+                let layer = match name {
+                    "bg" => Layer::Background,
+                    "win" => Layer::Window,
+                    "obj" => Layer::Sprites,
+                    "oam" => {
+                        let text = words
+                            .next()
+                            .ok_or(ParseError::MissingArgument("OAM 編號"))?;
+                        match text.parse() {
+                            Ok(index) if index < 40 => Layer::Sprite(index),
+                            _ => return Err(ParseError::InvalidNumber(text.to_string())),
+                        }
+                    }
+                    other => return Err(ParseError::UnknownCommand(other.to_string())),
+                };
+                let visible = match words.next() {
+                    Some("on") => true,
+                    Some("off") => false,
+                    Some(other) => return Err(ParseError::UnknownCommand(other.to_string())),
+                    None => return Err(ParseError::MissingArgument("on|off")),
+                };
+                Command::SetLayer(layer, visible)
+            }
+            "cheat" => {
+                let Some(action) = words.next() else {
+                    return Ok(Command::Cheats);
+                };
+                if action == "add" {
+                    let code = words.next().ok_or(ParseError::MissingArgument("代碼"))?;
+                    let name = words.collect::<Vec<_>>().join(" ");
+                    let cheat = Cheat::new(code, &name)
+                        .map_err(|_| ParseError::InvalidCheat(code.to_string()))?;
+                    return Ok(Command::AddCheat(cheat));
+                }
+                let text = words
+                    .next()
+                    .ok_or(ParseError::MissingArgument("金手指編號"))?;
+                let index = text
+                    .parse()
+                    .map_err(|_| ParseError::InvalidNumber(text.to_string()))?;
+                match action {
+                    "on" => Command::EnableCheat(index, true),
+                    "off" => Command::EnableCheat(index, false),
+                    "del" => Command::DeleteCheat(index),
+                    other => return Err(ParseError::UnknownCommand(other.to_string())),
+                }
+            }
+            "search" => match words
+                .next()
+                .ok_or(ParseError::MissingArgument("搜尋動作"))?
+            {
+                "new" => match words.next() {
+                    None | Some("8") => Command::SearchStart(Width::Byte),
+                    Some("16") => Command::SearchStart(Width::Word),
+                    Some(other) => return Err(ParseError::InvalidNumber(other.to_string())),
+                },
+                "eq" => Command::SearchFilter(Filter::Equal),
+                "changed" => Command::SearchFilter(Filter::Changed),
+                "inc" => Command::SearchFilter(Filter::Increased),
+                "dec" => Command::SearchFilter(Filter::Decreased),
+                "=" => Command::SearchFilter(Filter::Value(parse_number(
+                    words.next().ok_or(ParseError::MissingArgument("數值"))?,
+                )?)),
+                "list" => match words.next() {
+                    Some(text) => Command::SearchList(
+                        text.parse()
+                            .map_err(|_| ParseError::InvalidNumber(text.to_string()))?,
+                    ),
+                    None => Command::SearchList(20),
+                },
+                "freeze" => {
+                    let text = words
+                        .next()
+                        .ok_or(ParseError::MissingArgument("結果編號"))?;
+                    let index = text
+                        .parse()
+                        .map_err(|_| ParseError::InvalidNumber(text.to_string()))?;
+                    Command::SearchFreeze(index, words.collect::<Vec<_>>().join(" "))
+                }
+                other => return Err(ParseError::UnknownCommand(other.to_string())),
+            },
+            "h" | "help" => Command::Help,
+            "q" | "quit" => Command::Quit,
+            other => return Err(ParseError::UnknownCommand(other.to_string())),
+        })
+    }
+}
+
+/// 停止執行的原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StopReason {
+    Breakpoint(usize), // 中斷點編號
+    Watchpoint(WatchHit),
+    Step,
+    VBlank,
+}
+
+// 目前的執行模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum RunMode {
+    Paused,
+    Continue,
+    StepOver { return_pc: u16, sp: u16 },
+    StepOut { sp: u16 },
+    ToVBlank,
+}
+
+#[derive(Debug)]
+pub struct Debugger {
+    breakpoints: Vec<(usize, Breakpoint)>, // (編號, 中斷點)，刪除後其他編號不變
+    next_id: usize,
+    mode: RunMode,
+    search: Option<RamSearch>,
+}
+
+impl Debugger {
+    pub fn new() -> Self {
+        Debugger {
+            breakpoints: Vec::new(),
+            next_id: 0,
+            mode: RunMode::Paused,
+            search: None,
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.mode == RunMode::Paused
+    }
+
+    /// 新增中斷點並回傳其編號
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) -> usize {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.breakpoints.push((id, breakpoint));
+        id
+    }
+
+    fn breakpoint(&self, id: usize) -> Option<Breakpoint> {
+        self.breakpoints
+            .iter()
+            .find(|&&(other, _)| other == id)
+            .map(|&(_, breakpoint)| breakpoint)
+    }
+
+    /// 執行一個命令，回傳要顯示給使用者的文字
+    pub fn execute(&mut self, gb: &mut GameBoy, command: Command) -> String {
+        match command {
+            Command::Break(location) => match location.resolve(&gb.symbols) {
+                Some(breakpoint) => {
+                    let index = self.add_breakpoint(breakpoint);
+                    match location {
+                        Location::Label(name) if gb.symbols.lookup(&name).is_some() => {
+                            format!("中斷點 #{} 設於 {} ({})", index, name, breakpoint)
+                        }
+                        _ => format!("中斷點 #{} 設於 {}", index, breakpoint),
+                    }
+                }
+                None => format!("找不到標籤: {}", location),
+            },
+            Command::Delete(id) => match self.breakpoint(id) {
+                Some(removed) => {
+                    self.breakpoints.retain(|&(other, _)| other != id);
+                    format!("已刪除中斷點 #{} ({})", id, removed)
+                }
+                None => format!("沒有中斷點 #{}", id),
+            },
+            Command::Watch(watchpoint) => {
+                gb.mmu.watchpoints.add(watchpoint);
+                format!(
+                    "監看點設於 {:04X} ({:?})",
+                    watchpoint.address, watchpoint.kind
+                )
+            }
+            Command::Unwatch(address) => {
+                if gb.mmu.watchpoints.remove(address) {
+                    format!("已移除監看點 {:04X}", address)
+                } else {
+                    format!("{:04X} 沒有監看點", address)
+                }
+            }
+            Command::List => self.list(gb),
+            Command::StepIn => {
+                gb.step_cpu_with_timing();
+                format_registers(gb)
+            }
+            Command::StepOver => {
+                self.step_over(gb);
+                self.resume_message(gb)
+            }
+            Command::StepOut => {
+                self.mode = RunMode::StepOut { sp: gb.cpu.sp };
+                String::new()
+            }
+            Command::Continue => {
+                self.mode = RunMode::Continue;
+                String::new()
+            }
+            Command::Pause => {
+                self.mode = RunMode::Paused;
+                format_registers(gb)
+            }
+            Command::RunToVBlank => {
+                self.mode = RunMode::ToVBlank;
+                String::new()
+            }
+            Command::Registers => format_registers(gb),
+            Command::Set(register, value) => {
+                set_register(gb, register, value);
+                format_registers(gb)
+            }
+            Command::Examine(address, length) => format_memory(gb, address, length),
+            Command::Disassemble(None, count) => format_disassembly(gb, gb.cpu.pc, None, count),
+            Command::Disassemble(Some(location), count) => match location.resolve(&gb.symbols) {
+                Some(start) => format_disassembly(gb, start.address, start.bank, count),
+                None => format!("找不到標籤: {}", location),
+            },
+            Command::Layers => format_layers(gb),
+            Command::SetLayer(layer, visible) => {
+                let debug = &mut gb.mmu.ppu.debug;
+                match layer {
+                    Layer::Background => debug.background = visible,
+                    Layer::Window => debug.window = visible,
+                    Layer::Sprites => debug.sprites = visible,
+                    Layer::Sprite(index) => debug.set_sprite_visible(index, visible),
+                }
+                format_layers(gb)
+            }
+            Command::Cheats => format_cheats(gb),
+            Command::AddCheat(cheat) => {
+                let index = gb.mmu.cheats.add(cheat);
+                format!("已新增金手指 #{}", index)
+            }
+            Command::EnableCheat(index, enabled) => {
+                if gb.mmu.cheats.set_enabled(index, enabled) {
+                    format_cheats(gb)
+                } else {
+                    format!("沒有金手指 #{}", index)
+                }
+            }
+            Command::DeleteCheat(index) => match gb.mmu.cheats.remove(index) {
+                Some(cheat) => format!("已刪除金手指 {}", cheat.text),
+                None => format!("沒有金手指 #{}", index),
+            },
+            Command::SearchStart(width) => {
+                let search = RamSearch::new(&gb.mmu, width);
+                let count = search.candidates().len();
+                self.search = Some(search);
+                format!("已建立快照，{} 個位址", count)
+            }
+            Command::SearchFilter(filter) => match self.search.as_mut() {
+                Some(search) => {
+                    search.filter(&gb.mmu, filter);
+                    format!("剩餘 {} 個位址", search.candidates().len())
+                }
+                None => NO_SEARCH.to_string(),
+            },
+            Command::SearchList(count) => match self.search.as_ref() {
+                Some(search) => format_search(search, count),
+                None => NO_SEARCH.to_string(),
+            },
+            Command::SearchFreeze(index, name) => {
+                let Some(search) = self.search.as_ref() else {
+                    return NO_SEARCH.to_string();
+                };
+                match search.freeze(&gb.mmu, index, &name) {
+                    Some(cheats) => {
+                        let codes: Vec<String> = cheats
+                            .into_iter()
+                            .map(|cheat| {
+                                let text = cheat.text.clone();
+                                gb.mmu.cheats.add(cheat);
+                                text
+                            })
+                            .collect();
+                        format!("已新增凍結金手指 {}", codes.join(" "))
+                    }
+                    None => format!("沒有搜尋結果 #{}", index),
+                }
+            }
+            Command::Help => HELP.to_string(),
+            Command::Quit => String::new(),
+        }
+    }
+
+    // CALL/RST 會執行到返回為止，其他指令等同 step-in
+    fn step_over(&mut self, gb: &mut GameBoy) {
+        let opcode = gb.mmu.read_byte_ppu(gb.cpu.pc);
+        match cpu::opcode(false, opcode) {
+            Some(op) if op.mnemonic == "CALL" || op.mnemonic == "RST" => {
+                self.mode = RunMode::StepOver {
+                    return_pc: gb.cpu.pc.wrapping_add(op.bytes as u16),
+                    sp: gb.cpu.sp,
+                };
+            }
+            _ => {
+                gb.step_cpu_with_timing();
+            }
+        }
+    }
+
+    fn resume_message(&self, gb: &GameBoy) -> String {
+        if self.is_paused() {
+            format_registers(gb)
+        } else {
+            String::new()
+        }
+    }
+
+    fn list(&self, gb: &GameBoy) -> String {
+        let mut out = String::new();
+        for (id, breakpoint) in &self.breakpoints {
+            out.push_str(&format!("#{} {}\n", id, breakpoint));
+        }
+        for watchpoint in gb.mmu.watchpoints.iter() {
+            out.push_str(&format!(
+                "watch {:04X} ({:?})\n",
+                watchpoint.address, watchpoint.kind
+            ));
+        }
+        if out.is_empty() {
+            out.push_str("沒有中斷點或監看點");
+        }
+        out.trim_end().to_string()
+    }
+
+    /// 依目前模式執行直到一幀完成或停止；暫停時不執行任何指令
+    pub fn run_frame(&mut self, gb: &mut GameBoy) -> Option<StopReason> {
+        if self.is_paused() {
+            return None;
+        }
+
+        // 恢復執行時略過目前 PC 上的中斷點，避免原地停住
+        let mut first = true;
+        loop {
+            if !first && let Some(&(id, _)) = self.breakpoints.iter().find(|(_, b)| b.matches(gb)) {
+                return self.stop(StopReason::Breakpoint(id));
+            }
+            first = false;
+
+            let opcode = gb.mmu.read_byte_ppu(gb.cpu.pc);
+            gb.step_cpu_with_timing();
+
+            if let Some(hit) = gb.mmu.watchpoints.hit.take() {
+                return self.stop(StopReason::Watchpoint(hit));
+            }
+
+            match self.mode {
+                RunMode::StepOver { return_pc, sp }
+                    if gb.cpu.pc == return_pc && gb.cpu.sp == sp =>
+                {
+                    return self.stop(StopReason::Step);
+                }
+                RunMode::StepOut { sp } if is_return(opcode) && gb.cpu.sp > sp => {
+                    return self.stop(StopReason::Step);
+                }
+                _ => {}
+            }
+
+            if gb.mmu.ppu.take_frame_ready() {
+                if self.mode == RunMode::ToVBlank {
+                    return self.stop(StopReason::VBlank);
+                }
+                return None;
+            }
+        }
+    }
+
+    fn stop(&mut self, reason: StopReason) -> Option<StopReason> {
+        self.mode = RunMode::Paused;
+        Some(reason)
+    }
+
+    /// 描述停止原因與目前暫存器
+    pub fn describe_stop(&self, gb: &GameBoy, reason: StopReason) -> String {
+        let header = match reason {
+            StopReason::Breakpoint(id) => match self.breakpoint(id) {
+                Some(breakpoint) => format!("命中中斷點 #{} ({})", id, breakpoint),
+                None => format!("命中中斷點 #{}", id),
+            },
+            StopReason::Watchpoint(hit) => format!(
+                "監看點 {:04X}: {} {:02X}",
+                hit.address,
+                if hit.write { "寫入" } else { "讀取" },
+                hit.value
+            ),
+            StopReason::Step => "單步完成".to_string(),
+            StopReason::VBlank => "進入 VBlank".to_string(),
+        };
+        format!("{}\n{}", header, format_registers(gb))
+    }
+}
+
+// RET、RET cc、RETI
+fn is_return(opcode: u8) -> bool {
+    matches!(opcode, 0xC9 | 0xD9 | 0xC0 | 0xC8 | 0xD0 | 0xD8)
+}
+
+fn set_register(gb: &mut GameBoy, register: Register, value: u16) {
+    let cpu = &mut gb.cpu;
+    let byte = value as u8;
+    match register {
+        Register::A => cpu.set_a(byte),
+        Register::F => cpu.set_f(byte),
+        Register::B => cpu.set_b(byte),
+        Register::C => cpu.set_c(byte),
+        Register::D => cpu.set_d(byte),
+        Register::E => cpu.set_e(byte),
+        Register::H => cpu.set_h(byte),
+        Register::L => cpu.set_l(byte),
+        Register::AF => cpu.set_af(value),
+        Register::BC => cpu.set_bc(value),
+        Register::DE => cpu.set_de(value),
+        Register::HL => cpu.set_hl(value),
+        Register::SP => cpu.sp = value,
+        Register::PC => cpu.pc = value,
+    }
+}
+
+pub fn format_registers(gb: &GameBoy) -> String {
+    let cpu = &gb.cpu;
+    let bank = gb
+        .mmu
+        .rom_bank_at(cpu.pc)
+        .map_or_else(|| "--".to_string(), |bank| format!("{:02X}", bank));
+    let registers = format!(
+        "AF={:04X} BC={:04X} DE={:04X} HL={:04X} SP={:04X} PC={}:{:04X} IME={:?} LY={}",
+        cpu.get_af(),
+        cpu.get_bc(),
+        cpu.get_de(),
+        cpu.get_hl(),
+        cpu.sp,
+        bank,
+        cpu.pc,
+        cpu.ime,
+        gb.mmu.read_byte_ppu(0xFF44)
+    );
+    format!("{}\n{}", registers, format_disassembly(gb, cpu.pc, None, 1))
+}
+
+// 指定 `bank` 時 0x4000-0x7FFF 讀取該 ROM bank，否則讀取目前映射的內容
+fn format_disassembly(gb: &GameBoy, address: u16, bank: Option<u16>, count: u16) -> String {
+    let mut out = String::new();
+    let mut address = address;
+    for _ in 0..count {
+        let (text, length, bank, label) = match bank {
+            Some(bank) if (0x4000..0x8000).contains(&address) => {
+                let (text, length) = disasm::disassemble_bank(&gb.mmu, &gb.symbols, bank, address);
+                (
+                    text,
+                    length,
+                    Some(bank),
+                    gb.symbols.label_in_bank(bank, address),
+                )
+            }
+            _ => {
+                let (text, length) = disasm::disassemble(&gb.mmu, &gb.symbols, address);
+                let bank = gb.mmu.rom_bank_at(address);
+                (text, length, bank, gb.symbols.label_at(&gb.mmu, address))
+            }
+        };
+        let bank = bank.map_or_else(|| "--".to_string(), |bank| format!("{:02X}", bank));
+        if let Some(label) = label {
+            out.push_str(&format!("{}:\n", label));
+        }
+        out.push_str(&format!("{}:{:04X}  {}\n", bank, address, text));
+        address = address.wrapping_add(length);
+    }
+    out.trim_end().to_string()
+}
+
+fn format_memory(gb: &GameBoy, address: u16, length: u16) -> String {
+    let mut out = String::new();
+    for row in (0..length).step_by(16) {
+        let start = address.wrapping_add(row);
+        out.push_str(&format!("{:04X}:", start));
+        for offset in 0..16.min(length - row) {
+            out.push_str(&format!(
+                " {:02X}",
+                gb.mmu.read_byte_ppu(start.wrapping_add(offset))
+            ));
+        }
+        out.push('\n');
+    }
+    out.trim_end().to_string()
+}
+
+// 圖層開關狀態與被隱藏的 OAM 項目
+fn format_layers(gb: &GameBoy) -> String {
+    let debug = gb.mmu.ppu.debug;
+    let state = |visible: bool| if visible { "on" } else { "off" };
+    let hidden: Vec<String> = (0..40)
+        .filter(|&index| !debug.sprite_visible(index))
+        .map(|index| index.to_string())
+        .collect();
+    let mut out = format!(
+        "bg {}  win {}  obj {}",
+        state(debug.background),
+        state(debug.window),
+        state(debug.sprites)
+    );
+    if !hidden.is_empty() {
+        out.push_str(&format!("\n隱藏的 OAM: {}", hidden.join(" ")));
+    }
+    out
+}
+
+fn format_cheats(gb: &GameBoy) -> String {
+    let lines: Vec<String> = gb
+        .mmu
+        .cheats
+        .iter()
+        .enumerate()
+        .map(|(index, cheat)| {
+            let state = if cheat.enabled { "on " } else { "off" };
+            format!("#{} {} {} {}", index, state, cheat.text, cheat.name)
+                .trim_end()
+                .to_string()
+        })
+        .collect();
+    if lines.is_empty() {
+        "沒有金手指".to_string()
+    } else {
+        lines.join("\n")
+    }
+}
+
+const NO_SEARCH: &str = "尚未建立搜尋快照 (search new [8|16])";
+
+fn format_search(search: &RamSearch, count: usize) -> String {
+    let candidates = search.candidates();
+    let mut out = format!("{} 個位址", candidates.len());
+    for (index, candidate) in candidates.iter().take(count).enumerate() {
+        match search.width() {
+            Width::Byte => out.push_str(&format!(
+                "\n#{:<4} {:04X} = {:02X} ({})",
+                index, candidate.address, candidate.value, candidate.value
+            )),
+            Width::Word => out.push_str(&format!(
+                "\n#{:<4} {:04X} = {:04X} ({})",
+                index, candidate.address, candidate.value, candidate.value
+            )),
+        }
+    }
+    out
+}
+
+const HELP: &str = "\
+b|break [bank:]addr|label  設定 PC 中斷點 (標籤優先，$addr 強制為位址)
+d|delete n              刪除中斷點
+w|watch addr [r|w|rw]   設定記憶體監看點
+unwatch addr            移除監看點
+l|list                  列出中斷點與監看點
+s|step                  單步 (step-in)
+n|next                  單步，跳過 CALL/RST (step-over)
+finish|out              執行到目前函式返回 (step-out)
+c|continue              繼續執行
+p|pause                 暫停
+v|vblank                執行到下一次 VBlank
+r|regs                  顯示暫存器
+set reg value           修改暫存器 (a, f, b, ..., af, bc, de, hl, sp, pc)
+x addr [len]            顯示記憶體
+dis [[bank:]addr] [n]   反組譯 (預設從 PC 開始 8 個指令，指定 bank 時不受目前映射影響)
+layer [bg|win|obj on|off] 切換圖層顯示 (不影響 LCDC)
+layer oam n on|off      切換單一 OAM 項目 (0-39)
+cheat [add code [name]] 列出 / 新增金手指 (GameShark 或 Game Genie)
+cheat on|off|del n      啟用 / 停用 / 刪除金手指
+search new [8|16]       對 WRAM/HRAM/卡帶 RAM 建立搜尋快照
+search eq|changed|inc|dec|= value  與上次比較並過濾
+search list [n]         列出搜尋結果
+search freeze n [name]  將結果轉為凍結金手指
+q|quit                  離開";
+
+/// 在背景執行緒逐行讀取 stdin，讓 SDL 主迴圈不會被阻塞
+pub fn spawn_stdin_reader() -> Receiver<String> {
+    let (tx, rx) = crossbeam::channel::unbounded();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else { break };
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+    rx
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_commands() {
+        assert_eq!(
+            "break 02:4abc".parse(),
+            Ok(Command::Break(Location::Address(Breakpoint {
+                address: 0x4ABC,
+                bank: Some(2)
+            })))
+        );
+        assert_eq!(
+            "b Main.loop".parse(),
+            Ok(Command::Break(Location::Label("Main.loop".into())))
+        );
+        assert_eq!(
+            "watch $c000 w".parse(),
+            Ok(Command::Watch(Watchpoint {
+                address: 0xC000,
+                kind: WatchKind::Write
+            }))
+        );
+        assert_eq!(
+            "set hl 0x1234".parse(),
+            Ok(Command::Set(Register::HL, 0x1234))
+        );
+        assert_eq!("x c000".parse(), Ok(Command::Examine(0xC000, 0x40)));
+        assert_eq!(
+            "set q 1".parse::<Command>(),
+            Err(ParseError::UnknownRegister("q".into()))
+        );
+        assert_eq!(
+            "zzz".parse::<Command>(),
+            Err(ParseError::UnknownCommand("zzz".into()))
+        );
+        assert_eq!("layer".parse(), Ok(Command::Layers));
+        assert_eq!(
+            "layer win off".parse(),
+            Ok(Command::SetLayer(Layer::Window, false))
+        );
+        assert_eq!(
+            "layer oam 39 on".parse(),
+            Ok(Command::SetLayer(Layer::Sprite(39), true))
+        );
+        assert_eq!(
+            "layer oam 40 off".parse::<Command>(),
+            Err(ParseError::InvalidNumber("40".into()))
+        );
+        assert_eq!(
+            "cheat add 016338CD Infinite lives".parse(),
+            Ok(Command::AddCheat(
+                Cheat::new("016338CD", "Infinite lives").unwrap()
+            ))
+        );
+        assert_eq!("cheat off 1".parse(), Ok(Command::EnableCheat(1, false)));
+        assert_eq!(
+            "search new 16".parse(),
+            Ok(Command::SearchStart(Width::Word))
+        );
+        assert_eq!(
+            "search = 3e8".parse(),
+            Ok(Command::SearchFilter(Filter::Value(0x3E8)))
+        );
+        assert_eq!(
+            "search freeze 2 hp".parse(),
+            Ok(Command::SearchFreeze(2, "hp".into()))
+        );
+        assert_eq!(
+            "cheat add 0163".parse::<Command>(),
+            Err(ParseError::InvalidCheat("0163".into()))
+        );
+    }
+
+    #[test]
+    fn search_results_become_freeze_cheats() {
+        let mut gb = GameBoy::with_program(&[0x18, 0xFE]);
+        let mut debugger = Debugger::new();
+        gb.mmu.write_byte(0xC123, 5);
+        debugger.execute(&mut gb, "search new".parse().unwrap());
+        gb.mmu.write_byte(0xC123, 4);
+        debugger.execute(&mut gb, "search dec".parse().unwrap());
+        assert!(
+            debugger
+                .execute(&mut gb, "search = 4".parse().unwrap())
+                .contains("剩餘 1 個位址")
+        );
+
+        let output = debugger.execute(&mut gb, "search freeze 0 lives".parse().unwrap());
+        assert_eq!(output, "已新增凍結金手指 010423C1");
+        gb.mmu.write_byte(0xC123, 0);
+        gb.run_frame();
+        assert_eq!(gb.mmu.read_byte_ppu(0xC123), 4);
+    }
+
+    #[test]
+    fn stops_at_breakpoint() {
+        let mut gb = GameBoy::with_program(&[0x00, 0x00, 0x00, 0x18, 0xFE]); // NOP x3; JR -2
+        let mut debugger = Debugger::new();
+        debugger.execute(&mut gb, "break c002".parse().unwrap());
+        debugger.execute(&mut gb, Command::Continue);
+
+        assert_eq!(debugger.run_frame(&mut gb), Some(StopReason::Breakpoint(0)));
+        assert_eq!(gb.cpu.pc, 0xC002);
+        assert!(debugger.is_paused());
+    }
+
+    #[test]
+    fn breaks_on_symbol_label() {
+        let mut gb = GameBoy::with_program(&[0x00, 0x00, 0x00, 0x18, 0xFE]);
+        gb.symbols = std::sync::Arc::new(SymbolTable::parse("00:c003 Main.loop\n").unwrap());
+        let mut debugger = Debugger::new();
+
+        assert!(
+            debugger
+                .execute(&mut gb, "break Missing".parse().unwrap())
+                .contains("找不到標籤")
+        );
+        debugger.execute(&mut gb, "break Main.loop".parse().unwrap());
+        debugger.execute(&mut gb, Command::Continue);
+
+        assert_eq!(debugger.run_frame(&mut gb), Some(StopReason::Breakpoint(0)));
+        assert_eq!(gb.cpu.pc, 0xC003);
+        assert!(format_registers(&gb).contains("Main.loop:\n--:C003  JR Main.loop"));
+    }
+
+    #[test]
+    fn hex_like_labels_take_precedence() {
+        let mut gb = GameBoy::with_program(&[0x00, 0x00, 0x00, 0x18, 0xFE]);
+        gb.symbols =
+            std::sync::Arc::new(SymbolTable::parse("00:c002 Add\n00:c003 BEEF\n").unwrap());
+        let mut debugger = Debugger::new();
+
+        assert_eq!(
+            debugger.execute(&mut gb, "break Add".parse().unwrap()),
+            "中斷點 #0 設於 Add (C002)"
+        );
+        assert_eq!(
+            debugger.execute(&mut gb, "break BEEF".parse().unwrap()),
+            "中斷點 #1 設於 BEEF (C003)"
+        );
+        // 數值位址需要 `$` 前綴或以數字開頭；符號表沒有的名稱仍可當作位址
+        assert_eq!(
+            debugger.execute(&mut gb, "break $add".parse().unwrap()),
+            "中斷點 #2 設於 0ADD"
+        );
+        assert_eq!(
+            debugger.execute(&mut gb, "break dead".parse().unwrap()),
+            "中斷點 #3 設於 DEAD"
+        );
+        assert_eq!(
+            "b 0c002".parse(),
+            Ok(Command::Break(Location::Address(Breakpoint {
+                address: 0xC002,
+                bank: None
+            })))
+        );
+    }
+
+    #[test]
+    fn disassembles_unmapped_rom_bank() {
+        let mut gb = GameBoy::with_program(&[]);
+        gb.mmu.mbc_type = 1;
+        gb.mmu.rom = vec![0; 0x10000];
+        gb.mmu.rom[3 * 0x4000..3 * 0x4000 + 3].copy_from_slice(&[0xC3, 0x50, 0x01]);
+        let mut debugger = Debugger::new();
+
+        let output = debugger.execute(&mut gb, "dis 03:4000 1".parse().unwrap());
+        assert_eq!(output, "03:4000  JP $0150");
+        assert_eq!(gb.mmu.rom_bank_at(0x4000), Some(1));
+    }
+
+    #[test]
+    fn banked_breakpoint_requires_matching_bank() {
+        let gb = GameBoy::with_program(&[]);
+        let mut at_4000 = gb.clone();
+        at_4000.cpu.pc = 0x4000;
+
+        // 無 MBC 時 0x4000-0x7FFF 固定為 bank 1
+        assert!(
+            Breakpoint {
+                address: 0x4000,
+                bank: Some(1)
+            }
+            .matches(&at_4000)
+        );
+        assert!(
+            !Breakpoint {
+                address: 0x4000,
+                bank: Some(2)
+            }
+            .matches(&at_4000)
+        );
+    }
+
+    #[test]
+    fn step_over_runs_through_call() {
+        // CALL C010; NOP ... C010: INC A; RET
+        let mut program = vec![0xCD, 0x10, 0xC0, 0x00];
+        program.resize(0x10, 0x00);
+        program.extend_from_slice(&[0x3C, 0xC9]);
+        let mut gb = GameBoy::with_program(&program);
+        let a = gb.cpu.a();
+        let mut debugger = Debugger::new();
+
+        debugger.execute(&mut gb, Command::StepOver);
+        assert_eq!(debugger.run_frame(&mut gb), Some(StopReason::Step));
+        assert_eq!(gb.cpu.pc, 0xC003);
+        assert_eq!(gb.cpu.a(), a.wrapping_add(1));
+    }
+
+    #[test]
+    fn step_out_returns_to_caller() {
+        let mut program = vec![0xCD, 0x10, 0xC0, 0x00];
+        program.resize(0x10, 0x00);
+        program.extend_from_slice(&[0x00, 0x00, 0xC9]);
+        let mut gb = GameBoy::with_program(&program);
+        let mut debugger = Debugger::new();
+
+        debugger.execute(&mut gb, Command::StepIn);
+        assert_eq!(gb.cpu.pc, 0xC010);
+        debugger.execute(&mut gb, Command::StepOut);
+        assert_eq!(debugger.run_frame(&mut gb), Some(StopReason::Step));
+        assert_eq!(gb.cpu.pc, 0xC003);
+    }
+
+    #[test]
+    fn write_watchpoint_stops_after_store() {
+        // LD A,42; LD (C100),A; JR -2
+        let mut gb = GameBoy::with_program(&[0x3E, 0x42, 0xEA, 0x00, 0xC1, 0x18, 0xFE]);
+        let mut debugger = Debugger::new();
+        debugger.execute(&mut gb, "watch c100 w".parse().unwrap());
+        debugger.execute(&mut gb, Command::Continue);
+
+        assert_eq!(
+            debugger.run_frame(&mut gb),
+            Some(StopReason::Watchpoint(WatchHit {
+                address: 0xC100,
+                value: 0x42,
+                write: true
+            }))
+        );
+        assert_eq!(gb.cpu.pc, 0xC005);
+    }
+
+    #[test]
+    fn breakpoint_ids_survive_delete() {
+        let mut gb = GameBoy::with_program(&[0x00, 0x00, 0x00, 0x18, 0xFE]);
+        let mut debugger = Debugger::new();
+        debugger.execute(&mut gb, "break c001".parse().unwrap());
+        debugger.execute(&mut gb, "break c002".parse().unwrap());
+        assert_eq!(
+            debugger.execute(&mut gb, "delete 0".parse().unwrap()),
+            "已刪除中斷點 #0 (C001)"
+        );
+        assert_eq!(debugger.execute(&mut gb, Command::List), "#1 C002");
+        assert!(
+            debugger
+                .execute(&mut gb, "break c003".parse().unwrap())
+                .starts_with("中斷點 #2")
+        );
+
+        debugger.execute(&mut gb, Command::Continue);
+        assert_eq!(debugger.run_frame(&mut gb), Some(StopReason::Breakpoint(1)));
+        assert!(
+            debugger
+                .describe_stop(&gb, StopReason::Breakpoint(1))
+                .starts_with("命中中斷點 #1 (C002)")
+        );
+    }
+
+    #[test]
+    fn interrupt_polling_does_not_hit_watchpoints() {
+        // NOP; LDH A,($0F); JR -4
+        let mut gb = GameBoy::with_program(&[0x00, 0xF0, 0x0F, 0x18, 0xFC]);
+        let mut debugger = Debugger::new();
+        debugger.execute(&mut gb, "watch ff0f r".parse().unwrap());
+        debugger.execute(&mut gb, "watch ffff".parse().unwrap());
+        debugger.execute(&mut gb, Command::Continue);
+
+        let Some(StopReason::Watchpoint(hit)) = debugger.run_frame(&mut gb) else {
+            panic!("應停在監看點");
+        };
+        assert_eq!((hit.address, hit.write), (0xFF0F, false));
+        assert_eq!(gb.cpu.pc, 0xC003);
+    }
+
+    #[test]
+    fn run_to_vblank_stops_at_frame_boundary() {
+        let mut gb = GameBoy::with_program(&[0x18, 0xFE]); // JR -2
+        let mut debugger = Debugger::new();
+        debugger.execute(&mut gb, Command::RunToVBlank);
+
+        assert_eq!(debugger.run_frame(&mut gb), Some(StopReason::VBlank));
+        assert_eq!(gb.mmu.read_byte(0xFF44), 144);
+    }
+
+    #[test]
+    fn set_register_edits_cpu_state() {
+        let mut gb = GameBoy::new();
+        let mut debugger = Debugger::new();
+        debugger.execute(&mut gb, Command::Set(Register::AF, 0x12FF));
+        debugger.execute(&mut gb, Command::Set(Register::PC, 0x0150));
+
+        assert_eq!(gb.cpu.get_af(), 0x12F0);
+        assert_eq!(gb.cpu.pc, 0x0150);
+    }
+}
@@ -0,0 +1,250 @@
+//! 反組譯器
+//!
+//! 依 `Opcodes.json` 的助記符與運算元描述，將記憶體中的位元組轉為文字。
+//! 讀取一律經由 `Mmu::read_byte_ppu`，不受 PPU 模式限制也不會觸發監看點。
+//! 跳轉目標與記憶體位址若在符號表中有標籤，會以標籤取代數值。
+
+use crate::cpu::{self, Cpu, Opcode, Operand};
+use crate::mmu::Mmu;
+use crate::symbols::SymbolTable;
+
+/// 反組譯目前映射到 `address` 的指令，回傳 (文字, 長度)
+pub fn disassemble(mmu: &Mmu, symbols: &SymbolTable, address: u16) -> (String, u16) {
+    decode(
+        |addr| mmu.read_byte_ppu(addr),
+        |target| symbols.label_at(mmu, target).map(str::to_string),
+        address,
+    )
+}
+
+/// 反組譯指定 ROM bank 中的指令 (0x4000-0x7FFF 不受目前映射影響)
+pub fn disassemble_bank(
+    mmu: &Mmu,
+    symbols: &SymbolTable,
+    bank: u16,
+    address: u16,
+) -> (String, u16) {
+    decode(
+        |addr| mmu.read_rom_bank(bank, addr),
+        |target| {
+            let target_bank = if (0x4000..0x8000).contains(&target) {
+                bank
+            } else {
+                crate::symbols::mapped_bank(mmu, target)
+            };
+            symbols
+                .label_in_bank(target_bank, target)
+                .map(str::to_string)
+        },
+        address,
+    )
+}
+
+/// Gameboy Doctor 格式的追蹤行：`A:01 F:B0 B:00 ... SP:FFFE PC:0100 PCMEM:00,C3,13,02`
+pub fn doctor_line(cpu: &Cpu, mmu: &Mmu) -> String {
+    let pc = cpu.pc;
+    let pcmem = |offset: u16| mmu.read_byte_ppu(pc.wrapping_add(offset));
+    format!(
+        "A:{:02X} F:{:02X} B:{:02X} C:{:02X} D:{:02X} E:{:02X} H:{:02X} L:{:02X} SP:{:04X} PC:{:04X} PCMEM:{:02X},{:02X},{:02X},{:02X}",
+        cpu.a(),
+        cpu.f(),
+        cpu.b(),
+        cpu.c(),
+        cpu.d(),
+        cpu.e(),
+        cpu.h(),
+        cpu.l(),
+        cpu.sp,
+        pc,
+        pcmem(0),
+        pcmem(1),
+        pcmem(2),
+        pcmem(3)
+    )
+}
+
+fn decode(
+    read: impl Fn(u16) -> u8,
+    label: impl Fn(u16) -> Option<String>,
+    address: u16,
+) -> (String, u16) {
+    let first = read(address);
+    let (opcode, operand_start) = if first == 0xCB {
+        let second = read(address.wrapping_add(1));
+        (cpu::opcode(true, second), 2)
+    } else {
+        (cpu::opcode(false, first), 1)
+    };
+
+    let Some(opcode) = opcode else {
+        return (format!("DB ${:02X}", first), 1);
+    };
+
+    let length = opcode.bytes.max(operand_start as u8) as u16;
+    let n8 = read(address.wrapping_add(operand_start));
+    let n16 = u16::from_le_bytes([n8, read(address.wrapping_add(operand_start + 1))]);
+    let next = address.wrapping_add(length);
+
+    let operands = format_operands(opcode, n8, n16, next, &label);
+    let text = if operands.is_empty() {
+        opcode.mnemonic.clone()
+    } else {
+        format!("{} {}", opcode.mnemonic, operands.join(", "))
+    };
+    (text, length)
+}
+
+fn format_operands(
+    opcode: &Opcode,
+    n8: u8,
+    n16: u16,
+    next: u16,
+    label: &impl Fn(u16) -> Option<String>,
+) -> Vec<String> {
+    let mut out = Vec::with_capacity(opcode.operands.len());
+    let mut operands = opcode.operands.iter();
+
+    while let Some(operand) = operands.next() {
+        // LD HL, SP+e8：兩個運算元合併為一個
+        if operand.name == "SP" && operand.increment == Some(true) {
+            operands.next();
+            out.push(format!("SP{:+}", n8 as i8));
+            continue;
+        }
+        out.push(format_operand(opcode, operand, n8, n16, next, label));
+    }
+    out
+}
+
+fn format_operand(
+    opcode: &Opcode,
+    operand: &Operand,
+    n8: u8,
+    n16: u16,
+    next: u16,
+    label: &impl Fn(u16) -> Option<String>,
+) -> String {
+    let address = |value: u16| label(value).unwrap_or_else(|| format!("${:04X}", value));
+    let text = match operand.name.as_str() {
+        "n8" => format!("${:02X}", n8),
+        "n16" => format!("${:04X}", n16),
+        "a16" => address(n16),
+        "a8" => address(0xFF00 | n8 as u16),
+        // JR 顯示跳轉目標，ADD SP 顯示有號位移
+        "e8" if opcode.mnemonic == "JR" => address(next.wrapping_add(n8 as i8 as u16)),
+        "e8" => format!("{}", n8 as i8),
+        "C" if operand.immediate == Some(false) => "$FF00+C".to_string(),
+        name => name.to_string(),
+    };
+
+    if operand.immediate == Some(false) {
+        let suffix = if operand.increment == Some(true) {
+            "+"
+        } else if operand.decrement == Some(true) {
+            "-"
+        } else {
+            ""
+        };
+        format!("[{}{}]", text, suffix)
+    } else {
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gameboy::GameBoy;
+
+    fn disassemble_bytes(bytes: &[u8]) -> (String, u16) {
+        let gb = GameBoy::with_program(bytes);
+        disassemble(&gb.mmu, &SymbolTable::default(), 0xC000)
+    }
+
+    #[test]
+    fn formats_operands() {
+        assert_eq!(disassemble_bytes(&[0x00]), ("NOP".to_string(), 1));
+        assert_eq!(
+            disassemble_bytes(&[0x01, 0x34, 0x12]),
+            ("LD BC, $1234".to_string(), 3)
+        );
+        assert_eq!(disassemble_bytes(&[0x22]), ("LD [HL+], A".to_string(), 1));
+        assert_eq!(disassemble_bytes(&[0x3A]), ("LD A, [HL-]".to_string(), 1));
+        assert_eq!(
+            disassemble_bytes(&[0xE0, 0x40]),
+            ("LDH [$FF40], A".to_string(), 2)
+        );
+        assert_eq!(
+            disassemble_bytes(&[0xE2]),
+            ("LDH [$FF00+C], A".to_string(), 1)
+        );
+        assert_eq!(
+            disassemble_bytes(&[0xEA, 0x00, 0xC1]),
+            ("LD [$C100], A".to_string(), 3)
+        );
+        assert_eq!(
+            disassemble_bytes(&[0xCB, 0x7C]),
+            ("BIT 7, H".to_string(), 2)
+        );
+        assert_eq!(disassemble_bytes(&[0xFF]), ("RST $38".to_string(), 1));
+    }
+
+    #[test]
+    fn formats_relative_and_signed_operands() {
+        assert_eq!(
+            disassemble_bytes(&[0x18, 0xFE]),
+            ("JR $C000".to_string(), 2)
+        );
+        assert_eq!(
+            disassemble_bytes(&[0x20, 0x05]),
+            ("JR NZ, $C007".to_string(), 2)
+        );
+        assert_eq!(
+            disassemble_bytes(&[0xE8, 0xFE]),
+            ("ADD SP, -2".to_string(), 2)
+        );
+        assert_eq!(
+            disassemble_bytes(&[0xF8, 0x05]),
+            ("LD HL, SP+5".to_string(), 2)
+        );
+    }
+
+    #[test]
+    fn doctor_line_matches_reference_format() {
+        let mut mmu = Mmu::new();
+        mmu.rom[0x100..0x104].copy_from_slice(&[0x00, 0xC3, 0x13, 0x02]);
+        let cpu = Cpu::new();
+
+        assert_eq!(
+            doctor_line(&cpu, &mmu),
+            "A:01 F:B0 B:00 C:13 D:00 E:D8 H:01 L:4D SP:FFFE PC:0100 PCMEM:00,C3,13,02"
+        );
+    }
+
+    #[test]
+    fn reads_requested_rom_bank() {
+        let mut mmu = Mmu::new();
+        mmu.rom = vec![0; 0x4000 * 4];
+        mmu.rom[0x4000 * 3] = 0xC3; // bank 3: JP $0150
+        mmu.rom[0x4000 * 3 + 1] = 0x50;
+        mmu.rom[0x4000 * 3 + 2] = 0x01;
+
+        let symbols = SymbolTable::default();
+        assert_eq!(disassemble(&mmu, &symbols, 0x4000), ("NOP".to_string(), 1));
+        assert_eq!(
+            disassemble_bank(&mmu, &symbols, 3, 0x4000),
+            ("JP $0150".to_string(), 3)
+        );
+    }
+
+    #[test]
+    fn replaces_addresses_with_labels() {
+        let symbols =
+            SymbolTable::parse("00:c000 Start\n00:c100 wCounter\n00:ff80 hFlag\n").unwrap();
+        let mmu = GameBoy::with_program(&[0x18, 0xFE, 0xEA, 0x00, 0xC1, 0xE0, 0x80]).mmu;
+
+        assert_eq!(disassemble(&mmu, &symbols, 0xC000).0, "JR Start");
+        assert_eq!(disassemble(&mmu, &symbols, 0xC002).0, "LD [wCounter], A");
+        assert_eq!(disassemble(&mmu, &symbols, 0xC005).0, "LDH [hFlag], A");
+    }
+}
@@ -1,24 +1,28 @@
 // Game Boy 模擬器主結構
 
-use crate::apu::Apu;
+use crate::cheats::Cheats;
 use crate::cpu::Cpu;
-use crate::joypad::Joypad;
-use crate::mmu::{IoHandler, Mmu};
-use crate::ppu::Ppu;
-use crate::timer::Timer;
-use std::time::Instant;
+use crate::disasm;
+use crate::joypad::JoypadKey;
+use crate::mmu::Mmu;
+use crate::profiler::{Profiler, StepStart};
+use crate::rom::RomOptions;
+use crate::symbols::SymbolTable;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 // Custom error types for better error handling (Rust 1.93.0 improvements)
 #[derive(Debug)]
-#[allow(dead_code)]
 pub enum GameBoyError {
     RomLoad {
         path: String,
         source: Box<dyn std::error::Error>,
     },
-    Timing(String),
-    Interrupt(String),
-    Io(std::io::Error),
+    BootRom {
+        path: String,
+        source: std::io::Error,
+    },
 }
 
 impl std::fmt::Display for GameBoyError {
@@ -27,9 +31,9 @@ impl std::fmt::Display for GameBoyError {
             GameBoyError::RomLoad { path, source } => {
                 write!(f, "Failed to load ROM '{}': {}", path, source)
             }
-            GameBoyError::Timing(msg) => write!(f, "Timing error: {}", msg),
-            GameBoyError::Interrupt(msg) => write!(f, "Interrupt error: {}", msg),
-            GameBoyError::Io(err) => write!(f, "I/O error: {}", err),
+            GameBoyError::BootRom { path, source } => {
+                write!(f, "Failed to load boot ROM '{}': {}", path, source)
+            }
         }
     }
 }
@@ -38,203 +42,90 @@ impl std::error::Error for GameBoyError {
     fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
         match self {
             GameBoyError::RomLoad { source, .. } => Some(source.as_ref()),
-            GameBoyError::Io(err) => Some(err),
-            _ => None,
+            GameBoyError::BootRom { source, .. } => Some(source),
         }
     }
 }
 
-impl From<std::io::Error> for GameBoyError {
-    fn from(err: std::io::Error) -> Self {
-        GameBoyError::Io(err)
-    }
+/// 模擬的機種，決定沒有開機 ROM 時的初始暫存器值
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Model {
+    #[default]
+    Dmg,
+    Mgb, // Game Boy Pocket
+    Sgb,
 }
 
-/// Game Boy interrupt types
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub enum InterruptType {
-    VBlank,
-    LcdStat,
-    Timer,
-    Serial,
-    Joypad,
-}
-
-/// Joypad interrupt delay tracking
-struct JoypadInterruptDelay {
-    cycles_remaining: u8,
-}
+impl std::str::FromStr for Model {
+    type Err = String;
 
-/// Interrupt handler
-pub struct InterruptHandler {
-    pub ie_register: u8,
-    pub if_register: u8,
-    joypad_interrupt_delay: Option<JoypadInterruptDelay>,
-}
-
-struct GameBoyIoWrapper {
-    ppu: *const Ppu,
-    apu: *const Apu,
-    timer: *const Timer,
-    joypad: *const Joypad,
-    interrupt_handler: *const InterruptHandler,
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_ascii_lowercase().as_str() {
+            "dmg" => Ok(Model::Dmg),
+            "mgb" | "pocket" => Ok(Model::Mgb),
+            "sgb" => Ok(Model::Sgb),
+            _ => Err(format!("未知的機種: {} (可用 dmg、mgb、sgb)", text)),
+        }
+    }
 }
 
-impl GameBoyIoWrapper {
-    fn new(
-        ppu: &Ppu,
-        apu: &Apu,
-        timer: &Timer,
-        joypad: &Joypad,
-        interrupt_handler: &InterruptHandler,
-    ) -> Self {
-        GameBoyIoWrapper {
-            ppu: std::ptr::from_ref(ppu),
-            apu: std::ptr::from_ref(apu),
-            timer: std::ptr::from_ref(timer),
-            joypad: std::ptr::from_ref(joypad),
-            interrupt_handler: std::ptr::from_ref(interrupt_handler),
+impl From<Model> for String {
+    fn from(model: Model) -> Self {
+        match model {
+            Model::Dmg => "dmg",
+            Model::Mgb => "mgb",
+            Model::Sgb => "sgb",
         }
+        .to_string()
     }
 }
 
-impl IoHandler for GameBoyIoWrapper {
-    fn read_io(&self, address: u16) -> u8 {
-        unsafe {
-            match address {
-                0xFF00 => {
-                    if !self.joypad.is_null() {
-                        (*self.joypad).read_register()
-                    } else {
-                        0xFF
-                    }
-                }
-                0xFF04..=0xFF07 => {
-                    if !self.timer.is_null() {
-                        (*self.timer).read_register(address)
-                    } else {
-                        0
-                    }
-                }
-                0xFF10..=0xFF3F => {
-                    if !self.apu.is_null() {
-                        (*self.apu).read_register(address)
-                    } else {
-                        0
-                    }
-                }
-                0xFF40..=0xFF4B => {
-                    if !self.ppu.is_null() {
-                        (*self.ppu).read_register(address)
-                    } else {
-                        0
-                    }
-                }
-                0xFF0F => {
-                    if !self.interrupt_handler.is_null() {
-                        (*self.interrupt_handler).if_register | 0xE0
-                    } else {
-                        0xE0
-                    }
-                }
-                0xFFFF => {
-                    if !self.interrupt_handler.is_null() {
-                        (*self.interrupt_handler).ie_register
-                    } else {
-                        0
-                    }
-                }
-                _ => 0,
-            }
-        }
+impl TryFrom<String> for Model {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        text.parse()
     }
+}
 
-    fn write_io(&mut self, address: u16, value: u8, interrupt_flags: &mut u8) {
-        unsafe {
-            match address {
-                0xFF00 => {
-                    if !self.joypad.is_null() {
-                        let joypad = self.joypad as *mut Joypad;
-                        (*joypad).write_register(value);
-                    }
-                }
-                0xFF04..=0xFF07 => {
-                    if !self.timer.is_null() {
-                        let timer = self.timer as *mut Timer;
-                        (*timer).write_register(address, value, interrupt_flags);
-                    }
-                }
-                0xFF10..=0xFF3F => {
-                    if !self.apu.is_null() {
-                        let apu = self.apu as *mut Apu;
-                        (*apu).write_register(address, value);
-                    }
-                }
-                0xFF40..=0xFF4B => {
-                    if !self.ppu.is_null() {
-                        let ppu = self.ppu as *mut Ppu;
-                        (*ppu).write_register(address, value, interrupt_flags);
-                    }
-                }
-                0xFF0F => {
-                    if !self.interrupt_handler.is_null() {
-                        let handler = self.interrupt_handler as *mut InterruptHandler;
-                        (*handler).if_register = value | 0xE0;
-                    }
-                }
-                0xFFFF => {
-                    if !self.interrupt_handler.is_null() {
-                        let handler = self.interrupt_handler as *mut InterruptHandler;
-                        (*handler).ie_register = value;
-                    }
-                }
-                _ => {}
-            }
+impl Model {
+    // 開機 ROM 結束時的 AF、BC、DE、HL
+    fn boot_registers(self) -> [u16; 4] {
+        match self {
+            Model::Dmg => [0x01B0, 0x0013, 0x00D8, 0x014D],
+            Model::Mgb => [0xFFB0, 0x0013, 0x00D8, 0x014D],
+            Model::Sgb => [0x0100, 0x0014, 0x0000, 0xC060],
         }
     }
 }
 
+// 所有狀態皆為一般擁有權 (周邊由 MMU 擁有)，因此可以安全地移動與複製
+#[derive(Clone)]
 pub struct GameBoy {
     pub cpu: Cpu,
     pub mmu: Mmu,
-    pub ppu: Ppu,
-    pub apu: Apu,
-    pub timer: Timer,
-    pub joypad: Joypad,
-    pub interrupt_handler: InterruptHandler,
     #[allow(dead_code)]
     pub cycles: u64,
+    pub trace: Option<String>, // Gameboy Doctor 格式的追蹤紀錄，由前端定期取出
+    pub trace_labels: bool,    // 追蹤紀錄在有標籤的位址前插入 `label:` 行 (不再是 Doctor 格式)
+    pub symbols: Arc<SymbolTable>, // RGBDS 符號表 (唯讀，複製時共用)
+    pub profiler: Option<Profiler>, // 啟用時記錄每一步的週期分布
+    pub cheat_path: Option<PathBuf>, // 與 ROM 同名的 .cht 金手指檔
 }
 
 impl GameBoy {
-    pub fn new() -> Box<Self> {
-        let mut gb = Box::new(GameBoy {
+    pub fn new() -> Self {
+        let mut gb = GameBoy {
             cpu: Cpu::new(),
             mmu: Mmu::new(),
-            ppu: Ppu::new(),
-            apu: Apu::new(),
-            timer: Timer::new(),
-            joypad: Joypad::new(),
-            interrupt_handler: InterruptHandler::new(),
             cycles: 0,
-        });
-
-        // 設置 I/O 處理器
-        let io_wrapper = GameBoyIoWrapper::new(
-            &gb.ppu,
-            &gb.apu,
-            &gb.timer,
-            &gb.joypad,
-            &gb.interrupt_handler,
-        );
-        gb.mmu.set_io_handler(Box::new(io_wrapper));
-
-        // 讓 MMU 能依 PPU mode 套用 VRAM/OAM CPU 存取限制
-        gb.mmu.set_ppu(&gb.ppu);
-
-        // 設置組件間的引用
-        gb.joypad
-            .set_interrupt_handler(&mut gb.interrupt_handler as *mut InterruptHandler);
+            trace: None,
+            trace_labels: false,
+            symbols: Arc::default(),
+            profiler: None,
+            cheat_path: None,
+        };
 
         // 設置初始硬體狀態 (模擬啟動後狀態)
         gb.mmu.write_byte(0xFFFF, 0x00); // 關閉所有中斷
@@ -245,23 +136,112 @@ impl GameBoy {
         gb
     }
 
-    // 載入 ROM
-    pub fn load_rom(&mut self, path: &str) -> Result<(), GameBoyError> {
-        self.mmu.load_rom(path).map_err(|e| GameBoyError::RomLoad {
-            path: path.to_string(),
-            source: e,
-        })?;
+    // 以指定機種開機後的暫存器值取代預設的 DMG 值
+    pub fn set_model(&mut self, model: Model) {
+        self.set_registers(model.boot_registers());
+    }
 
-        self.interrupt_handler.auto_configure_for_game(
-            std::path::Path::new(path)
-                .file_name()
-                .and_then(|n| n.to_str())
-                .unwrap_or(""),
-        );
+    // 載入 256 bytes 的 DMG 開機 ROM，從 0x0000 開始執行 (暫存器與 LCD 由開機 ROM 設定)
+    pub fn load_boot_rom(&mut self, path: &Path) -> Result<(), GameBoyError> {
+        let error = |source| GameBoyError::BootRom {
+            path: path.display().to_string(),
+            source,
+        };
+        let data = std::fs::read(path).map_err(error)?;
+        if data.len() != 0x100 {
+            return Err(error(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                format!("開機 ROM 應為 256 bytes，實際為 {} bytes", data.len()),
+            )));
+        }
 
+        self.mmu.boot_rom = Some(data);
+        self.mmu.write_byte(0xFF40, 0x00);
+        self.cpu.pc = 0x0000;
+        self.cpu.sp = 0x0000;
+        self.set_registers([0; 4]);
         Ok(())
     }
 
+    fn set_registers(&mut self, [af, bc, de, hl]: [u16; 4]) {
+        self.cpu.set_af(af);
+        self.cpu.set_bc(bc);
+        self.cpu.set_de(de);
+        self.cpu.set_hl(hl);
+    }
+
+    // 載入 ROM (可為 zip/gz/7z 壓縮檔)，並套用修補檔
+    pub fn load_rom(&mut self, path: &str, options: &RomOptions) -> Result<(), GameBoyError> {
+        // 外部 RAM 存檔與 ROM 同名，可用 --save-dir 指定其他目錄
+        let save_path = Path::new(path).with_extension("sav");
+        self.mmu.save_path = Some(match (&options.save_dir, save_path.file_name()) {
+            (Some(dir), Some(name)) => dir.join(name),
+            _ => save_path,
+        });
+
+        self.mmu
+            .load_rom(path, options)
+            .map_err(|e| GameBoyError::RomLoad {
+                path: path.to_string(),
+                source: e,
+            })?;
+
+        // 與 ROM 同名的 .sym 檔 (RGBDS 輸出) 存在時一併載入
+        let sym_path = std::path::Path::new(path).with_extension("sym");
+        if sym_path.exists() {
+            match SymbolTable::load(&sym_path) {
+                Ok(symbols) => {
+                    println!("載入符號檔: {}", sym_path.display());
+                    self.symbols = Arc::new(symbols);
+                }
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+
+        // 與 ROM 同名的 .cht 金手指檔
+        let cheat_path = std::path::Path::new(path).with_extension("cht");
+        if cheat_path.exists() {
+            match Cheats::load(&cheat_path) {
+                Ok(cheats) => {
+                    println!("載入金手指: {}", cheat_path.display());
+                    self.mmu.cheats = cheats;
+                }
+                Err(e) => eprintln!("{}", e),
+            }
+        }
+        self.cheat_path = Some(cheat_path);
+
+        Ok(())
+    }
+
+    // 金手指有變更時寫回 .cht 檔
+    pub fn save_cheats(&mut self) {
+        let Some(path) = self.cheat_path.as_ref() else {
+            return;
+        };
+        if !self.mmu.cheats.is_modified() {
+            return;
+        }
+        match self.mmu.cheats.save(path) {
+            Ok(()) => println!("已儲存金手指: {}", path.display()),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+
+    // 更新按鍵狀態，按鍵下降緣時由 Joypad 請求中斷
+    pub fn set_key(&mut self, key: JoypadKey, pressed: bool) {
+        self.mmu
+            .joypad
+            .set_key(key, pressed, &mut self.mmu.interrupts);
+    }
+
+    // 一次設定所有按鍵 (供影片錄製與播放)
+    pub fn set_joypad(&mut self, pressed: u8) {
+        self.mmu
+            .joypad
+            .set_pressed(pressed, &mut self.mmu.interrupts);
+    }
+
     // 執行指定數量的時鐘循環 (用於分幀輸入處理優化)
     #[allow(dead_code)]
     pub fn run_cycles(&mut self, target_cycles: u64) {
@@ -277,54 +257,83 @@ impl GameBoy {
     // 執行到一幀完成（進入 VBlank）為止，確保畫面呈現穩定
     pub fn run_frame(&mut self) {
         // 清掉上一幀可能殘留的 ready
-        let _ = self.ppu.take_frame_ready();
+        let _ = self.mmu.ppu.take_frame_ready();
 
         // 以「進入 VBlank 的升緣」作為幀完成訊號：不會被指令邊界漏掉
         loop {
             let _ = self.step_cpu_with_timing();
 
-            if self.ppu.take_frame_ready() {
+            if self.mmu.ppu.take_frame_ready() {
                 break;
             }
         }
     }
 
+    // 測試用：將程式寫入 WRAM，從 0xC000 開始執行 (SP = 0xDFF0)
+    #[cfg(test)]
+    pub fn with_program(program: &[u8]) -> Self {
+        let mut gb = GameBoy::new();
+        for (i, &byte) in program.iter().enumerate() {
+            gb.mmu.write_byte(0xC000 + i as u16, byte);
+        }
+        gb.cpu.pc = 0xC000;
+        gb.cpu.sp = 0xDFF0;
+        gb
+    }
+
+    // 啟用追蹤紀錄：每個執行的指令記錄一行
+    pub fn enable_trace(&mut self) {
+        self.trace = Some(String::new());
+    }
+
+    // 啟用效能分析器
+    pub fn enable_profiler(&mut self) {
+        self.profiler = Some(Profiler::new());
+    }
+
+    // 取出目前累積的追蹤紀錄
+    pub fn take_trace(&mut self) -> String {
+        self.trace.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
     pub fn get_present_framebuffer(&self) -> &[u8] {
-        self.ppu.get_present_framebuffer()
+        self.mmu.ppu.get_present_framebuffer()
     }
 
     // 執行一個 CPU 指令，並在執行期間同步更新 Timer 和 PPU
-    fn step_cpu_with_timing(&mut self) -> u32 {
-        // 同步中斷處理器與 MMU
-        self.interrupt_handler.ie_register = self.mmu.read_byte(0xFFFF);
-        self.interrupt_handler.if_register = self.mmu.read_byte(0xFF0F);
+    pub fn step_cpu_with_timing(&mut self) -> u32 {
+        let trace_line = self.trace.is_some().then(|| {
+            let line = disasm::doctor_line(&self.cpu, &self.mmu);
+            match self.symbols.label_at(&self.mmu, self.cpu.pc) {
+                Some(label) if self.trace_labels => format!("{}:\n{}", label, line),
+                _ => line,
+            }
+        });
+        let instructions = self.cpu.instr_count;
+        let profile_start = self
+            .profiler
+            .is_some()
+            .then(|| StepStart::capture(&self.cpu, &self.mmu));
 
         // 執行 CPU 指令
         let cycles = self.cpu.step(&mut self.mmu);
 
-        // 同步中斷處理器 FROM MMU（CPU 執行期間可能修改了 IF/IE）
-        self.interrupt_handler.ie_register = self.mmu.read_byte(0xFFFF);
-        self.interrupt_handler.if_register = self.mmu.read_byte(0xFF0F);
+        // 只記錄實際執行的指令 (HALT 等待與中斷分派不記錄)
+        if let (Some(trace), Some(line)) = (self.trace.as_mut(), trace_line)
+            && self.cpu.instr_count != instructions
+        {
+            trace.push_str(&line);
+            trace.push('\n');
+        }
 
-        // 批量更新 PPU 和 Timer
-        let mut if_reg = self.interrupt_handler.if_register;
+        // 批量更新 PPU、Timer 和 APU (中斷直接寫入 MMU 的中斷控制器)
         for _cycle in 0..cycles {
-            self.ppu.tick(&self.mmu, &mut if_reg);
-            self.timer.tick(&mut if_reg);
-            self.apu.tick();
-
-            // 處理 joypad 中斷延遲
-            if self.interrupt_handler.process_joypad_interrupt_delay() {
-                if_reg |= 0x10; // Joypad interrupt flag
-            }
+            self.mmu.tick();
         }
 
-        // 更新中斷處理器
-        self.interrupt_handler.if_register = if_reg;
-
-        // 同步回 MMU
-        self.mmu.write_byte(0xFF0F, if_reg);
-        self.mmu.if_reg = if_reg;
+        if let (Some(profiler), Some(start)) = (self.profiler.as_mut(), profile_start) {
+            profiler.record(start, &self.cpu, &self.mmu, cycles);
+        }
 
         cycles
     }
@@ -332,106 +341,91 @@ impl GameBoy {
     // 獲取當前畫面緩衝區
     #[allow(dead_code)]
     pub fn get_framebuffer(&self) -> &[u8] {
-        self.ppu.get_framebuffer()
+        self.mmu.ppu.get_framebuffer()
     }
 }
 
-impl InterruptHandler {
-    pub fn new() -> Self {
-        Self {
-            ie_register: 0,
-            if_register: 0xE0,
-            joypad_interrupt_delay: None,
-        }
-    }
-
-    pub fn has_pending_interrupts(&self) -> bool {
-        (self.ie_register & self.if_register & 0x1F) != 0
-    }
-
-    pub fn get_highest_priority_interrupt(&self) -> Option<(InterruptType, u16)> {
-        let pending = self.ie_register & self.if_register & 0x1F;
-        if pending == 0 {
-            return None;
-        }
+#[cfg(test)]
+mod tests {
+    use super::{Arc, GameBoy, Model, SymbolTable};
 
-        // Game Boy 中斷優先級：VBlank > LCD > Timer > Serial > Joypad
-        for bit in 0..5 {
-            if (pending & (1 << bit)) != 0 {
-                let interrupt_type = match bit {
-                    0 => InterruptType::VBlank,
-                    1 => InterruptType::LcdStat,
-                    2 => InterruptType::Timer,
-                    3 => InterruptType::Serial,
-                    4 => InterruptType::Joypad,
-                    _ => continue,
-                };
-                let vector = match bit {
-                    0 => 0x40,
-                    1 => 0x48,
-                    2 => 0x50,
-                    3 => 0x58,
-                    4 => 0x60,
-                    _ => 0,
-                };
-                return Some((interrupt_type, vector));
-            }
-        }
-        None
-    }
+    #[test]
+    fn cloned_gameboy_runs_independently() {
+        let mut gb = GameBoy::new();
+        gb.run_frame();
 
-    pub fn trigger_interrupt(&mut self, interrupt_type: InterruptType) {
-        let bit = match interrupt_type {
-            InterruptType::VBlank => 0,
-            InterruptType::LcdStat => 1,
-            InterruptType::Timer => 2,
-            InterruptType::Serial => 3,
-            InterruptType::Joypad => 4,
-        };
+        // 移動到堆積後再複製，兩者不應共享任何狀態
+        let original = Box::new(gb);
+        let mut copy = (*original).clone();
+        copy.mmu.write_byte(0xC000, 0x42);
+        copy.run_frame();
 
-        if matches!(interrupt_type, InterruptType::Joypad) {
-            if self.joypad_interrupt_delay.is_none() {
-                self.joypad_interrupt_delay = Some(JoypadInterruptDelay {
-                    cycles_remaining: 4,
-                });
-            }
-        } else {
-            self.if_register = (self.if_register | (1 << bit)) | 0xE0;
-        }
+        assert_eq!(original.mmu.read_byte(0xC000), 0x00);
+        assert_eq!(copy.mmu.read_byte(0xC000), 0x42);
+        assert_ne!(original.cpu.pc, copy.cpu.pc);
     }
 
-    pub fn process_joypad_interrupt_delay(&mut self) -> bool {
-        if let Some(ref mut delay) = self.joypad_interrupt_delay {
-            delay.cycles_remaining = delay.cycles_remaining.saturating_sub(1);
-            if delay.cycles_remaining == 0 {
-                self.if_register = (self.if_register | 0x10) | 0xE0;
-                self.joypad_interrupt_delay = None;
-                return true;
-            }
-        }
-        false
+    #[test]
+    fn trace_records_one_line_per_instruction() {
+        let mut gb = GameBoy::new();
+        gb.enable_trace();
+        gb.step_cpu_with_timing();
+        gb.step_cpu_with_timing();
+
+        let trace = gb.take_trace();
+        let lines: Vec<&str> = trace.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("A:01 F:B0 B:00 C:13"));
+        assert!(lines[0].contains("PC:0100 PCMEM:00,00,00,00"));
+        assert!(lines[1].contains("PC:0101"));
+        assert!(gb.take_trace().is_empty());
     }
 
-    pub fn clear_interrupt_flag(&mut self, interrupt_type: InterruptType) {
-        let bit = match interrupt_type {
-            InterruptType::VBlank => 0,
-            InterruptType::LcdStat => 1,
-            InterruptType::Timer => 2,
-            InterruptType::Serial => 3,
-            InterruptType::Joypad => 4,
-        };
-        self.if_register = (self.if_register & !(1 << bit)) | 0xE0;
+    #[test]
+    fn trace_labels_are_opt_in() {
+        let mut gb = GameBoy::new();
+        gb.symbols = Arc::new(SymbolTable::parse("00:0100 Start\n").unwrap());
+        gb.enable_trace();
+        let mut labeled = gb.clone();
+        labeled.trace_labels = true;
+
+        gb.step_cpu_with_timing();
+        assert!(gb.take_trace().starts_with("A:01 "));
+        labeled.step_cpu_with_timing();
+        assert!(labeled.take_trace().starts_with("Start:\nA:01 "));
     }
 
-    pub fn acknowledge_interrupt(&mut self, interrupt_type: InterruptType, _start_time: Instant) {
-        self.clear_interrupt_flag(interrupt_type);
+    #[test]
+    fn model_sets_post_boot_registers() {
+        let mut gb = GameBoy::new();
+        gb.set_model("pocket".parse().unwrap());
+        assert_eq!(gb.cpu.get_af(), 0xFFB0);
+        gb.set_model(Model::Sgb);
+        assert_eq!(gb.cpu.get_af(), 0x0100);
+        assert_eq!(gb.cpu.get_hl(), 0xC060);
+        assert!("cgb".parse::<Model>().is_err());
     }
 
-    pub fn auto_configure_for_game(&mut self, _rom_filename: &str) {}
-}
-
-impl Default for InterruptHandler {
-    fn default() -> Self {
-        Self::new()
+    #[test]
+    fn boot_rom_is_unmapped_by_ff50_write() {
+        let path = std::env::temp_dir().join(format!("rust_gb_boot_{}.bin", std::process::id()));
+        // LD A,1 ; LDH (0x50),A
+        let mut boot = vec![0u8; 0x100];
+        boot[..4].copy_from_slice(&[0x3E, 0x01, 0xE0, 0x50]);
+        std::fs::write(&path, &boot).unwrap();
+
+        let mut gb = GameBoy::new();
+        gb.mmu.rom[0x0000] = 0xAA;
+        gb.load_boot_rom(&path).unwrap();
+        assert_eq!(gb.cpu.pc, 0x0000);
+        assert_eq!(gb.mmu.read_byte(0x0000), 0x3E);
+
+        gb.step_cpu_with_timing();
+        gb.step_cpu_with_timing();
+        assert_eq!(gb.mmu.read_byte(0x0000), 0xAA);
+
+        std::fs::write(&path, [0u8; 16]).unwrap();
+        assert!(gb.load_boot_rom(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
     }
 }
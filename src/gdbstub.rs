@@ -0,0 +1,727 @@
+//! GDB Remote Serial Protocol 伺服器
+//!
+//! 讓 gdb/lldb 或支援 RSP 的 IDE 透過 TCP 連線到執行中的遊戲。
+//! 暫存器以 AF、BC、DE、HL、SP、PC (各 16 位元，little-endian) 的順序提供，
+//! 記憶體以 `Mmu::poke` 直接寫入 (修改 ROM 而非切換 MBC，不觸發 I/O 副作用)，
+//! 中斷點與監看點沿用 `Debugger` 的實作；GDB 設定的中斷點另外記錄編號，`z0` 只移除自己設定的中斷點。
+//! 連線採用非阻塞 I/O，由主迴圈每幀呼叫 `poll()`，不需要額外的執行緒。
+
+use crate::debugger::{Breakpoint, Command, Debugger, StopReason, WatchKind, Watchpoint};
+use crate::gameboy::GameBoy;
+use std::io::{ErrorKind, Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+const TARGET_XML: &str = r#"<?xml version="1.0"?>
+<!DOCTYPE target SYSTEM "gdb-target.dtd">
+<target version="1.0">
+  <feature name="org.gnu.gdb.sm83.core">
+    <reg name="af" bitsize="16" type="uint16" regnum="0"/>
+    <reg name="bc" bitsize="16" type="uint16"/>
+    <reg name="de" bitsize="16" type="uint16"/>
+    <reg name="hl" bitsize="16" type="uint16"/>
+    <reg name="sp" bitsize="16" type="data_ptr"/>
+    <reg name="pc" bitsize="16" type="code_ptr"/>
+  </feature>
+</target>"#;
+
+const REGISTER_COUNT: usize = 6;
+
+/// qSupported 宣告的封包大小上限 (不含 `$`、`#` 與校驗和)
+const PACKET_SIZE: usize = 0x4000;
+
+/// 監聽 TCP 埠，同一時間只服務一個客戶端
+#[derive(Debug)]
+pub struct GdbServer {
+    listener: TcpListener,
+    client: Option<GdbConnection>,
+}
+
+impl GdbServer {
+    pub fn bind(port: u16) -> std::io::Result<Self> {
+        let listener = TcpListener::bind(("127.0.0.1", port))?;
+        listener.set_nonblocking(true)?;
+        Ok(GdbServer {
+            listener,
+            client: None,
+        })
+    }
+
+    #[allow(dead_code)]
+    pub fn local_port(&self) -> std::io::Result<u16> {
+        Ok(self.listener.local_addr()?.port())
+    }
+
+    #[allow(dead_code)]
+    pub fn is_connected(&self) -> bool {
+        self.client.is_some()
+    }
+
+    /// 接受新連線並處理所有已到達的封包
+    pub fn poll(&mut self, debugger: &mut Debugger, gb: &mut GameBoy) {
+        if self.client.is_none() {
+            match self.listener.accept() {
+                Ok((stream, addr)) => match GdbConnection::new(stream) {
+                    Ok(connection) => {
+                        println!("GDB 已連線: {}", addr);
+                        // 連線後暫停，等待客戶端下達命令
+                        debugger.execute(gb, Command::Pause);
+                        self.client = Some(connection);
+                    }
+                    Err(e) => eprintln!("GDB 連線設定失敗: {}", e),
+                },
+                Err(e) if e.kind() == ErrorKind::WouldBlock => {}
+                Err(e) => eprintln!("GDB 接受連線失敗: {}", e),
+            }
+        }
+
+        if let Some(client) = self.client.as_mut()
+            && !client.poll(debugger, gb)
+        {
+            println!("GDB 已中斷連線");
+            // 移除這個客戶端設定的中斷點
+            for (_, id) in client.breakpoints.drain(..) {
+                debugger.execute(gb, Command::Delete(id));
+            }
+            self.client = None;
+            // 客戶端離開後讓遊戲繼續執行
+            debugger.execute(gb, Command::Continue);
+        }
+    }
+
+    /// 除錯器在執行中停止時回報停止原因
+    pub fn report_stop(&mut self, gb: &GameBoy, reason: StopReason) {
+        if let Some(client) = self.client.as_mut()
+            && client.send_packet(&stop_reply(gb, reason)).is_err()
+        {
+            self.client = None;
+        }
+    }
+}
+
+#[derive(Debug)]
+struct GdbConnection {
+    stream: TcpStream,
+    buffer: Vec<u8>,
+    no_ack: bool,
+    breakpoints: Vec<(u16, usize)>, // GDB 設定的中斷點：(位址, Debugger 的中斷點編號)
+}
+
+impl GdbConnection {
+    fn new(stream: TcpStream) -> std::io::Result<Self> {
+        stream.set_nonblocking(true)?;
+        stream.set_nodelay(true)?;
+        Ok(GdbConnection {
+            stream,
+            buffer: Vec::new(),
+            no_ack: false,
+            breakpoints: Vec::new(),
+        })
+    }
+
+    // 讀取並處理封包；連線關閉時回傳 false
+    fn poll(&mut self, debugger: &mut Debugger, gb: &mut GameBoy) -> bool {
+        let mut chunk = [0u8; 4096];
+        loop {
+            match self.stream.read(&mut chunk) {
+                Ok(0) => return false,
+                Ok(n) => self.buffer.extend_from_slice(&chunk[..n]),
+                Err(e) if e.kind() == ErrorKind::WouldBlock => break,
+                Err(e) if e.kind() == ErrorKind::Interrupted => continue,
+                Err(_) => return false,
+            }
+        }
+
+        while let Some(packet) = self.next_packet() {
+            let reply = match packet {
+                Incoming::Interrupt => {
+                    if debugger.is_paused() {
+                        continue;
+                    }
+                    debugger.execute(gb, Command::Pause);
+                    Some("S02".to_string())
+                }
+                Incoming::BadChecksum => {
+                    // 要求客戶端重送；no-ack 模式下直接丟棄
+                    if !self.no_ack && self.write_all(b"-").is_err() {
+                        return false;
+                    }
+                    continue;
+                }
+                Incoming::Packet(data) => {
+                    if !self.no_ack && self.write_all(b"+").is_err() {
+                        return false;
+                    }
+                    match handle_packet(&data, debugger, gb, &mut self.breakpoints) {
+                        Reply::Send(reply) => {
+                            if data == "QStartNoAckMode" {
+                                let sent = self.send_packet(&reply);
+                                self.no_ack = true;
+                                if sent.is_err() {
+                                    return false;
+                                }
+                                continue;
+                            }
+                            Some(reply)
+                        }
+                        Reply::Deferred => None,
+                        Reply::Close(reply) => {
+                            let _ = self.send_packet(&reply);
+                            return false;
+                        }
+                    }
+                }
+            };
+            if let Some(reply) = reply
+                && self.send_packet(&reply).is_err()
+            {
+                return false;
+            }
+        }
+        true
+    }
+
+    // 從緩衝區取出一個完整封包並驗證 #xx 校驗和，忽略 ack 字元
+    fn next_packet(&mut self) -> Option<Incoming> {
+        loop {
+            let first = *self.buffer.first()?;
+            match first {
+                b'$' => {
+                    let end = self.buffer.iter().position(|&b| b == b'#')?;
+                    if self.buffer.len() < end + 3 {
+                        return None;
+                    }
+                    let payload = &self.buffer[1..end];
+                    let expected = std::str::from_utf8(&self.buffer[end + 1..end + 3])
+                        .ok()
+                        .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                    let packet = if expected == Some(checksum(payload)) {
+                        Incoming::Packet(String::from_utf8_lossy(payload).into_owned())
+                    } else {
+                        Incoming::BadChecksum
+                    };
+                    self.buffer.drain(..end + 3);
+                    return Some(packet);
+                }
+                0x03 => {
+                    self.buffer.remove(0);
+                    return Some(Incoming::Interrupt);
+                }
+                _ => {
+                    self.buffer.remove(0);
+                }
+            }
+        }
+    }
+
+    fn send_packet(&mut self, data: &str) -> std::io::Result<()> {
+        let packet = format!("${}#{:02x}", data, checksum(data.as_bytes()));
+        self.write_all(packet.as_bytes())
+    }
+
+    // 非阻塞 socket 上的 write_all：緩衝區滿時重試
+    fn write_all(&mut self, mut data: &[u8]) -> std::io::Result<()> {
+        while !data.is_empty() {
+            match self.stream.write(data) {
+                Ok(0) => return Err(ErrorKind::WriteZero.into()),
+                Ok(n) => data = &data[n..],
+                Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::Interrupted) => {
+                    std::thread::yield_now();
+                }
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+}
+
+// 封包內容的校驗和：所有位元組相加取低 8 位元
+fn checksum(data: &[u8]) -> u8 {
+    data.iter().fold(0u8, |sum, &b| sum.wrapping_add(b))
+}
+
+#[derive(Debug)]
+enum Incoming {
+    Packet(String),
+    BadChecksum,
+    Interrupt, // Ctrl-C (0x03)
+}
+
+#[derive(Debug)]
+enum Reply {
+    Send(String),
+    Deferred, // continue：停止時才由 report_stop 回覆
+    Close(String),
+}
+
+fn handle_packet(
+    data: &str,
+    debugger: &mut Debugger,
+    gb: &mut GameBoy,
+    breakpoints: &mut Vec<(u16, usize)>,
+) -> Reply {
+    let reply = match data.as_bytes().first() {
+        Some(b'?') => "S05".to_string(),
+        Some(b'g') => read_registers(gb),
+        Some(b'G') => ok_or_error(write_registers(gb, &data[1..])),
+        Some(b'p') => match parse_hex(&data[1..]).map(|n| n as usize) {
+            Some(n) if n < REGISTER_COUNT => hex_u16(register_values(gb)[n]),
+            _ => "E01".to_string(),
+        },
+        Some(b'P') => ok_or_error(write_register(gb, &data[1..])),
+        Some(b'm') => read_memory(gb, &data[1..]).unwrap_or_else(|| "E01".to_string()),
+        Some(b'M') => ok_or_error(write_memory(gb, &data[1..])),
+        Some(b'c') => {
+            if let Some(address) = parse_hex(&data[1..]) {
+                gb.cpu.pc = address;
+            }
+            debugger.execute(gb, Command::Continue);
+            return Reply::Deferred;
+        }
+        Some(b's') => {
+            if let Some(address) = parse_hex(&data[1..]) {
+                gb.cpu.pc = address;
+            }
+            debugger.execute(gb, Command::StepIn);
+            stop_reply(gb, StopReason::Step)
+        }
+        Some(b'Z') => ok_or_empty(set_point(debugger, gb, breakpoints, &data[1..], true)),
+        Some(b'z') => ok_or_empty(set_point(debugger, gb, breakpoints, &data[1..], false)),
+        Some(b'H') | Some(b'T') => "OK".to_string(),
+        Some(b'D') => return Reply::Close("OK".to_string()),
+        Some(b'k') => return Reply::Close(String::new()),
+        _ => query(data).unwrap_or_default(),
+    };
+    Reply::Send(reply)
+}
+
+fn query(data: &str) -> Option<String> {
+    if data.starts_with("qSupported") {
+        return Some(format!(
+            "PacketSize={:x};qXfer:features:read+;QStartNoAckMode+",
+            PACKET_SIZE
+        ));
+    }
+    if let Some(args) = data.strip_prefix("qXfer:features:read:target.xml:") {
+        let (offset, length) = args.split_once(',')?;
+        let offset = usize::from_str_radix(offset, 16).ok()?;
+        let length = usize::from_str_radix(length, 16).ok()?;
+        let start = offset.min(TARGET_XML.len());
+        let end = offset.saturating_add(length).min(TARGET_XML.len());
+        let prefix = if end == TARGET_XML.len() { 'l' } else { 'm' };
+        return Some(format!("{}{}", prefix, &TARGET_XML[start..end]));
+    }
+    match data {
+        "QStartNoAckMode" => Some("OK".to_string()),
+        "qAttached" => Some("1".to_string()),
+        "qC" => Some("QC1".to_string()),
+        "qfThreadInfo" => Some("m1".to_string()),
+        "qsThreadInfo" => Some("l".to_string()),
+        _ => None,
+    }
+}
+
+fn stop_reply(gb: &GameBoy, reason: StopReason) -> String {
+    match reason {
+        StopReason::Watchpoint(hit) => {
+            let kind = match gb.mmu.watchpoints.kind_at(hit.address) {
+                Some(WatchKind::Access) => "awatch",
+                Some(WatchKind::Read) => "rwatch",
+                _ => "watch",
+            };
+            format!("T05{}:{:04x};", kind, hit.address)
+        }
+        StopReason::Breakpoint(_) => "T05swbreak:;".to_string(),
+        StopReason::Step | StopReason::VBlank => "S05".to_string(),
+    }
+}
+
+fn ok_or_error(result: Option<()>) -> String {
+    match result {
+        Some(()) => "OK".to_string(),
+        None => "E01".to_string(),
+    }
+}
+
+// 不支援的中斷點類型回覆空封包
+fn ok_or_empty(result: Option<()>) -> String {
+    match result {
+        Some(()) => "OK".to_string(),
+        None => String::new(),
+    }
+}
+
+fn parse_hex(text: &str) -> Option<u16> {
+    u16::from_str_radix(text, 16).ok()
+}
+
+// 16 位元暫存器以 little-endian 十六進位字串傳送
+fn hex_u16(value: u16) -> String {
+    format!("{:02x}{:02x}", value as u8, (value >> 8) as u8)
+}
+
+fn parse_hex_u16(text: &str) -> Option<u16> {
+    let bytes = text.get(0..4)?;
+    let low = u8::from_str_radix(&bytes[0..2], 16).ok()?;
+    let high = u8::from_str_radix(&bytes[2..4], 16).ok()?;
+    Some(((high as u16) << 8) | low as u16)
+}
+
+fn register_values(gb: &GameBoy) -> [u16; REGISTER_COUNT] {
+    let cpu = &gb.cpu;
+    [
+        cpu.get_af(),
+        cpu.get_bc(),
+        cpu.get_de(),
+        cpu.get_hl(),
+        cpu.sp,
+        cpu.pc,
+    ]
+}
+
+fn set_register_value(gb: &mut GameBoy, index: usize, value: u16) {
+    let cpu = &mut gb.cpu;
+    match index {
+        0 => cpu.set_af(value),
+        1 => cpu.set_bc(value),
+        2 => cpu.set_de(value),
+        3 => cpu.set_hl(value),
+        4 => cpu.sp = value,
+        _ => cpu.pc = value,
+    }
+}
+
+fn read_registers(gb: &GameBoy) -> String {
+    register_values(gb).iter().map(|&v| hex_u16(v)).collect()
+}
+
+fn write_registers(gb: &mut GameBoy, hex: &str) -> Option<()> {
+    let values = (0..REGISTER_COUNT)
+        .map(|i| parse_hex_u16(hex.get(i * 4..)?))
+        .collect::<Option<Vec<_>>>()?;
+    for (index, value) in values.into_iter().enumerate() {
+        set_register_value(gb, index, value);
+    }
+    Some(())
+}
+
+fn write_register(gb: &mut GameBoy, args: &str) -> Option<()> {
+    let (index, value) = args.split_once('=')?;
+    let index = parse_hex(index)? as usize;
+    if index >= REGISTER_COUNT {
+        return None;
+    }
+    set_register_value(gb, index, parse_hex_u16(value)?);
+    Some(())
+}
+
+fn parse_range(args: &str) -> Option<(u16, u16)> {
+    let (address, length) = args.split_once(',')?;
+    Some((parse_hex(address)?, parse_hex(length)?))
+}
+
+// 回覆長度不超過宣告的封包大小 (每個位元組兩個十六進位字元)
+fn read_memory(gb: &GameBoy, args: &str) -> Option<String> {
+    let (address, length) = parse_range(args)?;
+    let length = length.min((PACKET_SIZE / 2) as u16);
+    Some(
+        (0..length)
+            .map(|i| format!("{:02x}", gb.mmu.read_byte_ppu(address.wrapping_add(i))))
+            .collect(),
+    )
+}
+
+fn write_memory(gb: &mut GameBoy, args: &str) -> Option<()> {
+    let (range, hex) = args.split_once(':')?;
+    let (address, length) = parse_range(range)?;
+    if hex.len() != length as usize * 2 {
+        return None;
+    }
+    for i in 0..length {
+        let offset = i as usize * 2;
+        let value = u8::from_str_radix(hex.get(offset..offset + 2)?, 16).ok()?;
+        if !gb.mmu.poke(address.wrapping_add(i), value) {
+            return None;
+        }
+    }
+    Some(())
+}
+
+// Z/z type,addr,kind：0/1 為中斷點，2/3/4 為寫入/讀取/存取監看點
+fn set_point(
+    debugger: &mut Debugger,
+    gb: &mut GameBoy,
+    breakpoints: &mut Vec<(u16, usize)>,
+    args: &str,
+    insert: bool,
+) -> Option<()> {
+    let mut parts = args.split(',');
+    let kind = parts.next()?;
+    let address = parse_hex(parts.next()?)?;
+    let length = parts.next().and_then(parse_hex).unwrap_or(1).max(1);
+
+    let watch_kind = match kind {
+        "0" | "1" => {
+            let existing = breakpoints.iter().position(|&(other, _)| other == address);
+            match (insert, existing) {
+                (true, None) => {
+                    let id = debugger.add_breakpoint(Breakpoint {
+                        address,
+                        bank: None,
+                    });
+                    breakpoints.push((address, id));
+                }
+                (false, Some(index)) => {
+                    let (_, id) = breakpoints.remove(index);
+                    debugger.execute(gb, Command::Delete(id));
+                }
+                _ => {}
+            }
+            return Some(());
+        }
+        "2" => WatchKind::Write,
+        "3" => WatchKind::Read,
+        "4" => WatchKind::Access,
+        _ => return None,
+    };
+
+    for offset in 0..length {
+        let address = address.wrapping_add(offset);
+        if insert {
+            gb.mmu.watchpoints.add(Watchpoint {
+                address,
+                kind: watch_kind,
+            });
+        } else {
+            gb.mmu.watchpoints.remove(address);
+        }
+    }
+    Some(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    // 本機 GDB 客戶端：送出封包並讀取回覆
+    struct Client {
+        stream: TcpStream,
+    }
+
+    impl Client {
+        fn send(&mut self, data: &str) {
+            write!(self.stream, "${}#{:02x}", data, checksum(data.as_bytes())).unwrap();
+        }
+
+        fn receive(&mut self) -> String {
+            let mut reply = Vec::new();
+            let mut byte = [0u8; 1];
+            loop {
+                self.stream.read_exact(&mut byte).unwrap();
+                match byte[0] {
+                    b'+' if reply.is_empty() => continue,
+                    b'#' => break,
+                    b => reply.push(b),
+                }
+            }
+            let mut checksum = [0u8; 2];
+            self.stream.read_exact(&mut checksum).unwrap();
+            String::from_utf8(reply)
+                .unwrap()
+                .trim_start_matches('$')
+                .to_string()
+        }
+    }
+
+    struct Session {
+        server: GdbServer,
+        debugger: Debugger,
+        gb: GameBoy,
+        client: Client,
+    }
+
+    impl Session {
+        fn new(program: &[u8]) -> Self {
+            let mut gb = GameBoy::with_program(program);
+
+            let mut server = GdbServer::bind(0).unwrap();
+            let stream = TcpStream::connect(("127.0.0.1", server.local_port().unwrap())).unwrap();
+            stream
+                .set_read_timeout(Some(Duration::from_secs(5)))
+                .unwrap();
+            let mut debugger = Debugger::new();
+            debugger.execute(&mut gb, Command::Continue);
+
+            // 等待伺服器接受連線
+            while !server.is_connected() {
+                server.poll(&mut debugger, &mut gb);
+            }
+            Session {
+                server,
+                debugger,
+                gb,
+                client: Client { stream },
+            }
+        }
+
+        // 送出一個封包，輪詢伺服器直到收到回覆
+        fn request(&mut self, data: &str) -> String {
+            self.client.send(data);
+            self.client.stream.set_nonblocking(true).unwrap();
+            let mut peek = [0u8; 1];
+            loop {
+                self.server.poll(&mut self.debugger, &mut self.gb);
+                match self.client.stream.peek(&mut peek) {
+                    Ok(_) => break,
+                    Err(e) if e.kind() == ErrorKind::WouldBlock => continue,
+                    Err(e) => panic!("{}", e),
+                }
+            }
+            self.client.stream.set_nonblocking(false).unwrap();
+            self.client.receive()
+        }
+    }
+
+    #[test]
+    fn connection_pauses_and_reports_registers() {
+        let mut session = Session::new(&[]);
+        assert!(session.debugger.is_paused());
+
+        assert_eq!(session.request("?"), "S05");
+        session.gb.cpu.set_af(0x1280);
+        session.gb.cpu.set_hl(0xBEEF);
+        assert_eq!(session.request("g"), "80121300d800efbef0df00c0");
+        assert_eq!(session.request("p5"), "00c0");
+        assert_eq!(session.request("P4=00d0"), "OK");
+        assert_eq!(session.gb.cpu.sp, 0xD000);
+    }
+
+    #[test]
+    fn reads_and_writes_memory_through_mmu() {
+        let mut session = Session::new(&[0x3E, 0x42]);
+        assert_eq!(session.request("mc000,2"), "3e42");
+        assert_eq!(session.request("Mc100,3:010203"), "OK");
+        assert_eq!(session.gb.mmu.read_byte(0xC102), 0x03);
+    }
+
+    #[test]
+    fn memory_writes_patch_rom_without_side_effects() {
+        let mut session = Session::new(&[]);
+        session.gb.mmu.mbc_type = 1;
+        session.gb.mmu.rom = vec![0; 0x10000];
+        session.gb.mmu.rom_bank = 2;
+        let div = session.gb.mmu.read_byte(0xFF04);
+
+        // 寫入 0x2000 修改 ROM bank 0，不會切換 bank
+        assert_eq!(session.request("M2000,1:05"), "OK");
+        assert_eq!(session.gb.mmu.rom[0x2000], 0x05);
+        assert_eq!(session.gb.mmu.rom_bank, 2);
+        assert_eq!(session.request("M4001,1:aa"), "OK");
+        assert_eq!(session.gb.mmu.rom[2 * 0x4000 + 1], 0xAA);
+
+        // 有副作用的 I/O 寄存器 (DIV) 拒絕寫入
+        assert_eq!(session.request("Mff04,1:00"), "E01");
+        assert_eq!(session.gb.mmu.read_byte(0xFF04), div);
+        assert_eq!(session.request("Mff0f,1:04"), "OK");
+        assert_eq!(session.gb.mmu.interrupts.flags, 0x04);
+    }
+
+    #[test]
+    fn rejects_packet_with_bad_checksum() {
+        let mut session = Session::new(&[0x3E, 0x42]);
+        write!(session.client.stream, "$mc000,2#00").unwrap();
+        let mut ack = [0u8; 1];
+        session.client.stream.set_nonblocking(true).unwrap();
+        loop {
+            session.server.poll(&mut session.debugger, &mut session.gb);
+            match session.client.stream.read(&mut ack) {
+                Ok(_) => break,
+                Err(e) if e.kind() == ErrorKind::WouldBlock => continue,
+                Err(e) => panic!("{}", e),
+            }
+        }
+        session.client.stream.set_nonblocking(false).unwrap();
+        assert_eq!(&ack, b"-");
+
+        // 重送正確的封包後照常回覆
+        assert_eq!(session.request("mc000,2"), "3e42");
+    }
+
+    #[test]
+    fn serves_target_description() {
+        let mut session = Session::new(&[]);
+        assert!(
+            session
+                .request("qSupported:xmlRegisters=i386")
+                .contains("qXfer:features:read+")
+        );
+        let xml = session.request("qXfer:features:read:target.xml:0,1000");
+        assert!(xml.starts_with('l'));
+        assert!(xml.contains(r#"<reg name="pc" bitsize="16" type="code_ptr"/>"#));
+        // offset + length 溢位時回覆空的最後一段
+        assert_eq!(
+            session.request("qXfer:features:read:target.xml:ffffffffffffffff,1"),
+            "l"
+        );
+    }
+
+    #[test]
+    fn memory_reads_fit_in_packet_size() {
+        let mut session = Session::new(&[]);
+        assert_eq!(session.request("m0,ffff").len(), PACKET_SIZE);
+    }
+
+    #[test]
+    fn removing_gdb_breakpoint_keeps_user_breakpoints() {
+        let mut session = Session::new(&[]);
+        let user = session.debugger.add_breakpoint(Breakpoint {
+            address: 0xC002,
+            bank: None,
+        });
+        assert_eq!(session.request("Z0,c002,1"), "OK");
+        assert_eq!(session.request("z0,c002,1"), "OK");
+        let deleted = session
+            .debugger
+            .execute(&mut session.gb, Command::Delete(user));
+        assert!(deleted.starts_with("已刪除中斷點"), "{}", deleted);
+    }
+
+    #[test]
+    fn breakpoint_and_continue() {
+        // NOP; NOP; NOP; JR -2
+        let mut session = Session::new(&[0x00, 0x00, 0x00, 0x18, 0xFE]);
+        assert_eq!(session.request("Z0,c002,1"), "OK");
+        session.client.send("c");
+
+        let reason = loop {
+            session.server.poll(&mut session.debugger, &mut session.gb);
+            if let Some(reason) = session.debugger.run_frame(&mut session.gb) {
+                break reason;
+            }
+        };
+        session.server.report_stop(&session.gb, reason);
+        assert_eq!(session.client.receive(), "T05swbreak:;");
+        assert_eq!(session.gb.cpu.pc, 0xC002);
+
+        assert_eq!(session.request("z0,c002,1"), "OK");
+        assert_eq!(session.request("s"), "S05");
+        assert_eq!(session.gb.cpu.pc, 0xC003);
+    }
+
+    #[test]
+    fn write_watchpoint_reports_address() {
+        // LD A,42; LD (C100),A; JR -2
+        let mut session = Session::new(&[0x3E, 0x42, 0xEA, 0x00, 0xC1, 0x18, 0xFE]);
+        assert_eq!(session.request("Z2,c100,1"), "OK");
+        session.client.send("c");
+
+        let reason = loop {
+            session.server.poll(&mut session.debugger, &mut session.gb);
+            if let Some(reason) = session.debugger.run_frame(&mut session.gb) {
+                break reason;
+            }
+        };
+        session.server.report_stop(&session.gb, reason);
+        assert_eq!(session.client.receive(), "T05watch:c100;");
+    }
+}
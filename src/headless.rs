@@ -0,0 +1,49 @@
+//! 無視窗執行 (`--headless`)
+//!
+//! 不初始化 SDL，以最快速度執行到 `--frames` 指定的幀數，
+//! 適合搭配 `--trace`、`--profile`、`--state` 或 `--play` 做自動化測試 (播放影片時未指定 `--frames`
+//! 則在影片結束時停止；兩者都沒有時命令列解析會拒絕)。結束時輸出串口內容。
+
+use crate::cli::Options;
+use crate::gameboy::GameBoy;
+use crate::sdl3::{self, EmulatorError};
+use std::io::Write;
+
+pub fn run(mut gb: GameBoy, options: &Options) -> Result<(), EmulatorError> {
+    let mut trace_file = sdl3::open_trace(&mut gb, &options.debug)?;
+    let mut movie = sdl3::open_movie(&mut gb, options)?;
+    if options.debug.profile_path.is_some() {
+        gb.enable_profiler();
+    }
+
+    let mut frames_run = 0u64;
+    while options.frames.is_none_or(|frames| frames_run < frames) {
+        if let Some(movie) = movie.as_mut() {
+            movie.begin_frame(&mut gb, 0);
+        }
+        gb.run_frame();
+        // 沒有音訊輸出，丟棄樣本避免緩衝區無限增長
+        gb.mmu.apu.audio_buffer.clear();
+        if sdl3::end_movie_frame(movie.as_mut(), &gb) && options.frames.is_none() {
+            break;
+        }
+        if let Some(file) = trace_file.as_mut() {
+            let _ = file.write_all(gb.take_trace().as_bytes());
+        }
+        frames_run += 1;
+    }
+
+    sdl3::close_movie(movie);
+    if !gb.mmu.serial_output.is_empty() {
+        println!("{}", gb.mmu.serial_output);
+    }
+    gb.mmu.save_external_ram();
+    gb.save_cheats();
+    if let Some(file) = trace_file.as_mut() {
+        let _ = file.flush();
+    }
+    if let Some(prefix) = options.debug.profile_path.as_deref() {
+        sdl3::write_profile(&gb, prefix);
+    }
+    Ok(())
+}
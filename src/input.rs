@@ -0,0 +1,211 @@
+//! 輸入層：連發與巨集
+//!
+//! 介於前端 (`InputManager`) 與 `Joypad::set_key` 之間，以模擬的幀為單位運作：
+//! 連發的頻率與巨集的長度都以幀計算，不受實際時間、`--speed` 或畫面延遲影響。
+//! 每幀開始前呼叫 [`InputLayer::next_frame`]，取得需要送給 Joypad 的按鍵變化。
+//! 同一幀內按下又放開的按鍵至少會保持按下一幀，快速點按不會遺失。
+
+use crate::joypad::JoypadKey;
+
+/// 輸入綁定的目標：一般按鍵或連發按鍵
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Binding {
+    Key(JoypadKey),
+    Turbo(JoypadKey),
+}
+
+/// 每幀的按鍵狀態，位元順序同 `JoypadKey::as_index`
+type KeyMask = u8;
+
+#[derive(Debug)]
+pub struct InputLayer {
+    held: KeyMask,
+    tapped: KeyMask,               // 這一幀內按下過的按鍵
+    turbo_start: [Option<u64>; 8], // 連發按下時的幀數，決定切換相位
+    turbo_rate: u32,               // 每隔幾幀切換一次按下/放開
+    frame: u64,
+    macros: Vec<Vec<KeyMask>>,
+    recording: Option<(usize, Vec<KeyMask>)>,
+    playback: Option<(usize, usize)>, // (巨集編號, 下一幀的位置)
+    output: KeyMask,
+}
+
+impl InputLayer {
+    pub fn new(turbo_rate: u32, macro_slots: usize) -> Self {
+        InputLayer {
+            held: 0,
+            tapped: 0,
+            turbo_start: [None; 8],
+            turbo_rate: turbo_rate.max(1),
+            frame: 0,
+            macros: vec![Vec::new(); macro_slots],
+            recording: None,
+            playback: None,
+            output: 0,
+        }
+    }
+
+    pub fn set(&mut self, binding: Binding, pressed: bool) {
+        match binding {
+            Binding::Key(key) => {
+                let bit = 1 << key.as_index();
+                if pressed {
+                    self.held |= bit;
+                    self.tapped |= bit;
+                } else {
+                    self.held &= !bit;
+                }
+            }
+            Binding::Turbo(key) => {
+                self.turbo_start[key.as_index()] = pressed.then_some(self.frame);
+            }
+        }
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// 開始或停止錄製巨集，回傳停止時錄下的幀數
+    pub fn toggle_recording(&mut self, slot: usize) -> Option<usize> {
+        if slot >= self.macros.len() {
+            return None;
+        }
+        match self.recording.take() {
+            Some((recorded_slot, mut frames)) => {
+                // 去掉結尾沒有按任何鍵的幀
+                while frames.last() == Some(&0) {
+                    frames.pop();
+                }
+                let len = frames.len();
+                self.macros[recorded_slot] = frames;
+                Some(len)
+            }
+            None => {
+                self.playback = None;
+                self.recording = Some((slot, Vec::new()));
+                None
+            }
+        }
+    }
+
+    /// 從頭播放巨集 (錄製中或巨集為空時忽略)，播放期間與玩家的輸入合併
+    pub fn play(&mut self, slot: usize) -> bool {
+        let playable =
+            self.recording.is_none() && self.macros.get(slot).is_some_and(|m| !m.is_empty());
+        if playable {
+            self.playback = Some((slot, 0));
+        }
+        playable
+    }
+
+    // 玩家的輸入加上連發相位
+    fn player_mask(&self) -> KeyMask {
+        let rate = self.turbo_rate as u64;
+        let mut mask = self.held | self.tapped;
+        for (index, start) in self.turbo_start.iter().enumerate() {
+            if let Some(start) = start
+                && ((self.frame - start) / rate).is_multiple_of(2)
+            {
+                mask |= 1 << index;
+            }
+        }
+        mask
+    }
+
+    /// 目前送給 Joypad 的按鍵狀態 (1 = 按下)
+    pub fn mask(&self) -> u8 {
+        self.output
+    }
+
+    /// 進入下一幀：回傳與上一幀相比有變化的按鍵
+    pub fn next_frame(&mut self) -> Vec<(JoypadKey, bool)> {
+        let mut mask = self.player_mask();
+        self.tapped = 0;
+        if let Some((_, frames)) = self.recording.as_mut() {
+            frames.push(mask);
+        }
+        if let Some((slot, pos)) = self.playback {
+            let frames = &self.macros[slot];
+            mask |= frames[pos];
+            self.playback = (pos + 1 < frames.len()).then_some((slot, pos + 1));
+        }
+        self.frame += 1;
+
+        let changed = mask ^ self.output;
+        self.output = mask;
+        JoypadKey::ALL
+            .into_iter()
+            .filter(|key| changed & (1 << key.as_index()) != 0)
+            .map(|key| (key, mask & (1 << key.as_index()) != 0))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(input: &mut InputLayer, frames: usize) -> Vec<Vec<(JoypadKey, bool)>> {
+        (0..frames).map(|_| input.next_frame()).collect()
+    }
+
+    #[test]
+    fn turbo_toggles_every_rate_frames() {
+        let mut input = InputLayer::new(2, 0);
+        input.set(Binding::Turbo(JoypadKey::A), true);
+        let a = |pressed| vec![(JoypadKey::A, pressed)];
+        assert_eq!(
+            run(&mut input, 5),
+            [a(true), vec![], a(false), vec![], a(true)]
+        );
+
+        // 一般按鍵按住時連發不會放開
+        input.set(Binding::Key(JoypadKey::A), true);
+        assert!(run(&mut input, 4).iter().all(Vec::is_empty));
+        input.set(Binding::Key(JoypadKey::A), false);
+        input.set(Binding::Turbo(JoypadKey::A), false);
+        assert_eq!(input.next_frame(), a(false));
+    }
+
+    #[test]
+    fn short_tap_lasts_one_frame() {
+        let mut input = InputLayer::new(1, 0);
+        input.set(Binding::Key(JoypadKey::Start), true);
+        input.set(Binding::Key(JoypadKey::Start), false);
+        assert_eq!(
+            run(&mut input, 3),
+            [
+                vec![(JoypadKey::Start, true)],
+                vec![(JoypadKey::Start, false)],
+                vec![]
+            ]
+        );
+    }
+
+    #[test]
+    fn records_and_replays_macro() {
+        let mut input = InputLayer::new(1, 2);
+        input.toggle_recording(1);
+        input.set(Binding::Key(JoypadKey::Down), true);
+        run(&mut input, 2);
+        input.set(Binding::Key(JoypadKey::Down), false);
+        input.set(Binding::Key(JoypadKey::B), true);
+        input.next_frame();
+        input.set(Binding::Key(JoypadKey::B), false);
+        run(&mut input, 3);
+        assert_eq!(input.toggle_recording(1), Some(3));
+        assert!(!input.play(0));
+
+        assert!(input.play(1));
+        assert_eq!(
+            run(&mut input, 4),
+            [
+                vec![(JoypadKey::Down, true)],
+                vec![],
+                vec![(JoypadKey::B, true), (JoypadKey::Down, false)],
+                vec![(JoypadKey::B, false)],
+            ]
+        );
+    }
+}
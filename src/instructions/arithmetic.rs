@@ -3,10 +3,10 @@
 //! 包含 ADD, ADC, SUB, SBC, AND, OR, XOR, CP 等指令
 
 use crate::cpu::Cpu;
-use crate::mmu::Mmu;
+use crate::mmu::Memory;
 
 /// 處理 ADD 指令
-pub fn handle_add(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_add<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     if opcode.operands.len() >= 2 {
         let original_a = cpu.a();
         // Handle 16-bit form where HL is the destination: `ADD HL, rr`
@@ -61,7 +61,7 @@ pub fn handle_add(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
 }
 
 /// 處理 ADC (帶進位加法) 指令
-pub fn handle_adc(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_adc<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     if opcode.operands.len() >= 2 {
         let original_a = cpu.a();
         let carry = cpu.get_flag_c() as u8;
@@ -88,7 +88,7 @@ pub fn handle_adc(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
 }
 
 /// 處理 SUB (減法) 指令
-pub fn handle_sub(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_sub<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     if opcode.operands.len() >= 2 {
         let original_a = cpu.a();
         let val = match opcode.operands[1].name.as_str() {
@@ -134,7 +134,7 @@ pub fn handle_sub(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
 }
 
 /// 處理 SBC (帶借位減法) 指令
-pub fn handle_sbc(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_sbc<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     if opcode.operands.len() >= 2 {
         let original_a = cpu.a();
         let carry = cpu.get_flag_c() as u8;
@@ -161,7 +161,7 @@ pub fn handle_sbc(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
 }
 
 /// 處理 AND 指令
-pub fn handle_and(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_and<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     if opcode.operands.len() >= 2 {
         let val = match opcode.operands[1].name.as_str() {
             "A" => cpu.a(),
@@ -185,7 +185,7 @@ pub fn handle_and(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
 }
 
 /// 處理 OR 指令
-pub fn handle_or(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_or<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     if opcode.operands.len() >= 2 {
         let val = match opcode.operands[1].name.as_str() {
             "A" => cpu.a(),
@@ -209,7 +209,7 @@ pub fn handle_or(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
 }
 
 /// 處理 XOR 指令
-pub fn handle_xor(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_xor<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     if opcode.operands.len() >= 2 {
         let val = match opcode.operands[1].name.as_str() {
             "A" => cpu.a(),
@@ -233,7 +233,7 @@ pub fn handle_xor(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
 }
 
 /// 處理 CP (比較) 指令
-pub fn handle_cp(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_cp<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     if opcode.operands.len() >= 2 {
         let original_a = cpu.a();
         let val = match opcode.operands[1].name.as_str() {
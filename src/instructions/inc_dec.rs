@@ -3,10 +3,10 @@
 //! 這個模組負責處理 INC, DEC 等增減指令
 
 use crate::cpu::Cpu;
-use crate::mmu::Mmu;
+use crate::mmu::Memory;
 
 /// 處理 INC (Increment) 指令
-pub fn handle_inc(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_inc<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     if !opcode.operands.is_empty() {
         let op = &opcode.operands[0];
         let name = op.name.as_str();
@@ -95,7 +95,7 @@ pub fn handle_inc(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
 }
 
 /// 處理 DEC (Decrement) 指令
-pub fn handle_dec(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_dec<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     if !opcode.operands.is_empty() {
         let op = &opcode.operands[0];
         let name = op.name.as_str();
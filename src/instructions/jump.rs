@@ -3,10 +3,10 @@
 //! 這個模組負責處理 JP, JR, CALL, RET 等跳轉指令
 
 use crate::cpu::Cpu;
-use crate::mmu::Mmu;
+use crate::mmu::Memory;
 
 /// 處理 JP (Jump) 指令
-pub fn handle_jp(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_jp<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     if !opcode.operands.is_empty() {
         match opcode.operands[0].name.as_str() {
             "a16" => {
@@ -35,7 +35,7 @@ pub fn handle_jp(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
 }
 
 /// 處理 JR (Jump Relative) 指令
-pub fn handle_jr(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_jr<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     if !opcode.operands.is_empty() {
         match opcode.operands[0].name.as_str() {
             "e8" => {
@@ -59,7 +59,7 @@ pub fn handle_jr(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
 }
 
 /// 處理 CALL 指令
-pub fn handle_call(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_call<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     if !opcode.operands.is_empty() {
         match opcode.operands[0].name.as_str() {
             "a16" => {
@@ -85,30 +85,30 @@ pub fn handle_call(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
 }
 
 /// 處理 RET (Return) 指令
-pub fn handle_ret(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_ret<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     if opcode.operands.is_empty() {
         // RET - 返回 (無條件，總是 taken)
-        cpu.pc = cpu.pop_word(&*mmu);
+        cpu.pc = cpu.pop_word(mmu);
         cpu.branch_taken = true;
     } else {
         // RET cc - 條件返回
         if let Some(condition) = get_condition(&opcode.operands[0].name)
             && check_condition(cpu, condition) {
-                cpu.pc = cpu.pop_word(&*mmu);
+                cpu.pc = cpu.pop_word(mmu);
                 cpu.branch_taken = true;
             }
     }
 }
 
 /// 處理 RETI (Return from Interrupt) 指令
-pub fn handle_reti(cpu: &mut Cpu, mmu: &mut Mmu, _opcode: &crate::cpu::Opcode) {
+pub fn handle_reti<M: Memory>(cpu: &mut Cpu, mmu: &mut M, _opcode: &crate::cpu::Opcode) {
     cpu.pc = cpu.pop_word(mmu);
     cpu.ime = crate::cpu::InterruptMasterState::Enabled; // 重新啟用中斷
     cpu.branch_taken = true;
 }
 
 /// 處理 RST (Restart) 指令
-pub fn handle_rst(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_rst<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     // RST 指令將 PC 推入堆疊，然後跳轉到指定位址
     if let Some(operand) = opcode.operands.first() {
         let name = operand.name.to_lowercase();
@@ -127,10 +127,10 @@ pub fn handle_rst(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
 }
 
 /// 處理 HALT 指令
-pub fn handle_halt(cpu: &mut Cpu, mmu: &mut Mmu, _opcode: &crate::cpu::Opcode) {
+pub fn handle_halt<M: Memory>(cpu: &mut Cpu, mmu: &mut M, _opcode: &crate::cpu::Opcode) {
     // 檢查 HALT bug 條件：IME=0 且有中斷待處理 (IE & IF != 0)
-    let ie = mmu.read_byte(0xFFFF);
-    let iff = mmu.read_byte(0xFF0F);
+    let ie = mmu.read_internal(0xFFFF);
+    let iff = mmu.read_internal(0xFF0F);
     let pending = ie & iff & 0x1F;
 
     if cpu.ime == crate::cpu::InterruptMasterState::Disabled && pending != 0 {
@@ -144,7 +144,7 @@ pub fn handle_halt(cpu: &mut Cpu, mmu: &mut Mmu, _opcode: &crate::cpu::Opcode) {
 }
 
 /// 處理 EI (Enable Interrupts) 指令
-pub fn handle_ei(cpu: &mut Cpu, _mmu: &mut Mmu, _opcode: &crate::cpu::Opcode) {
+pub fn handle_ei<M: Memory>(cpu: &mut Cpu, _mmu: &mut M, _opcode: &crate::cpu::Opcode) {
     // EI 不會立即生效，而是在下一個指令之後啟用 IME
     cpu.ime = crate::cpu::InterruptMasterState::Pending;
 }
@@ -3,10 +3,10 @@
 //! 這個模組負責處理所有 LD 指令的變體
 
 use crate::cpu::Cpu;
-use crate::mmu::Mmu;
+use crate::mmu::Memory;
 
 /// 處理載入指令
-pub fn handle_ld(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_ld<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     // 特殊處理：LD HL, SP+e8 (opcode 0xF8) 有 3 個 operands
     if opcode.operands.len() == 3 {
         let op0 = &opcode.operands[0];
@@ -3,10 +3,10 @@
 //! 這個模組負責處理 LDH, CPL, DAA, RLA, RRCA 等雜項指令
 
 use crate::cpu::Cpu;
-use crate::mmu::Mmu;
+use crate::mmu::Memory;
 
 /// 處理 LDH (Load High) 指令
-pub fn handle_ldh(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_ldh<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     if opcode.operands.len() >= 2 {
         let op0 = &opcode.operands[0];
         let op1 = &opcode.operands[1];
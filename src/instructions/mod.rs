@@ -11,11 +11,14 @@ pub mod misc;
 pub mod rotate;
 pub mod stack;
 
+#[cfg(test)]
+mod tests;
+
 use crate::cpu::Cpu;
-use crate::mmu::Mmu;
+use crate::mmu::Memory;
 
 /// 主要的指令處理器
-pub fn execute_instruction(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn execute_instruction<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     match opcode.mnemonic.as_str() {
         // 載入指令
         "LD" => ld::handle_ld(cpu, mmu, opcode),
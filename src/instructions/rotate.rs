@@ -3,7 +3,7 @@
 //! 包含 RLCA, RLA, RRCA, RRA 等旋轉指令
 
 use crate::cpu::Cpu;
-use crate::mmu::Mmu;
+use crate::mmu::Memory;
 
 /// 處理 RLCA 指令 (向左旋轉累加器)
 pub fn handle_rlca(cpu: &mut Cpu, _opcode: &crate::cpu::Opcode) {
@@ -39,7 +39,7 @@ pub fn handle_rra(cpu: &mut Cpu, _opcode: &crate::cpu::Opcode) {
 }
 
 /// 處理 BIT 指令 (測試位元)
-pub fn handle_bit(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_bit<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     // 從操作數中提取位元位置和暫存器
     if opcode.operands.len() >= 2 {
         // 第一個操作數是位元位置
@@ -87,7 +87,7 @@ pub fn handle_bit(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
 }
 
 /// 處理 SET 指令 (設定位元)
-pub fn handle_set(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_set<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     // 從操作數中提取位元位置和暫存器
     if opcode.operands.len() >= 2 {
         // 第一個操作數是位元位置
@@ -129,7 +129,7 @@ pub fn handle_set(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
 }
 
 /// 處理 RES 指令 (清除位元)
-pub fn handle_res(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_res<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     // 從操作數中提取位元位置和暫存器
     if opcode.operands.len() >= 2 {
         // 第一個操作數是位元位置
@@ -171,7 +171,7 @@ pub fn handle_res(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
 }
 
 /// 輔助函數：獲取操作數的值
-fn get_operand_value(cpu: &Cpu, mmu: &Mmu, name: &str, immediate: Option<bool>) -> u8 {
+fn get_operand_value<M: Memory>(cpu: &Cpu, mmu: &mut M, name: &str, immediate: Option<bool>) -> u8 {
     match (name, immediate) {
         ("A", Some(true)) => cpu.registers.a,
         ("B", Some(true)) => cpu.registers.b,
@@ -186,7 +186,7 @@ fn get_operand_value(cpu: &Cpu, mmu: &Mmu, name: &str, immediate: Option<bool>)
 }
 
 /// 輔助函數：設置操作數的值
-fn set_operand_value(cpu: &mut Cpu, mmu: &mut Mmu, name: &str, immediate: Option<bool>, value: u8) {
+fn set_operand_value<M: Memory>(cpu: &mut Cpu, mmu: &mut M, name: &str, immediate: Option<bool>, value: u8) {
     match (name, immediate) {
         ("A", Some(true)) => cpu.registers.a = value,
         ("B", Some(true)) => cpu.registers.b = value,
@@ -200,7 +200,7 @@ fn set_operand_value(cpu: &mut Cpu, mmu: &mut Mmu, name: &str, immediate: Option
     }
 }
 
-pub fn handle_rlc(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_rlc<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     let name = &opcode.operands[0].name;
     let imm = opcode.operands[0].immediate;
     let val = get_operand_value(cpu, mmu, name, imm);
@@ -213,7 +213,7 @@ pub fn handle_rlc(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
     cpu.set_flag_c(bit7 == 1);
 }
 
-pub fn handle_rrc(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_rrc<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     let name = &opcode.operands[0].name;
     let imm = opcode.operands[0].immediate;
     let val = get_operand_value(cpu, mmu, name, imm);
@@ -226,7 +226,7 @@ pub fn handle_rrc(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
     cpu.set_flag_c(bit0 == 1);
 }
 
-pub fn handle_rl(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_rl<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     let name = &opcode.operands[0].name;
     let imm = opcode.operands[0].immediate;
     let val = get_operand_value(cpu, mmu, name, imm);
@@ -244,7 +244,7 @@ pub fn handle_rl(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
     cpu.set_flag_c(bit7 == 1);
 }
 
-pub fn handle_rr(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_rr<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     let name = &opcode.operands[0].name;
     let imm = opcode.operands[0].immediate;
     let val = get_operand_value(cpu, mmu, name, imm);
@@ -262,7 +262,7 @@ pub fn handle_rr(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
     cpu.set_flag_c(bit0 == 1);
 }
 
-pub fn handle_sla(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_sla<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     let name = &opcode.operands[0].name;
     let imm = opcode.operands[0].immediate;
     let val = get_operand_value(cpu, mmu, name, imm);
@@ -275,7 +275,7 @@ pub fn handle_sla(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
     cpu.set_flag_c(bit7 == 1);
 }
 
-pub fn handle_sra(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_sra<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     let name = &opcode.operands[0].name;
     let imm = opcode.operands[0].immediate;
     let val = get_operand_value(cpu, mmu, name, imm);
@@ -289,7 +289,7 @@ pub fn handle_sra(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
     cpu.set_flag_c(bit0 == 1);
 }
 
-pub fn handle_srl(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_srl<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     let name = &opcode.operands[0].name;
     let imm = opcode.operands[0].immediate;
     let val = get_operand_value(cpu, mmu, name, imm);
@@ -302,7 +302,7 @@ pub fn handle_srl(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
     cpu.set_flag_c(bit0 == 1);
 }
 
-pub fn handle_swap(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_swap<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     let name = &opcode.operands[0].name;
     let imm = opcode.operands[0].immediate;
     let val = get_operand_value(cpu, mmu, name, imm);
@@ -3,10 +3,10 @@
 //! 這個模組負責處理 PUSH, POP 等堆疊操作指令
 
 use crate::cpu::Cpu;
-use crate::mmu::Mmu;
+use crate::mmu::Memory;
 
 /// 處理 PUSH 指令
-pub fn handle_push(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_push<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     if !opcode.operands.is_empty() {
         match opcode.operands[0].name.as_str() {
             "AF" => {
@@ -27,9 +27,9 @@ pub fn handle_push(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
 }
 
 /// 處理 POP 指令
-pub fn handle_pop(cpu: &mut Cpu, mmu: &mut Mmu, opcode: &crate::cpu::Opcode) {
+pub fn handle_pop<M: Memory>(cpu: &mut Cpu, mmu: &mut M, opcode: &crate::cpu::Opcode) {
     if !opcode.operands.is_empty() {
-        let val = cpu.pop_word(&*mmu);
+        let val = cpu.pop_word(mmu);
         match opcode.operands[0].name.as_str() {
             "AF" => {
                 cpu.set_af(val);
@@ -0,0 +1,258 @@
+//! 逐指令單元測試
+//!
+//! 載入社群 SingleStepTests (sm83) 的 JSON 測試向量，每筆包含初始/最終 CPU 與 RAM 狀態
+//! 以及匯流排週期。每筆向量在平坦 RAM 上以 `Cpu::step` 執行一次，並依操作碼彙整不一致之處。
+//!
+//! 測試資料目錄由環境變數 `SST_DIR` 指定，預設為 `tests/sm83/v1`；目錄不存在時略過。
+
+use crate::cpu::{Cpu, InterruptMasterState};
+use crate::mmu::Memory;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+/// 平坦 64 KiB RAM 匯流排：沒有 MBC、I/O 或存取限制
+struct FlatRam {
+    ram: Box<[u8; 0x10000]>,
+}
+
+impl FlatRam {
+    fn new() -> Self {
+        FlatRam {
+            ram: Box::new([0; 0x10000]),
+        }
+    }
+}
+
+impl Memory for FlatRam {
+    fn read_byte(&mut self, address: u16) -> u8 {
+        self.ram[address as usize]
+    }
+
+    fn write_byte(&mut self, address: u16, value: u8) {
+        self.ram[address as usize] = value;
+    }
+}
+
+#[derive(Deserialize, Debug)]
+struct TestCase {
+    name: String,
+    initial: CpuSnapshot,
+    #[serde(rename = "final")]
+    final_state: CpuSnapshot,
+    // 每個元素代表一個 M-cycle ([位址, 數值, 動作] 或 null)
+    cycles: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize, Debug)]
+struct CpuSnapshot {
+    pc: u16,
+    sp: u16,
+    a: u8,
+    b: u8,
+    c: u8,
+    d: u8,
+    e: u8,
+    f: u8,
+    h: u8,
+    l: u8,
+    #[serde(default)]
+    ime: u8,
+    #[serde(default)]
+    ie: Option<u8>,
+    ram: Vec<(u16, u8)>,
+}
+
+fn load_state(cpu: &mut Cpu, mmu: &mut FlatRam, state: &CpuSnapshot) {
+    cpu.pc = state.pc;
+    cpu.sp = state.sp;
+    cpu.set_a(state.a);
+    cpu.set_f(state.f);
+    cpu.set_b(state.b);
+    cpu.set_c(state.c);
+    cpu.set_d(state.d);
+    cpu.set_e(state.e);
+    cpu.set_h(state.h);
+    cpu.set_l(state.l);
+    cpu.ime = if state.ime != 0 {
+        InterruptMasterState::Enabled
+    } else {
+        InterruptMasterState::Disabled
+    };
+    if let Some(ie) = state.ie {
+        mmu.write_byte(0xFFFF, ie);
+    }
+    for &(addr, value) in &state.ram {
+        mmu.write_byte(addr, value);
+    }
+}
+
+// 執行單筆測試向量，回傳所有不一致的欄位
+fn run_case(case: &TestCase) -> Result<(), String> {
+    let mut cpu = Cpu::new();
+    let mut mmu = FlatRam::new();
+    load_state(&mut cpu, &mut mmu, &case.initial);
+
+    let cycles = cpu.step(&mut mmu);
+
+    let expected = &case.final_state;
+    let mut diffs = Vec::new();
+    let mut check = |field: &str, want: u16, got: u16| {
+        if want != got {
+            diffs.push(format!("{field}: 預期 {want:04X} 實際 {got:04X}"));
+        }
+    };
+
+    check("A", expected.a as u16, cpu.a() as u16);
+    check("F", expected.f as u16, cpu.f() as u16);
+    check("B", expected.b as u16, cpu.b() as u16);
+    check("C", expected.c as u16, cpu.c() as u16);
+    check("D", expected.d as u16, cpu.d() as u16);
+    check("E", expected.e as u16, cpu.e() as u16);
+    check("H", expected.h as u16, cpu.h() as u16);
+    check("L", expected.l as u16, cpu.l() as u16);
+    check("PC", expected.pc, cpu.pc);
+    check("SP", expected.sp, cpu.sp);
+    check(
+        "IME",
+        expected.ime as u16,
+        (cpu.ime == InterruptMasterState::Enabled) as u16,
+    );
+    check("cycles", case.cycles.len() as u16 * 4, cycles as u16);
+    for &(addr, value) in &expected.ram {
+        check(&format!("[{addr:04X}]"), value as u16, mmu.read_byte(addr) as u16);
+    }
+
+    if diffs.is_empty() {
+        Ok(())
+    } else {
+        Err(diffs.join(", "))
+    }
+}
+
+/// 執行一組測試向量，回傳 (總數, 失敗數, 第一筆失敗描述)
+fn run_suite(cases: &[TestCase]) -> (usize, usize, Option<String>) {
+    let mut failed = 0;
+    let mut first_failure = None;
+    for case in cases {
+        if let Err(diffs) = run_case(case) {
+            failed += 1;
+            first_failure.get_or_insert_with(|| format!("{}: {}", case.name, diffs));
+        }
+    }
+    (cases.len(), failed, first_failure)
+}
+
+fn corpus_dir() -> PathBuf {
+    match std::env::var_os("SST_DIR") {
+        Some(dir) => PathBuf::from(dir),
+        None => Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/sm83/v1"),
+    }
+}
+
+#[test]
+fn single_step_tests_corpus() {
+    let dir = corpus_dir();
+    let Ok(entries) = std::fs::read_dir(&dir) else {
+        eprintln!("略過 SingleStepTests：找不到測試資料目錄 {}", dir.display());
+        return;
+    };
+
+    let mut files: Vec<PathBuf> = entries
+        .filter_map(|e| e.ok().map(|e| e.path()))
+        .filter(|p| p.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    files.sort();
+
+    // 以檔名 (操作碼，例如 "27" 或 "cb 11") 彙整結果
+    let mut report: BTreeMap<String, (usize, usize, Option<String>)> = BTreeMap::new();
+    for path in &files {
+        let data = std::fs::read_to_string(path).expect("無法讀取測試檔案");
+        let cases: Vec<TestCase> = serde_json::from_str(&data)
+            .unwrap_or_else(|e| panic!("{} 格式錯誤: {}", path.display(), e));
+        let opcode = path.file_stem().unwrap().to_string_lossy().into_owned();
+        report.insert(opcode, run_suite(&cases));
+    }
+
+    let mut total_failed = 0;
+    for (opcode, (total, failed, first)) in &report {
+        if *failed > 0 {
+            total_failed += failed;
+            eprintln!(
+                "操作碼 {opcode}: {failed}/{total} 失敗 — {}",
+                first.as_deref().unwrap_or_default()
+            );
+        }
+    }
+
+    assert_eq!(
+        total_failed,
+        0,
+        "{} 個操作碼中有 {} 筆測試失敗",
+        report.len(),
+        total_failed
+    );
+}
+
+// DAA 旗標邊界：加法/減法模式、半進位與進位的各種組合
+const DAA_CASES: &str = r#"[
+    {"name": "27 9A+0 -> 00 ZC",
+     "initial": {"pc": 49152, "sp": 65534, "a": 154, "b": 0, "c": 0, "d": 0, "e": 0, "f": 0, "h": 0, "l": 0, "ime": 0, "ram": [[49152, 39]]},
+     "final": {"pc": 49153, "sp": 65534, "a": 0, "b": 0, "c": 0, "d": 0, "e": 0, "f": 144, "h": 0, "l": 0, "ime": 0, "ram": [[49152, 39]]},
+     "cycles": [[49152, 39, "r-m"]]},
+    {"name": "27 0F+H -> 15",
+     "initial": {"pc": 49152, "sp": 65534, "a": 15, "b": 0, "c": 0, "d": 0, "e": 0, "f": 32, "h": 0, "l": 0, "ime": 0, "ram": [[49152, 39]]},
+     "final": {"pc": 49153, "sp": 65534, "a": 21, "b": 0, "c": 0, "d": 0, "e": 0, "f": 0, "h": 0, "l": 0, "ime": 0, "ram": [[49152, 39]]},
+     "cycles": [[49152, 39, "r-m"]]},
+    {"name": "27 FF+0 -> 65 C",
+     "initial": {"pc": 49152, "sp": 65534, "a": 255, "b": 0, "c": 0, "d": 0, "e": 0, "f": 0, "h": 0, "l": 0, "ime": 0, "ram": [[49152, 39]]},
+     "final": {"pc": 49153, "sp": 65534, "a": 101, "b": 0, "c": 0, "d": 0, "e": 0, "f": 16, "h": 0, "l": 0, "ime": 0, "ram": [[49152, 39]]},
+     "cycles": [[49152, 39, "r-m"]]},
+    {"name": "27 00+NH -> FA",
+     "initial": {"pc": 49152, "sp": 65534, "a": 0, "b": 0, "c": 0, "d": 0, "e": 0, "f": 96, "h": 0, "l": 0, "ime": 0, "ram": [[49152, 39]]},
+     "final": {"pc": 49153, "sp": 65534, "a": 250, "b": 0, "c": 0, "d": 0, "e": 0, "f": 64, "h": 0, "l": 0, "ime": 0, "ram": [[49152, 39]]},
+     "cycles": [[49152, 39, "r-m"]]},
+    {"name": "27 66+NC -> 06 C kept",
+     "initial": {"pc": 49152, "sp": 65534, "a": 102, "b": 0, "c": 0, "d": 0, "e": 0, "f": 80, "h": 0, "l": 0, "ime": 0, "ram": [[49152, 39]]},
+     "final": {"pc": 49153, "sp": 65534, "a": 6, "b": 0, "c": 0, "d": 0, "e": 0, "f": 80, "h": 0, "l": 0, "ime": 0, "ram": [[49152, 39]]},
+     "cycles": [[49152, 39, "r-m"]]}
+]"#;
+
+// ADD SP,e8 與 LD HL,SP+e8：H/C 以 SP 低位元組與無號 e8 計算，Z 一律清除
+const SP_OFFSET_CASES: &str = r#"[
+    {"name": "E8 00FF+01 -> 0100 HC",
+     "initial": {"pc": 49152, "sp": 255, "a": 0, "b": 0, "c": 0, "d": 0, "e": 0, "f": 128, "h": 0, "l": 0, "ime": 0, "ram": [[49152, 232], [49153, 1]]},
+     "final": {"pc": 49154, "sp": 256, "a": 0, "b": 0, "c": 0, "d": 0, "e": 0, "f": 48, "h": 0, "l": 0, "ime": 0, "ram": [[49152, 232], [49153, 1]]},
+     "cycles": [[49152, 232, "r-m"], [49153, 1, "r-m"], null, null]},
+    {"name": "E8 0000+FF -> FFFF",
+     "initial": {"pc": 49152, "sp": 0, "a": 0, "b": 0, "c": 0, "d": 0, "e": 0, "f": 0, "h": 0, "l": 0, "ime": 0, "ram": [[49152, 232], [49153, 255]]},
+     "final": {"pc": 49154, "sp": 65535, "a": 0, "b": 0, "c": 0, "d": 0, "e": 0, "f": 0, "h": 0, "l": 0, "ime": 0, "ram": [[49152, 232], [49153, 255]]},
+     "cycles": [[49152, 232, "r-m"], [49153, 255, "r-m"], null, null]},
+    {"name": "E8 FFFF+80 -> FF7F C",
+     "initial": {"pc": 49152, "sp": 65535, "a": 0, "b": 0, "c": 0, "d": 0, "e": 0, "f": 0, "h": 0, "l": 0, "ime": 0, "ram": [[49152, 232], [49153, 128]]},
+     "final": {"pc": 49154, "sp": 65407, "a": 0, "b": 0, "c": 0, "d": 0, "e": 0, "f": 16, "h": 0, "l": 0, "ime": 0, "ram": [[49152, 232], [49153, 128]]},
+     "cycles": [[49152, 232, "r-m"], [49153, 128, "r-m"], null, null]},
+    {"name": "F8 00FF+01 -> HL=0100 HC",
+     "initial": {"pc": 49152, "sp": 255, "a": 0, "b": 0, "c": 0, "d": 0, "e": 0, "f": 128, "h": 0, "l": 0, "ime": 0, "ram": [[49152, 248], [49153, 1]]},
+     "final": {"pc": 49154, "sp": 255, "a": 0, "b": 0, "c": 0, "d": 0, "e": 0, "f": 48, "h": 1, "l": 0, "ime": 0, "ram": [[49152, 248], [49153, 1]]},
+     "cycles": [[49152, 248, "r-m"], [49153, 1, "r-m"], null]}
+]"#;
+
+fn assert_inline_cases(json: &str) {
+    let cases: Vec<TestCase> = serde_json::from_str(json).expect("內建測試向量格式錯誤");
+    for case in &cases {
+        if let Err(diffs) = run_case(case) {
+            panic!("{}: {}", case.name, diffs);
+        }
+    }
+}
+
+#[test]
+fn daa_flag_corner_cases() {
+    assert_inline_cases(DAA_CASES);
+}
+
+#[test]
+fn sp_offset_flag_corner_cases() {
+    assert_inline_cases(SP_OFFSET_CASES);
+}
@@ -0,0 +1,83 @@
+// 中斷控制器 - 統一持有 IE (0xFFFF) 與 IF (0xFF0F)
+// 周邊 (PPU、Timer、Serial、Joypad) 透過 request() 請求中斷，CPU 經由匯流排讀寫 IE/IF
+
+use serde::{Deserialize, Serialize};
+
+/// Game Boy 中斷來源，依優先級由高到低排列
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InterruptType {
+    VBlank = 0,
+    LcdStat = 1,
+    Timer = 2,
+    Serial = 3,
+    Joypad = 4,
+}
+
+impl InterruptType {
+    pub const ALL: [InterruptType; 5] = [
+        InterruptType::VBlank,
+        InterruptType::LcdStat,
+        InterruptType::Timer,
+        InterruptType::Serial,
+        InterruptType::Joypad,
+    ];
+
+    // IE/IF 中對應的位元
+    pub fn mask(self) -> u8 {
+        1 << (self as u8)
+    }
+
+    // 中斷向量位址 (0x40, 0x48, 0x50, 0x58, 0x60)
+    pub fn vector(self) -> u16 {
+        0x40 + 8 * (self as u16)
+    }
+}
+
+// Joypad 中斷從下降緣到設置 IF 的延遲 (時鐘數)
+pub const JOYPAD_DELAY: u8 = 4;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterruptController {
+    pub ie: u8,       // 中斷啟用寄存器 (0xFFFF)
+    pub flags: u8,    // 中斷標誌寄存器 (0xFF0F) 的低 5 位
+    joypad_delay: u8, // 等待中的 Joypad 中斷剩餘時鐘數，0 = 沒有
+}
+
+impl InterruptController {
+    pub fn new() -> Self {
+        InterruptController {
+            ie: 0,
+            flags: 0,
+            joypad_delay: 0,
+        }
+    }
+
+    // 請求中斷：設置 IF 中對應的位元；Joypad 中斷延遲 JOYPAD_DELAY 個時鐘才設置
+    pub fn request(&mut self, interrupt: InterruptType) {
+        if interrupt == InterruptType::Joypad {
+            if self.joypad_delay == 0 {
+                self.joypad_delay = JOYPAD_DELAY;
+            }
+        } else {
+            self.flags |= interrupt.mask();
+        }
+    }
+
+    // 每個時鐘呼叫一次，處理 Joypad 中斷延遲
+    pub fn tick(&mut self) {
+        if self.joypad_delay > 0 {
+            self.joypad_delay -= 1;
+            if self.joypad_delay == 0 {
+                self.flags |= InterruptType::Joypad.mask();
+            }
+        }
+    }
+
+    pub fn read_if(&self) -> u8 {
+        self.flags | 0xE0 // 高 3 位始終為 1
+    }
+
+    pub fn write_if(&mut self, value: u8) {
+        self.flags = value & 0x1F;
+    }
+}
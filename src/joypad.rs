@@ -1,8 +1,28 @@
 // Joypad (按鍵輸入) - 處理玩家輸入
-
-use crate::gameboy::InterruptHandler;
-use std::time::{Duration, Instant};
-
+//
+// 核心只保存按鍵與選取線的狀態，P1 的讀值與中斷完全由這些狀態決定，不依賴主機時間；
+// 按壓時間的處理 (例如過短的按鍵) 由前端的輸入層負責。
+
+use crate::interrupt::{InterruptController, InterruptType};
+use sdl3::keyboard::Scancode;
+use serde::{Deserialize, Serialize};
+
+/// 預設鍵盤綁定：掃描碼、對應的 SDL 按鍵名稱 (設定檔以名稱檢查快速鍵重疊) 與 Game Boy 按鍵
+pub const DEFAULT_KEYBOARD: [(Scancode, &str, JoypadKey); 9] = [
+    // 基礎映射 - 適用於所有遊戲
+    (Scancode::Up, "Up", JoypadKey::Up),
+    (Scancode::Down, "Down", JoypadKey::Down),
+    (Scancode::Left, "Left", JoypadKey::Left),
+    (Scancode::Right, "Right", JoypadKey::Right),
+    (Scancode::Return, "Return", JoypadKey::Start),
+    (Scancode::RShift, "Right Shift", JoypadKey::Select),
+    // 統一映射 - 包含 Z, X, 和 Space
+    (Scancode::Z, "Z", JoypadKey::A),
+    (Scancode::X, "X", JoypadKey::B),
+    (Scancode::Space, "Space", JoypadKey::Start), // 額外的 Start 按鍵
+];
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Joypad {
     // 按鍵狀態 (0 代表按下，1 代表放開)
     // 位元: 0=A/右, 1=B/左, 2=Select/上, 3=Start/下
@@ -11,110 +31,17 @@ pub struct Joypad {
 
     // 選取位元 (Bit 4: 方向鍵, Bit 5: 功能鍵)
     pub select: u8,
-
-    // 精確狀態追蹤
-    pub key_states: [KeyState; 8], // 8個按鍵的狀態
-    pub debounce_filter: DebounceFilter,
-    pub interrupt_handler: Option<*mut InterruptHandler>,
-}
-
-#[derive(Debug, Clone)]
-pub struct KeyState {
-    #[allow(dead_code)]
-    pub key: JoypadKey,
-    pub pressed: bool,
-    pub last_change: Instant,
-    pub press_duration: Duration,
-    pub release_duration: Duration,
-    pub bounce_count: u32, // 去抖動計數
-}
-
-impl Default for KeyState {
-    fn default() -> Self {
-        Self {
-            key: JoypadKey::A,
-            pressed: false,
-            last_change: Instant::now(),
-            press_duration: Duration::ZERO,
-            release_duration: Duration::ZERO,
-            bounce_count: 0,
-        }
-    }
-}
-
-#[derive(Debug, Clone)]
-pub struct DebounceFilter {
-    pub debounce_threshold: Duration, // 去抖動閾值 (通常 5-10ms)
-    pub bounce_events_filtered: u64,
-}
-
-impl Default for DebounceFilter {
-    fn default() -> Self {
-        Self {
-            debounce_threshold: Duration::from_millis(5), // 5ms 去抖動
-            bounce_events_filtered: 0,
-        }
-    }
 }
 
 impl Joypad {
     pub fn new() -> Self {
-        let mut key_states = [
-            KeyState {
-                key: JoypadKey::A,
-                ..Default::default()
-            },
-            KeyState {
-                key: JoypadKey::B,
-                ..Default::default()
-            },
-            KeyState {
-                key: JoypadKey::Select,
-                ..Default::default()
-            },
-            KeyState {
-                key: JoypadKey::Start,
-                ..Default::default()
-            },
-            KeyState {
-                key: JoypadKey::Right,
-                ..Default::default()
-            },
-            KeyState {
-                key: JoypadKey::Left,
-                ..Default::default()
-            },
-            KeyState {
-                key: JoypadKey::Up,
-                ..Default::default()
-            },
-            KeyState {
-                key: JoypadKey::Down,
-                ..Default::default()
-            },
-        ];
-
-        // 初始化所有按鍵為放開狀態
-        for state in &mut key_states {
-            state.pressed = false;
-            state.last_change = Instant::now() - Duration::from_secs(1); // 設置為過去的時間，以便第一次按鍵被接受
-        }
-
         Joypad {
             action_keys: 0x0F,    // 預設為全放開 (1)
             direction_keys: 0x0F, // 預設為全放開 (1)
             select: 0x30,         // 預設為不選取 (11)
-            key_states,
-            debounce_filter: DebounceFilter::default(),
-            interrupt_handler: None,
         }
     }
 
-    /// 設置中斷處理器以進行優化的中斷處理
-    pub fn set_interrupt_handler(&mut self, handler: *mut InterruptHandler) {
-        self.interrupt_handler = Some(handler);
-    }
-
     /// 輔助函數：更新位元狀態
     fn update_key_bit(target: &mut u8, mask: u8, pressed: bool) {
         if pressed {
@@ -124,11 +51,8 @@ impl Joypad {
         }
     }
 
-    pub fn read_register(&self) -> u8 {
-        // 高位元(6-7)讀取時通常為 1，位元 4-5 是 select bits
-        let upper = 0xC0 | self.select;
-
-        // 低 4 位預設為 1（沒有按鍵按下）
+    // P10-P13 的電位：選取的按鍵列以 AND 合併，兩條選取線都為 0 時任一列按下都會拉低
+    fn lines(&self) -> u8 {
         let mut keys = 0x0F;
 
         if (self.select & 0x10) == 0 {
@@ -141,96 +65,54 @@ impl Joypad {
             keys &= self.action_keys;
         }
 
-        upper | keys
+        keys
     }
 
-    pub fn write_register(&mut self, value: u8) {
-        // 只允許寫入位元 4 和 5
-        self.select = value & 0x30;
+    pub fn read_register(&self) -> u8 {
+        // 高位元(6-7)讀取時固定為 1，位元 4-5 是 select bits
+        0xC0 | self.select | self.lines()
     }
 
-    // 更新按鍵狀態 (由外部轉送，如 SDL3)
-    // 按下時 bit 設為 0，放開時設為 1，返回是否觸發中斷
-    pub fn set_key(&mut self, key: JoypadKey, pressed: bool) -> bool {
-        let now = Instant::now();
-        let key_index = key.as_index();
-
-        // 應用去抖動過濾
-        if !self.should_process_key_change(key_index, pressed, now) {
-            return false; // 過濾掉抖動事件
-        }
-
-        let old_res = self.read_register();
-        let key_state = &mut self.key_states[key_index];
-
-        // 更新狀態追蹤
-        let previous_pressed = key_state.pressed;
-        let time_since_last_change = now.duration_since(key_state.last_change);
-
-        if pressed != previous_pressed {
-            // 狀態改變
-            if pressed {
-                key_state.press_duration = time_since_last_change;
-            } else {
-                key_state.release_duration = time_since_last_change;
-            }
-            key_state.last_change = now;
-            key_state.pressed = pressed;
-        } else {
-            // 相同狀態，可能是抖動
-            key_state.bounce_count += 1;
-            self.debounce_filter.bounce_events_filtered += 1;
-        }
-
-        // 更新舊的位元狀態以保持兼容性
-        match key {
-            JoypadKey::A => Self::update_key_bit(&mut self.action_keys, 0x01, pressed),
-            JoypadKey::B => Self::update_key_bit(&mut self.action_keys, 0x02, pressed),
-            JoypadKey::Select => Self::update_key_bit(&mut self.action_keys, 0x04, pressed),
-            JoypadKey::Start => Self::update_key_bit(&mut self.action_keys, 0x08, pressed),
-            JoypadKey::Right => Self::update_key_bit(&mut self.direction_keys, 0x01, pressed),
-            JoypadKey::Left => Self::update_key_bit(&mut self.direction_keys, 0x02, pressed),
-            JoypadKey::Up => Self::update_key_bit(&mut self.direction_keys, 0x04, pressed),
-            JoypadKey::Down => Self::update_key_bit(&mut self.direction_keys, 0x08, pressed),
-        }
-
-        let new_res = self.read_register();
-
-        // 如果任何位元從 1 變為 0 (Falling Edge)，觸發 Joypad 中斷
-        let should_trigger_interrupt = (old_res & !new_res & 0x0F) != 0;
-        if should_trigger_interrupt && let Some(handler_ptr) = self.interrupt_handler {
-            unsafe {
-                use crate::gameboy::InterruptType;
-                (*handler_ptr).trigger_interrupt(InterruptType::Joypad);
-            }
-        }
+    // 只允許寫入位元 4 和 5；選取到已按下的按鍵列同樣是下降緣
+    pub fn write_register(&mut self, value: u8, interrupts: &mut InterruptController) {
+        self.update(interrupts, |joypad| joypad.select = value & 0x30);
+    }
 
-        should_trigger_interrupt
+    // 更新按鍵狀態 (由外部轉送，如 SDL3)
+    // 按下時 bit 設為 0，放開時設為 1
+    pub fn set_key(&mut self, key: JoypadKey, pressed: bool, interrupts: &mut InterruptController) {
+        self.update(interrupts, |joypad| match key {
+            JoypadKey::A => Self::update_key_bit(&mut joypad.action_keys, 0x01, pressed),
+            JoypadKey::B => Self::update_key_bit(&mut joypad.action_keys, 0x02, pressed),
+            JoypadKey::Select => Self::update_key_bit(&mut joypad.action_keys, 0x04, pressed),
+            JoypadKey::Start => Self::update_key_bit(&mut joypad.action_keys, 0x08, pressed),
+            JoypadKey::Right => Self::update_key_bit(&mut joypad.direction_keys, 0x01, pressed),
+            JoypadKey::Left => Self::update_key_bit(&mut joypad.direction_keys, 0x02, pressed),
+            JoypadKey::Up => Self::update_key_bit(&mut joypad.direction_keys, 0x04, pressed),
+            JoypadKey::Down => Self::update_key_bit(&mut joypad.direction_keys, 0x08, pressed),
+        });
     }
 
-    /// 檢查是否應該處理按鍵變化（去抖動過濾）
-    fn should_process_key_change(
-        &mut self,
-        key_index: usize,
-        _new_pressed: bool,
-        now: Instant,
-    ) -> bool {
-        let key_state = &self.key_states[key_index];
-        let time_since_last_change = now.duration_since(key_state.last_change);
+    /// 一次設定全部 8 個按鍵 (位元順序同 `JoypadKey::as_index`，1 = 按下)
+    pub fn set_pressed(&mut self, pressed: u8, interrupts: &mut InterruptController) {
+        self.update(interrupts, |joypad| {
+            joypad.action_keys = !pressed & 0x0F;
+            joypad.direction_keys = !(pressed >> 4) & 0x0F;
+        });
+    }
 
-        // 如果時間間隔小於去抖動閾值，過濾掉這個事件
-        if time_since_last_change < self.debounce_filter.debounce_threshold {
-            self.debounce_filter.bounce_events_filtered += 1;
-            return false;
+    // 套用變化後，P10-P13 任何一條從 1 變為 0 (下降緣) 時請求 Joypad 中斷
+    fn update(&mut self, interrupts: &mut InterruptController, change: impl FnOnce(&mut Self)) {
+        let old_lines = self.lines();
+        change(self);
+        if (old_lines & !self.lines()) != 0 {
+            interrupts.request(InterruptType::Joypad);
         }
-
-        true
     }
-
-
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum JoypadKey {
     A,
     B,
@@ -243,6 +125,17 @@ pub enum JoypadKey {
 }
 
 impl JoypadKey {
+    pub const ALL: [JoypadKey; 8] = [
+        JoypadKey::A,
+        JoypadKey::B,
+        JoypadKey::Select,
+        JoypadKey::Start,
+        JoypadKey::Right,
+        JoypadKey::Left,
+        JoypadKey::Up,
+        JoypadKey::Down,
+    ];
+
     /// 將 JoypadKey 轉換為數組索引
     pub fn as_index(self) -> usize {
         match self {
@@ -258,24 +151,90 @@ impl JoypadKey {
     }
 
     /// 獲取鍵盤映射
-    pub fn get_keyboard_mapping() -> std::collections::HashMap<sdl3::keyboard::Scancode, JoypadKey>
-    {
-        let mut mapping = std::collections::HashMap::new();
-        use sdl3::keyboard::Scancode;
+    pub fn get_keyboard_mapping() -> std::collections::HashMap<Scancode, JoypadKey> {
+        DEFAULT_KEYBOARD
+            .iter()
+            .map(|&(scancode, _, key)| (scancode, key))
+            .collect()
+    }
+}
 
-        // 基礎映射 - 適用於所有遊戲
-        mapping.insert(Scancode::Up, JoypadKey::Up);
-        mapping.insert(Scancode::Down, JoypadKey::Down);
-        mapping.insert(Scancode::Left, JoypadKey::Left);
-        mapping.insert(Scancode::Right, JoypadKey::Right);
-        mapping.insert(Scancode::Return, JoypadKey::Start);
-        mapping.insert(Scancode::RShift, JoypadKey::Select);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interrupt::JOYPAD_DELAY;
 
-        // 統一映射 - 包含 Z, X, 和 Space
-        mapping.insert(Scancode::Z, JoypadKey::A);
-        mapping.insert(Scancode::X, JoypadKey::B);
-        mapping.insert(Scancode::Space, JoypadKey::Start); // 額外的 Start 按鍵
+    // 經過 Joypad 中斷延遲後檢查並清除 IF
+    fn joypad_irq(interrupts: &mut InterruptController) -> bool {
+        for _ in 0..JOYPAD_DELAY {
+            interrupts.tick();
+        }
+        let requested = interrupts.flags & InterruptType::Joypad.mask() != 0;
+        interrupts.flags = 0;
+        requested
+    }
+
+    #[test]
+    fn both_select_lines_combine_rows() {
+        let mut joypad = Joypad::new();
+        let mut interrupts = InterruptController::new();
+        joypad.set_key(JoypadKey::A, true, &mut interrupts);
+        joypad.set_key(JoypadKey::Up, true, &mut interrupts);
+
+        joypad.write_register(0x00, &mut interrupts);
+        assert_eq!(joypad.read_register(), 0xC0 | 0x0A);
+        joypad.write_register(0x20, &mut interrupts);
+        assert_eq!(joypad.read_register(), 0xE0 | 0x0B);
+        joypad.write_register(0x30, &mut interrupts);
+        assert_eq!(joypad.read_register(), 0xFF);
+    }
 
-        mapping
+    #[test]
+    fn interrupt_only_on_falling_edge_of_selected_lines() {
+        let mut joypad = Joypad::new();
+        let mut interrupts = InterruptController::new();
+
+        // 未選取的按鍵列不影響 P1
+        joypad.set_key(JoypadKey::Start, true, &mut interrupts);
+        assert!(!joypad_irq(&mut interrupts));
+        // 選取已按下的功能鍵列產生下降緣
+        joypad.write_register(0x10, &mut interrupts);
+        assert!(joypad_irq(&mut interrupts));
+
+        // 同一條線已經為 0 時，再按下共用該線的按鍵不會再觸發
+        joypad.write_register(0x00, &mut interrupts);
+        joypad.set_key(JoypadKey::Down, true, &mut interrupts);
+        assert!(!joypad_irq(&mut interrupts));
+        joypad.set_key(JoypadKey::Start, false, &mut interrupts);
+        joypad.set_key(JoypadKey::Down, false, &mut interrupts);
+        assert!(!joypad_irq(&mut interrupts));
+
+        // 快速按放不會被過濾
+        joypad.set_key(JoypadKey::A, true, &mut interrupts);
+        joypad.set_key(JoypadKey::A, false, &mut interrupts);
+        joypad.set_key(JoypadKey::A, true, &mut interrupts);
+        assert!(joypad_irq(&mut interrupts));
+        assert_eq!(joypad.read_register() & 0x0F, 0x0E);
+    }
+
+    #[test]
+    fn interrupt_flag_is_delayed() {
+        let mut joypad = Joypad::new();
+        let mut interrupts = InterruptController::new();
+        joypad.write_register(0x10, &mut interrupts);
+        joypad.set_key(JoypadKey::A, true, &mut interrupts);
+
+        let joypad_flag = |interrupts: &InterruptController| {
+            interrupts.flags & InterruptType::Joypad.mask() != 0
+        };
+        for _ in 1..JOYPAD_DELAY {
+            interrupts.tick();
+            assert!(!joypad_flag(&interrupts));
+        }
+        // 延遲期間的第二個下降緣不會重新計時
+        joypad.set_key(JoypadKey::A, false, &mut interrupts);
+        joypad.set_key(JoypadKey::B, true, &mut interrupts);
+        interrupts.tick();
+        assert!(joypad_flag(&interrupts));
     }
 }
@@ -1,32 +1,132 @@
+#![forbid(unsafe_code)]
+
 mod apu;
+mod cartridge;
+mod cheats;
+mod cli;
+mod config;
 mod cpu;
+mod debug_view;
+mod debugger;
+mod disasm;
 mod gameboy;
+mod gdbstub;
+mod headless;
+mod input;
 mod instructions;
+mod interrupt;
 mod joypad;
 mod mmu;
+mod movie;
+mod palette;
+mod patch;
 mod ppu;
+mod profiler;
+mod ram_search;
 mod rom;
+mod savestate;
+mod screenshot;
 mod sdl3;
+mod speed;
+mod symbols;
 mod timer;
 
-fn main() {
-    // println!("=== 啟動 Game Boy 模擬器 ===");
+use std::process::ExitCode;
 
+fn main() -> ExitCode {
     // 獲取命令行參數
-    let args: Vec<String> = std::env::args().collect();
-    if args.len() < 2 {
-        println!("用法: {} <ROM 路徑>", args[0]);
-        return;
+    let options = match cli::parse(std::env::args().skip(1)) {
+        Ok(cli::Parsed::Run(options)) => options,
+        Ok(cli::Parsed::Help) => {
+            println!("{}", cli::USAGE);
+            return ExitCode::SUCCESS;
+        }
+        Err(e) => {
+            eprintln!("{}\n使用 --help 查看所有選項", e);
+            return ExitCode::from(sdl3::EXIT_USAGE);
+        }
+    };
+
+    match run(*options) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("錯誤: {}", e);
+            ExitCode::from(e.exit_code())
+        }
     }
+}
 
-    let rom_path = std::path::absolute(&args[1])
-        .map_err(|e| {
-            eprintln!("無效路徑: {}", e);
-            std::process::exit(1);
-        })
-        .unwrap();
-    let rom_path_str = rom_path.to_string_lossy().into_owned();
+fn run(mut options: cli::Options) -> Result<(), sdl3::EmulatorError> {
+    let rom_path = std::path::absolute(&options.rom_path)
+        .map_err(|e| sdl3::EmulatorError::InvalidPath(format!("{}: {}", options.rom_path, e)))?;
+    options.rom_path = rom_path.to_string_lossy().into_owned();
+
+    // --info：只顯示卡帶標頭資訊，不啟動模擬器
+    if options.info {
+        return print_rom_info(&options.rom_path, &options.rom).map_err(Into::into);
+    }
+
+    let config = match options.config.clone().or_else(config::default_path) {
+        Some(path) => config::Config::load(&path)?,
+        None => config::Config::default(),
+    };
+
+    // 先檢查操作碼表與 ROM，失敗時不必開啟視窗
+    cpu::opcodes().map_err(|e| sdl3::EmulatorError::OpcodesLoad(e.to_string()))?;
+    let gb = create_gameboy(&mut options, &config)?;
+
+    if options.headless {
+        headless::run(gb, &options)
+    } else {
+        // 直接進入 SDL3 主程式
+        sdl3::main(gb, options)
+    }
+}
+
+// 依啟動選項建立模擬器：ROM、個別遊戲設定、機種或開機 ROM，最後是即時存檔
+fn create_gameboy(
+    options: &mut cli::Options,
+    config: &config::Config,
+) -> Result<gameboy::GameBoy, sdl3::EmulatorError> {
+    let mut gb = gameboy::GameBoy::new();
+
+    // 存檔目錄在載入 ROM 時就要用到，個別遊戲設定則要讀到卡帶標頭後才知道
+    if options.rom.save_dir.is_none() {
+        options.rom.save_dir = config.paths.save_dir.clone();
+    }
+    gb.load_rom(&options.rom_path, &options.rom)?;
+    let game = gb
+        .mmu
+        .header
+        .as_ref()
+        .map(|header| config.game(header))
+        .unwrap_or_default();
+    options.apply_config(config, &game);
+
+    match options.boot_rom.as_deref() {
+        Some(path) => gb.load_boot_rom(path)?,
+        None => {
+            if let Some(model) = options.model {
+                gb.set_model(model);
+            }
+        }
+    }
+    if let Some(path) = options.state.as_deref() {
+        savestate::load_file(&mut gb, path).map_err(|source| sdl3::EmulatorError::State {
+            path: path.display().to_string(),
+            source,
+        })?;
+    }
+    Ok(gb)
+}
 
-    // 直接進入 SDL3 主程式
-    sdl3::main(rom_path_str);
+fn print_rom_info(path: &str, options: &rom::RomOptions) -> Result<(), gameboy::GameBoyError> {
+    let rom_error = |source: Box<dyn std::error::Error>| gameboy::GameBoyError::RomLoad {
+        path: path.to_string(),
+        source,
+    };
+    let data = rom::read_rom_file(path, options).map_err(|e| rom_error(e.into()))?;
+    let header = cartridge::CartridgeHeader::parse(&data).map_err(|e| rom_error(e.into()))?;
+    println!("{}", header.report(data.len()));
+    Ok(())
 }
@@ -1,42 +1,71 @@
 // 記憶體管理單元 (MMU) - 負責 CPU 與記憶體/I/O 的通訊
 // 整合操作碼資料和記憶體映射
 
-use crate::cpu; // 引用 cpu 模組
+use crate::apu::Apu;
+use crate::cartridge::CartridgeHeader;
+use crate::cheats::Cheats;
+use crate::debugger::Watchpoints;
+use crate::interrupt::{InterruptController, InterruptType};
+use crate::joypad::Joypad;
 use crate::ppu::{LcdMode, Ppu};
 use crate::rom; // 引用 rom 模組
-
-#[allow(dead_code)]
+use crate::timer::Timer;
+use serde::{Deserialize, Serialize};
+use serde_big_array::BigArray;
+use std::path::PathBuf;
+
+/// CPU 所見的記憶體匯流排
+///
+/// `Cpu` 與 `instructions` 內的指令處理器都以此 trait 泛型化，
+/// 因此可以接上 `Mmu`、測試用的平坦 RAM，或記錄每次存取的追蹤匯流排。
+/// 讀取取 `&mut self`，讓實作可以在讀取時記錄或產生副作用。
 pub trait Memory {
-    fn read_byte(&self, address: u16) -> u8;
+    fn read_byte(&mut self, address: u16) -> u8;
     fn write_byte(&mut self, address: u16, value: u8);
-    fn read_word(&self, address: u16) -> u16;
-    fn write_word(&mut self, address: u16, value: u16);
-}
 
-pub trait IoHandler {
-    fn read_io(&self, address: u16) -> u8;
-    fn write_io(&mut self, address: u16, value: u8, interrupt_flags: &mut u8);
+    // 讀取字組 (little-endian)
+    fn read_word(&mut self, address: u16) -> u16 {
+        let low = self.read_byte(address);
+        let high = self.read_byte(address.wrapping_add(1));
+        ((high as u16) << 8) | (low as u16)
+    }
+
+    // CPU 內部的存取 (中斷輪詢與分派時讀寫 IE/IF)，不觸發除錯器監看點
+    fn read_internal(&mut self, address: u16) -> u8 {
+        self.read_byte(address)
+    }
+
+    fn write_internal(&mut self, address: u16, value: u8) {
+        self.write_byte(address, value);
+    }
 }
 
 /// 功能啟用狀態
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum EnableState {
     Enabled,
     Disabled,
 }
 
+// MMU 擁有所有 I/O 周邊，讀寫 I/O 寄存器時直接轉送給對應元件
+// 存檔狀態不包含 ROM、卡帶標頭、金手指與監看點，載入時沿用目前的內容
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Mmu {
-    pub rom: Vec<u8>,          // ROM 數據 (包含所有銀行)
-    pub wram: [u8; 8192],      // WRAM - 8KB 內部工作 RAM
-    pub ext_ram: Vec<u8>,      // 外部卡帶 RAM (根據 MBC 分頁)
-    pub vram: Vec<u8>,         // VRAM - 8KB 視訊 RAM
-    pub oam: Vec<u8>,          // OAM - 160 位元組物件屬性記憶體
-    pub hram: [u8; 127],       // HRAM - 127 位元組高位 RAM
-    pub ie: u8,                // 中斷啟用寄存器
-    pub if_reg: u8,            // 中斷標誌寄存器 (0xFF0F)
-    pub serial_data: u8,       // 專用的串口數據寄存器 (SB)
-    pub serial_control: u8,    // 專用的串口控制寄存器 (SC)
-    pub serial_output: String, // 串口輸出緩衝區 (用於測試 ROM)
+    #[serde(skip)]
+    pub rom: Vec<u8>, // ROM 數據 (包含所有銀行)
+    #[serde(skip)]
+    pub header: Option<CartridgeHeader>, // 已載入 ROM 的卡帶標頭
+    pub boot_rom: Option<Vec<u8>>, // 開機 ROM，對應到 0x0000-0x00FF 直到寫入 0xFF50
+    #[serde(skip)]
+    pub save_path: Option<PathBuf>, // 外部 RAM 存檔路徑 (None 時不讀寫檔案)
+    #[serde(with = "BigArray")]
+    pub wram: [u8; 8192], // WRAM - 8KB 內部工作 RAM
+    pub ext_ram: Vec<u8>,          // 外部卡帶 RAM (根據 MBC 分頁)
+    #[serde(with = "BigArray")]
+    pub hram: [u8; 127], // HRAM - 127 位元組高位 RAM
+    pub serial_data: u8,           // 專用的串口數據寄存器 (SB)
+    pub serial_control: u8,        // 專用的串口控制寄存器 (SC)
+    pub serial_output: String,     // 串口輸出緩衝區 (用於測試 ROM)
 
     // MBC (Memory Bank Controller) 相關狀態
     pub mbc_type: u8,
@@ -45,23 +74,29 @@ pub struct Mmu {
     pub ram_state: EnableState,
     pub banking_mode: u8, // 0 = ROM banking, 1 = RAM banking
 
-    io_handler: Option<Box<dyn IoHandler>>,
-
-    // 供 CPU-side VRAM/OAM 存取限制使用（PPU 內部讀取不受限）
-    ppu: Option<*const Ppu>,
+    // I/O 周邊
+    pub interrupts: InterruptController, // IE (0xFFFF) 與 IF (0xFF0F)
+    pub ppu: Ppu,                        // 0xFF40-0xFF4B，並擁有 VRAM/OAM
+    pub apu: Apu,                        // 0xFF10-0xFF3F
+    pub timer: Timer,                    // 0xFF04-0xFF07
+    pub joypad: Joypad,                  // 0xFF00
+
+    #[serde(skip)]
+    pub watchpoints: Watchpoints, // 除錯器監看點 (僅 CPU 存取時檢查)
+    #[serde(skip)]
+    pub cheats: Cheats, // GameShark / Game Genie 金手指
 }
 
 impl Mmu {
     pub fn new() -> Self {
         Mmu {
             rom: vec![0; 0x8000],
+            header: None,
+            boot_rom: None,
+            save_path: None,
             wram: [0; 0x2000],
             ext_ram: Vec::new(),
-            vram: vec![0; 0x2000], // 8KB VRAM
-            oam: vec![0; 0xA0],    // 160 位元組 OAM
             hram: [0; 127],
-            ie: 0,
-            if_reg: 0xE0,
             serial_data: 0,
             serial_control: 0x7E, // SC 預設值
             serial_output: String::new(),
@@ -72,62 +107,80 @@ impl Mmu {
             ram_state: EnableState::Disabled,
             banking_mode: 0,
 
-            io_handler: None,
-            ppu: None,
-        }
-    }
-
-    // 設置 I/O 處理器
-    pub fn set_io_handler(&mut self, handler: Box<dyn IoHandler>) {
-        self.io_handler = Some(handler);
-    }
+            interrupts: InterruptController::new(),
+            ppu: Ppu::new(),
+            apu: Apu::new(),
+            timer: Timer::new(),
+            joypad: Joypad::new(),
 
-    pub fn set_ppu(&mut self, ppu: &Ppu) {
-        self.ppu = Some(std::ptr::from_ref(ppu));
-    }
-
-    // 獲取操作碼引用 (現在改為引用全域靜態變數)
-    #[allow(dead_code)]
-    pub fn get_opcodes(&self) -> &cpu::Opcodes {
-        &cpu::OPCODES
+            watchpoints: Watchpoints::default(),
+            cheats: Cheats::default(),
+        }
     }
 }
 
 impl Memory for Mmu {
-    fn read_byte(&self, address: u16) -> u8 {
-        self.read_byte(address)
+    #[inline]
+    fn read_byte(&mut self, address: u16) -> u8 {
+        let value = Mmu::read_byte(self, address);
+        self.watchpoints.check(address, value, false);
+        value
     }
 
+    #[inline]
     fn write_byte(&mut self, address: u16, value: u8) {
-        self.write_byte(address, value);
+        self.watchpoints.check(address, value, true);
+        Mmu::write_byte(self, address, value);
     }
 
-    fn read_word(&self, address: u16) -> u16 {
-        let low = self.read_byte(address);
-        let high = self.read_byte(address + 1);
-        ((high as u16) << 8) | (low as u16)
+    fn read_internal(&mut self, address: u16) -> u8 {
+        Mmu::read_byte(self, address)
     }
 
-    fn write_word(&mut self, address: u16, value: u16) {
-        let low = (value & 0xFF) as u8;
-        let high = ((value >> 8) & 0xFF) as u8;
-        self.write_byte(address, low);
-        self.write_byte(address + 1, high);
+    fn write_internal(&mut self, address: u16, value: u8) {
+        Mmu::write_byte(self, address, value);
     }
 }
 
 impl Mmu {
+    // 指定位址目前對應的 ROM bank (0x0000-0x3FFF 為 bank 0，非 ROM 區域回傳 None)
+    pub fn rom_bank_at(&self, address: u16) -> Option<u16> {
+        match address {
+            0x0000..=0x3FFF => Some(0),
+            0x4000..=0x7FFF => Some(if self.mbc_type == 0 { 1 } else { self.rom_bank }),
+            _ => None,
+        }
+    }
+
+    // 讀取指定 ROM bank 的內容 (0x4000-0x7FFF 以外的位址照常讀取)
+    pub fn read_rom_bank(&self, bank: u16, address: u16) -> u8 {
+        match address {
+            0x4000..=0x7FFF => {
+                let addr = (bank as usize * 0x4000) + (address as usize - 0x4000);
+                self.rom[addr % self.rom.len()]
+            }
+            _ => self.read_byte_ppu(address),
+        }
+    }
+
     // 給 PPU/DMA 內部使用：不受 CPU-side VRAM/OAM 存取限制影響
     pub fn read_byte_ppu(&self, address: u16) -> u8 {
         match address {
-            0x0000..=0x3FFF => self.rom[address as usize], // ROM Bank 0
+            // 開機 ROM 執行期間覆蓋卡帶的 0x0000-0x00FF
+            0x0000..=0x00FF if self.boot_rom.is_some() => self
+                .boot_rom
+                .as_ref()
+                .map_or(0xFF, |boot| boot[address as usize]),
+            // ROM 讀取經過 Game Genie 代碼
+            0x0000..=0x3FFF => self.cheats.patch_rom(address, self.rom[address as usize]), // ROM Bank 0
             0x4000..=0x7FFF => {
                 // ROM Bank 1-N (MBC1)
                 let bank = if self.mbc_type == 0 { 1 } else { self.rom_bank };
                 let addr = (bank as usize * 0x4000) + (address as usize - 0x4000);
-                self.rom[addr % self.rom.len()]
+                self.cheats
+                    .patch_rom(address, self.rom[addr % self.rom.len()])
             }
-            0x8000..=0x9FFF => self.vram[(address - 0x8000) as usize], // VRAM
+            0x8000..=0x9FFF => self.ppu.vram[(address - 0x8000) as usize], // VRAM
             0xA000..=0xBFFF => {
                 // 外部 RAM
                 if self.ram_state == EnableState::Enabled && !self.ext_ram.is_empty() {
@@ -139,53 +192,76 @@ impl Mmu {
             }
             0xC000..=0xDFFF => self.wram[(address - 0xC000) as usize], // WRAM
             0xE000..=0xFDFF => self.wram[(address - 0xE000) as usize], // Echo RAM
-            0xFE00..=0xFE9F => self.oam[(address - 0xFE00) as usize],  // OAM
+            0xFE00..=0xFE9F => self.ppu.oam[(address - 0xFE00) as usize], // OAM
             0xFEA0..=0xFEFF => 0xFF,                                   // 未使用區域
-            0xFF00..=0xFF7F => {
-                match address {
-                    0xFF01 => self.serial_data,
-                    0xFF02 => self.serial_control | 0x7E,
-                    0xFF0F => self.if_reg | 0xE0, // 高 3 位始終為 1
-                    _ => {
-                        if let Some(ref handler) = self.io_handler {
-                            handler.as_ref().read_io(address)
-                        } else {
-                            0
-                        }
-                    }
-                }
-            } // I/O 寄存器
+            0xFF00..=0xFF7F => match address {
+                0xFF01 => self.serial_data,
+                0xFF02 => self.serial_control | 0x7E,
+                0xFF0F => self.interrupts.read_if(),
+                0xFF00 => self.joypad.read_register(),
+                0xFF04..=0xFF07 => self.timer.read_register(address),
+                0xFF10..=0xFF3F => self.apu.read_register(address),
+                0xFF40..=0xFF4B => self.ppu.read_register(address),
+                _ => 0,
+            }, // I/O 寄存器
             0xFF80..=0xFFFE => self.hram[(address - 0xFF80) as usize], // HRAM
-            0xFFFF => self.ie,                                         // IE
+            0xFFFF => self.interrupts.ie,                              // IE
         }
     }
 
-    pub fn read_byte(&self, address: u16) -> u8 {
-        // CPU-side VRAM/OAM 存取限制：
-        // - Mode 3 (PixelTransfer) 時，CPU 不能存取 VRAM
-        // - Mode 2/3 時，CPU 不能存取 OAM
-        if let Some(ppu_ptr) = self.ppu {
-            unsafe {
-                let lcd_enabled = ((*ppu_ptr).lcdc & 0x80) != 0;
-                if lcd_enabled {
-                    match address {
-                        0x8000..=0x9FFF => {
-                            if (*ppu_ptr).mode == LcdMode::PixelTransfer {
-                                return 0xFF;
-                            }
-                        }
-                        0xFE00..=0xFE9F => {
-                            if matches!(
-                                (*ppu_ptr).mode,
-                                LcdMode::OamSearch | LcdMode::PixelTransfer
-                            ) {
-                                return 0xFF;
-                            }
-                        }
-                        _ => {}
+    // 給除錯器使用的直接寫入：ROM 與卡帶 RAM 寫入目前映射的 bank 而不切換 MBC，
+    // 不受 VRAM/OAM 存取限制；I/O 只接受沒有副作用的 SB、IF 與 IE，其他位址回傳 false
+    pub fn poke(&mut self, address: u16, value: u8) -> bool {
+        match address {
+            0x0000..=0x00FF if self.boot_rom.is_some() => {
+                match self
+                    .boot_rom
+                    .as_mut()
+                    .and_then(|boot| boot.get_mut(address as usize))
+                {
+                    Some(byte) => *byte = value,
+                    None => return false,
+                }
+            }
+            0x0000..=0x7FFF => {
+                if self.rom.is_empty() {
+                    return false;
+                }
+                let addr = match address {
+                    0x0000..=0x3FFF => address as usize,
+                    _ => {
+                        let bank = self.rom_bank_at(address).unwrap_or(1) as usize;
+                        bank * 0x4000 + (address as usize - 0x4000)
                     }
+                };
+                let len = self.rom.len();
+                self.rom[addr % len] = value;
+            }
+            0x8000..=0x9FFF => self.ppu.vram[(address - 0x8000) as usize] = value,
+            0xA000..=0xBFFF => {
+                if self.ext_ram.is_empty() {
+                    return false;
                 }
+                let addr = (self.ram_bank as usize * 0x2000) + (address as usize - 0xA000);
+                let len = self.ext_ram.len();
+                self.ext_ram[addr % len] = value;
             }
+            0xC000..=0xDFFF => self.wram[(address - 0xC000) as usize] = value,
+            0xE000..=0xFDFF => self.wram[(address - 0xE000) as usize] = value,
+            0xFE00..=0xFE9F => self.ppu.oam[(address - 0xFE00) as usize] = value,
+            0xFF01 => self.serial_data = value,
+            0xFF0F => self.interrupts.write_if(value),
+            0xFEA0..=0xFF7F => return false,
+            0xFF80..=0xFFFE => self.hram[(address - 0xFF80) as usize] = value,
+            0xFFFF => self.interrupts.ie = value,
+        }
+        true
+    }
+
+    pub fn read_byte(&self, address: u16) -> u8 {
+        // CPU-side VRAM/OAM 存取限制 (依 PPU mode)
+        if self.ppu.blocks_cpu_access(address) {
+            return 0xFF;
         }
 
         self.read_byte_ppu(address)
@@ -193,28 +269,8 @@ impl Mmu {
 
     pub fn write_byte(&mut self, address: u16, value: u8) {
         // CPU-side VRAM/OAM 存取限制（同 read_byte 的規則）
-        if let Some(ppu_ptr) = self.ppu {
-            unsafe {
-                let lcd_enabled = ((*ppu_ptr).lcdc & 0x80) != 0;
-                if lcd_enabled {
-                    match address {
-                        0x8000..=0x9FFF => {
-                            if (*ppu_ptr).mode == LcdMode::PixelTransfer {
-                                return;
-                            }
-                        }
-                        0xFE00..=0xFE9F => {
-                            if matches!(
-                                (*ppu_ptr).mode,
-                                LcdMode::OamSearch | LcdMode::PixelTransfer
-                            ) {
-                                return;
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-            }
+        if self.ppu.blocks_cpu_access(address) {
+            return;
         }
 
         match address {
@@ -255,7 +311,7 @@ impl Mmu {
                 }
             }
             0x8000..=0x9FFF => {
-                self.vram[(address - 0x8000) as usize] = value;
+                self.ppu.vram[(address - 0x8000) as usize] = value;
             } // VRAM
             0xA000..=0xBFFF => {
                 // 外部 RAM
@@ -267,11 +323,11 @@ impl Mmu {
             }
             0xC000..=0xDFFF => self.wram[(address - 0xC000) as usize] = value, // WRAM
             0xE000..=0xFDFF => self.wram[(address - 0xE000) as usize] = value, // Echo RAM
-            0xFE00..=0xFE9F => self.oam[(address - 0xFE00) as usize] = value,  // OAM
+            0xFE00..=0xFE9F => self.ppu.oam[(address - 0xFE00) as usize] = value, // OAM
             0xFEA0..=0xFEFF => {}                                              // 未使用
             0xFF00..=0xFF7F => {
                 if address == 0xFF0F {
-                    self.if_reg = value | 0xE0;
+                    self.interrupts.write_if(value);
                 } else if address == 0xFF01 {
                     // Serial Data (SB)
                     self.serial_data = value;
@@ -289,7 +345,7 @@ impl Mmu {
                         }
                         // 模擬傳輸完成：清除 Bit 7 並觸發 Serial 中斷 (Bit 3)
                         self.serial_control &= 0x7F;
-                        self.if_reg |= 0x08;
+                        self.interrupts.request(InterruptType::Serial);
                         // 模擬沒連接設備時，讀取回來的數據會是 0xFF
                         self.serial_data = 0xFF;
                     }
@@ -297,40 +353,35 @@ impl Mmu {
                     // 執行 OAM DMA 傳輸
                     self.perform_dma(value);
                     // 同時更新 PPU 的暫存器
-                    if let Some(ref mut handler) = self.io_handler {
-                        let mut if_reg = self.if_reg;
-                        handler.as_mut().write_io(address, value, &mut if_reg);
-                        self.if_reg = if_reg;
+                    self.ppu
+                        .write_register(address, value, &mut self.interrupts);
+                } else {
+                    match address {
+                        0xFF00 => self.joypad.write_register(value, &mut self.interrupts),
+                        0xFF04..=0xFF07 => self.timer.write_register(address, value),
+                        0xFF10..=0xFF3F => self.apu.write_register(address, value),
+                        0xFF40..=0xFF4B => {
+                            self.ppu
+                                .write_register(address, value, &mut self.interrupts)
+                        }
+                        // 開機 ROM 結束時寫入非零值，之後無法再映射回來
+                        0xFF50 if value != 0 => self.boot_rom = None,
+                        _ => {}
                     }
-                } else if let Some(ref mut handler) = self.io_handler {
-                    let mut if_reg = self.if_reg;
-                    handler.as_mut().write_io(address, value, &mut if_reg);
-                    self.if_reg = if_reg;
                 }
             } // I/O 寄存器
             0xFF80..=0xFFFE => self.hram[(address - 0xFF80) as usize] = value, // HRAM
-            0xFFFF => self.ie = value,                                         // IE
+            0xFFFF => self.interrupts.ie = value,                              // IE
         }
     }
 
-    // 讀取字組 (little-endian)
-    pub fn read_word(&self, address: u16) -> u16 {
-        let low = self.read_byte(address);
-        let high = self.read_byte(address + 1);
-        ((high as u16) << 8) | (low as u16)
-    }
-
-    // 寫入字組 (little-endian)
-    pub fn write_word(&mut self, address: u16, value: u16) {
-        let low = (value & 0xFF) as u8;
-        let high = ((value >> 8) & 0xFF) as u8;
-        self.write_byte(address, low);
-        self.write_byte(address + 1, high);
-    }
-
     // 載入 ROM 資料
-    pub fn load_rom(&mut self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let rom_data = rom::read_rom_file(path)?;
+    pub fn load_rom(
+        &mut self,
+        path: &str,
+        options: &rom::RomOptions,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let rom_data = rom::read_rom_file(path, options)?;
 
         // 偵錯資訊：確認載入成功
         println!("成功載入 ROM: {} (大小: {} bytes)", path, rom_data.len());
@@ -338,24 +389,21 @@ impl Mmu {
         // 重新分配 self.rom 以處理不同大小的 ROM (MBC)
         self.rom = rom_data;
 
-        // 檢查 MBC 與 RAM 大小
-        if self.rom.len() > 0x149 {
-            match self.rom[0x147] {
+        // 依卡帶標頭設定 MBC 與 RAM 大小
+        self.header = CartridgeHeader::parse(&self.rom).ok();
+        if let Some(header) = &self.header {
+            for warning in header.warnings(self.rom.len()) {
+                eprintln!("警告: {}", warning);
+            }
+
+            match header.cartridge_type {
                 1..=3 => self.mbc_type = 1,       // MBC1
                 5..=6 => self.mbc_type = 2,       // MBC2
                 0x0F..=0x13 => self.mbc_type = 3, // MBC3
                 _ => self.mbc_type = 0,
             }
 
-            let ram_size = match self.rom[0x149] {
-                0x01 => 2 * 1024,
-                0x02 => 8 * 1024,
-                0x03 => 32 * 1024,
-                0x04 => 128 * 1024,
-                0x05 => 64 * 1024,
-                _ => 0,
-            };
-
+            let ram_size = header.ram_size();
             if ram_size > 0 {
                 self.ext_ram = vec![0; ram_size];
                 self.load_save_file();
@@ -366,29 +414,61 @@ impl Mmu {
     }
 
     pub fn load_save_file(&mut self) {
-        if let Ok(data) = std::fs::read("save.sav") {
+        let Some(path) = self.save_path.as_ref() else {
+            return;
+        };
+        if let Ok(data) = std::fs::read(path) {
             let len = data.len().min(self.ext_ram.len());
             self.ext_ram[..len].copy_from_slice(&data[..len]);
-            println!("已載入存檔: save.sav ({} bytes)", len);
+            println!("已載入存檔: {} ({} bytes)", path.display(), len);
+        } else if std::path::Path::new("save.sav").exists() {
+            // 舊版不分遊戲固定存到 save.sav，無法確認屬於此 ROM，只提示不讀取
+            println!(
+                "找到舊版存檔 save.sav；若屬於這個遊戲，請改名為 {}",
+                path.display()
+            );
         }
     }
 
     pub fn save_external_ram(&self) {
+        let Some(path) = self.save_path.as_ref() else {
+            return;
+        };
         if !self.ext_ram.is_empty() {
-            if let Err(e) = std::fs::write("save.sav", &self.ext_ram) {
+            if let Err(e) = std::fs::write(path, &self.ext_ram) {
                 eprintln!("存檔失敗: {}", e);
             } else {
-                println!("存檔成功: save.sav");
+                println!("存檔成功: {}", path.display());
             }
         }
     }
 
+    // 推進周邊一個 T-cycle，周邊透過中斷控制器請求中斷
+    pub fn tick(&mut self) {
+        let was_vblank = self.ppu.mode == LcdMode::VBlank;
+        self.ppu.tick(&mut self.interrupts);
+        if !was_vblank && self.ppu.mode == LcdMode::VBlank {
+            self.apply_gameshark();
+        }
+        self.timer.tick(&mut self.interrupts);
+        self.apu.tick();
+        self.interrupts.tick();
+    }
+
+    // GameShark 代碼：每次進入 VBlank 時寫入 RAM
+    fn apply_gameshark(&mut self) {
+        let writes: Vec<(u16, u8)> = self.cheats.ram_writes().collect();
+        for (address, value) in writes {
+            self.write_byte(address, value);
+        }
+    }
+
     // 執行 OAM DMA 傳輸 (0xFF46)
     fn perform_dma(&mut self, value: u8) {
         let source_base = (value as u16) << 8;
         for i in 0..0xA0 {
             let byte = self.read_byte_ppu(source_base + i);
-            self.oam[i as usize] = byte;
+            self.ppu.oam[i as usize] = byte;
         }
     }
 }
@@ -0,0 +1,328 @@
+//! 輸入錄影 (movie)：逐幀記錄按鍵狀態，可重現同一次執行
+//!
+//! 檔案格式：`GBMV` 標記、版本 (u16 LE)，接著是以 bincode 編碼的 [`Movie`]。
+//! 錄影從開機 (記錄機種與卡帶 RAM) 或即時存檔 (整份存檔寫入影片) 開始，每幀一個位元組的按鍵狀態，
+//! 並每隔 [`CHECK_INTERVAL`] 幀記錄即時存檔內容的 CRC32，播放時比對以偵測不同步。
+//! 按鍵以 `Joypad::set_pressed` 一次寫入整幀的狀態。播放時不寫回 `.sav`，避免覆蓋玩家的存檔。
+
+use crate::gameboy::{GameBoy, Model};
+use crate::patch::crc32;
+use crate::savestate::{self, StateError};
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 4] = b"GBMV";
+const VERSION: u16 = 1;
+pub const CHECK_INTERVAL: u64 = 60;
+// 解碼大小上限，約為 12 天的按鍵記錄
+const MAX_MOVIE_SIZE: u64 = 64 * 1024 * 1024;
+
+#[derive(Debug)]
+pub enum MovieError {
+    Io(std::io::Error),
+    InvalidFormat,
+    Version(u16),
+    RomMismatch { expected: u32, found: u32 },
+    Decode(bincode::Error),
+    State(StateError),
+}
+
+impl std::fmt::Display for MovieError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MovieError::Io(err) => write!(f, "無法讀寫影片: {}", err),
+            MovieError::InvalidFormat => write!(f, "不是影片檔"),
+            MovieError::Version(version) => write!(f, "不支援的影片版本 {}", version),
+            MovieError::RomMismatch { expected, found } => write!(
+                f,
+                "影片屬於其他 ROM (CRC32 {:08X}，目前 ROM 為 {:08X})",
+                found, expected
+            ),
+            MovieError::Decode(err) => write!(f, "影片內容損毀: {}", err),
+            MovieError::State(err) => write!(f, "影片的起始存檔: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for MovieError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            MovieError::Io(err) => Some(err),
+            MovieError::Decode(err) => Some(err),
+            MovieError::State(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for MovieError {
+    fn from(err: std::io::Error) -> Self {
+        MovieError::Io(err)
+    }
+}
+
+impl From<bincode::Error> for MovieError {
+    fn from(err: bincode::Error) -> Self {
+        MovieError::Decode(err)
+    }
+}
+
+impl From<StateError> for MovieError {
+    fn from(err: StateError) -> Self {
+        MovieError::State(err)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Start {
+    PowerOn { model: Model, ext_ram: Vec<u8> },
+    State(Vec<u8>),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Movie {
+    pub rom_crc: u32,
+    pub start: Start,
+    pub frames: Vec<u8>, // 每幀的按鍵狀態，位元順序同 `JoypadKey::as_index`，1 = 按下
+    pub checks: Vec<(u64, u32)>, // (幀數, 即時存檔的 CRC32)
+}
+
+impl Movie {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(&VERSION.to_le_bytes());
+        bincode::serialize_into(&mut data, self).expect("寫入記憶體不會失敗");
+        data
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self, MovieError> {
+        if data.len() < 6 || &data[..4] != MAGIC {
+            return Err(MovieError::InvalidFormat);
+        }
+        let version = u16::from_le_bytes([data[4], data[5]]);
+        if version != VERSION {
+            return Err(MovieError::Version(version));
+        }
+        Ok(bincode::DefaultOptions::new()
+            .with_fixint_encoding()
+            .allow_trailing_bytes()
+            .with_limit(MAX_MOVIE_SIZE)
+            .deserialize(&data[6..])?)
+    }
+}
+
+// 模擬狀態的指紋
+fn state_hash(gb: &GameBoy) -> u32 {
+    crc32(&savestate::save(gb))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Record,
+    Play,
+}
+
+/// 錄影或播放中的影片
+#[derive(Debug)]
+pub struct MovieSession {
+    movie: Movie,
+    mode: Mode,
+    path: PathBuf,
+    frame: u64,
+    desynced: bool,
+}
+
+impl MovieSession {
+    /// 從目前的狀態開始錄影；`power_on` 為 false 時把目前狀態整份寫入影片
+    pub fn record(gb: &GameBoy, path: &Path, power_on: bool, model: Model) -> Self {
+        let start = if power_on {
+            Start::PowerOn {
+                model,
+                ext_ram: gb.mmu.ext_ram.clone(),
+            }
+        } else {
+            Start::State(savestate::save(gb))
+        };
+        MovieSession {
+            movie: Movie {
+                rom_crc: crc32(&gb.mmu.rom),
+                start,
+                frames: Vec::new(),
+                checks: Vec::new(),
+            },
+            mode: Mode::Record,
+            path: path.to_path_buf(),
+            frame: 0,
+            desynced: false,
+        }
+    }
+
+    /// 讀取影片並將模擬器設為影片的起始狀態 (開機的影片需要剛載入 ROM 的模擬器)
+    pub fn play(gb: &mut GameBoy, path: &Path) -> Result<Self, MovieError> {
+        let movie = Movie::decode(&std::fs::read(path)?)?;
+        let expected = crc32(&gb.mmu.rom);
+        if movie.rom_crc != expected {
+            return Err(MovieError::RomMismatch {
+                expected,
+                found: movie.rom_crc,
+            });
+        }
+        match &movie.start {
+            Start::PowerOn { model, ext_ram } => {
+                gb.set_model(*model);
+                gb.mmu.ext_ram.clone_from(ext_ram);
+            }
+            Start::State(state) => savestate::load(gb, state)?,
+        }
+        gb.mmu.save_path = None;
+        Ok(MovieSession {
+            movie,
+            mode: Mode::Play,
+            path: path.to_path_buf(),
+            frame: 0,
+            desynced: false,
+        })
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.mode == Mode::Play
+    }
+
+    /// 已結束的幀數與已記錄的幀數
+    #[cfg(test)]
+    pub fn progress(&self) -> (u64, usize) {
+        (self.frame, self.movie.frames.len())
+    }
+
+    /// 播放完所有幀
+    pub fn finished(&self) -> bool {
+        self.is_playing() && self.frame >= self.movie.frames.len() as u64
+    }
+
+    /// 每幀開始前呼叫：錄影時記錄 `live` 的按鍵，播放時改用影片的按鍵
+    pub fn begin_frame(&mut self, gb: &mut GameBoy, live: u8) {
+        let pressed = match self.mode {
+            Mode::Record => {
+                self.movie.frames.push(live);
+                live
+            }
+            Mode::Play => match self.movie.frames.get(self.frame as usize) {
+                Some(&pressed) => pressed,
+                None => return, // 播放完畢後交還給玩家
+            },
+        };
+        gb.set_joypad(pressed);
+    }
+
+    /// 每幀結束後呼叫：定期記錄或比對狀態指紋，第一次不同步時回傳發生的幀數
+    pub fn end_frame(&mut self, gb: &GameBoy) -> Option<u64> {
+        if self.finished() {
+            return None;
+        }
+        self.frame += 1;
+        if !self.frame.is_multiple_of(CHECK_INTERVAL) {
+            return None;
+        }
+        let hash = state_hash(gb);
+        match self.mode {
+            Mode::Record => {
+                self.movie.checks.push((self.frame, hash));
+                None
+            }
+            Mode::Play => {
+                let expected = self
+                    .movie
+                    .checks
+                    .iter()
+                    .find(|&&(frame, _)| frame == self.frame)?;
+                if expected.1 == hash || self.desynced {
+                    return None;
+                }
+                self.desynced = true;
+                Some(self.frame)
+            }
+        }
+    }
+
+    /// 結束錄影並寫入檔案 (播放時不做任何事)
+    pub fn finish(self) -> Result<Option<PathBuf>, MovieError> {
+        if self.mode == Mode::Play {
+            return Ok(None);
+        }
+        std::fs::write(&self.path, self.movie.encode())?;
+        Ok(Some(self.path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::joypad::JoypadKey;
+
+    // 不斷讀取 P1 並把按鍵狀態累加到 WRAM 的小程式
+    fn joypad_program() -> GameBoy {
+        GameBoy::with_program(&[
+            0x3E, 0x10, // LD A,$10 (選取功能鍵)
+            0xE0, 0x00, // LDH ($00),A
+            0xF0, 0x00, // LDH A,($00)
+            0x21, 0x00, 0xD0, // LD HL,$D000
+            0x86, // ADD A,(HL)
+            0x77, // LD (HL),A
+            0x18, 0xF3, // JR -13
+        ])
+    }
+
+    #[test]
+    fn replays_recorded_input_and_detects_desync() {
+        let mut gb = joypad_program();
+        let initial = gb.clone();
+        let path = std::env::temp_dir().join(format!("rust_gb_movie_{}.gbm", std::process::id()));
+
+        let mut recorder = MovieSession::record(&gb, &path, false, Model::Dmg);
+        for frame in 0..CHECK_INTERVAL * 2 {
+            let pressed = if frame % 7 < 3 {
+                1 << JoypadKey::A.as_index()
+            } else {
+                0
+            };
+            recorder.begin_frame(&mut gb, pressed);
+            gb.run_frame();
+            assert_eq!(recorder.end_frame(&gb), None);
+        }
+        let recorded = gb.mmu.read_byte(0xD000);
+        assert_eq!(recorder.finish().unwrap(), Some(path.clone()));
+
+        let mut replay = initial.clone();
+        let mut player = MovieSession::play(&mut replay, &path).unwrap();
+        while !player.finished() {
+            player.begin_frame(&mut replay, 0);
+            replay.run_frame();
+            assert_eq!(player.end_frame(&replay), None);
+        }
+        assert_eq!(replay.mmu.read_byte(0xD000), recorded);
+
+        // 播放途中改變記憶體會在下一個檢查點被偵測到
+        let mut replay = initial;
+        let mut player = MovieSession::play(&mut replay, &path).unwrap();
+        let mut desync = None;
+        while !player.finished() && desync.is_none() {
+            player.begin_frame(&mut replay, 0);
+            replay.run_frame();
+            replay.mmu.write_byte(0xD001, 0x5A);
+            desync = player.end_frame(&replay);
+        }
+        assert_eq!(desync, Some(CHECK_INTERVAL));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn rejects_oversized_length_fields() {
+        let mut data = MAGIC.to_vec();
+        data.extend_from_slice(&VERSION.to_le_bytes());
+        data.extend_from_slice(&0u32.to_le_bytes()); // rom_crc
+        data.extend_from_slice(&1u32.to_le_bytes()); // Start::State
+        data.extend_from_slice(&(MAX_MOVIE_SIZE + 1).to_le_bytes());
+        assert!(matches!(Movie::decode(&data), Err(MovieError::Decode(_))));
+    }
+}
@@ -0,0 +1,100 @@
+//! 顯示調色盤：將 PPU 輸出的 4 種色階轉成 RGBA
+//!
+//! 可用內建名稱 (`gray`、`green`、`pocket`) 或 4 個以逗號分隔的 `RRGGBB`，由亮到暗。
+
+use serde::Deserialize;
+
+/// 4 種色階的 RGBA 值 (索引 0 為最亮)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Palette(pub [[u8; 4]; 4]);
+
+impl Palette {
+    pub const GRAY: Palette = Palette([
+        [255, 255, 255, 255],
+        [170, 170, 170, 255],
+        [85, 85, 85, 255],
+        [0, 0, 0, 255],
+    ]);
+    // 原版 DMG 的黃綠色液晶
+    pub const GREEN: Palette = Palette([
+        [0x9B, 0xBC, 0x0F, 255],
+        [0x8B, 0xAC, 0x0F, 255],
+        [0x30, 0x62, 0x30, 255],
+        [0x0F, 0x38, 0x0F, 255],
+    ]);
+    pub const POCKET: Palette = Palette([
+        [0xC4, 0xCF, 0xA1, 255],
+        [0x8B, 0x95, 0x6D, 255],
+        [0x4D, 0x53, 0x3C, 255],
+        [0x1F, 0x1F, 0x1F, 255],
+    ]);
+
+    pub fn color(&self, shade: u8) -> [u8; 4] {
+        self.0[(shade & 0x03) as usize]
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::GRAY
+    }
+}
+
+impl TryFrom<String> for Palette {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        text.parse()
+    }
+}
+
+impl std::str::FromStr for Palette {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.to_ascii_lowercase().as_str() {
+            "gray" | "grey" => return Ok(Palette::GRAY),
+            "green" | "dmg" => return Ok(Palette::GREEN),
+            "pocket" => return Ok(Palette::POCKET),
+            _ => {}
+        }
+
+        let invalid = || {
+            format!(
+                "無效的調色盤: {} (可用 gray、green、pocket 或 4 個 RRGGBB)",
+                text
+            )
+        };
+        let colors: Vec<&str> = text.split(',').map(str::trim).collect();
+        if colors.len() != 4 {
+            return Err(invalid());
+        }
+        let mut palette = [[0u8; 4]; 4];
+        for (slot, hex) in palette.iter_mut().zip(colors) {
+            let hex = hex.trim_start_matches('#');
+            if hex.len() != 6 || !hex.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(invalid());
+            }
+            let rgb = u32::from_str_radix(hex, 16).map_err(|_| invalid())?;
+            let [_, r, g, b] = rgb.to_be_bytes();
+            *slot = [r, g, b, 255];
+        }
+        Ok(Palette(palette))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_named_and_custom_palettes() {
+        assert_eq!("Green".parse::<Palette>().unwrap(), Palette::GREEN);
+        let custom: Palette = "FFFFFF, #C0C0C0,808080,000000".parse().unwrap();
+        assert_eq!(custom.color(1), [0xC0, 0xC0, 0xC0, 255]);
+        assert_eq!(custom.color(7), [0, 0, 0, 255]);
+        assert!("FFFFFF,C0C0C0,808080".parse::<Palette>().is_err());
+        assert!("FFFFFF,C0C0C0,808080,+00000".parse::<Palette>().is_err());
+    }
+}
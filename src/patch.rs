@@ -0,0 +1,509 @@
+//! ROM 軟修補 (IPS / BPS / UPS)
+//!
+//! 修補只套用在記憶體中的 ROM 資料，原始檔案不會被修改。
+//! 格式依檔頭判斷：`PATCH` 為 IPS、`BPS1` 為 BPS、`UPS1` 為 UPS。
+//! BPS/UPS 會檢查來源、結果與修補檔本身的 CRC32，來源不符時回傳錯誤而不套用。
+
+use crate::rom::MAX_ROM_SIZE;
+use std::path::{Path, PathBuf};
+
+/// 尋找同名修補檔時依序嘗試的副檔名 (有 CRC 驗證的格式優先)
+pub const PATCH_EXTENSIONS: [&str; 3] = ["bps", "ups", "ips"];
+
+#[derive(Debug)]
+pub enum PatchError {
+    Io(std::io::Error),
+    UnknownFormat,
+    Truncated,
+    Invalid,
+    TargetTooLarge(usize),
+    SourceSize { expected: usize, actual: usize },
+    SourceChecksum { expected: u32, actual: u32 },
+    TargetChecksum { expected: u32, actual: u32 },
+    PatchChecksum { expected: u32, actual: u32 },
+}
+
+impl std::fmt::Display for PatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PatchError::Io(err) => write!(f, "無法讀取修補檔: {}", err),
+            PatchError::UnknownFormat => write!(f, "無法辨識的修補檔格式 (支援 IPS / BPS / UPS)"),
+            PatchError::Truncated => write!(f, "修補檔不完整"),
+            PatchError::Invalid => write!(f, "修補檔內容無效"),
+            PatchError::TargetTooLarge(size) => write!(
+                f,
+                "修補結果過大: {} bytes (上限 {} bytes)",
+                size, MAX_ROM_SIZE
+            ),
+            PatchError::SourceSize { expected, actual } => write!(
+                f,
+                "修補檔不適用於此 ROM: 預期大小 {} bytes，實際為 {} bytes",
+                expected, actual
+            ),
+            PatchError::SourceChecksum { expected, actual } => write!(
+                f,
+                "修補檔不適用於此 ROM: 預期 CRC32 {:08X}，實際為 {:08X}",
+                expected, actual
+            ),
+            PatchError::TargetChecksum { expected, actual } => write!(
+                f,
+                "修補結果的 CRC32 不符: 預期 {:08X}，實際為 {:08X}",
+                expected, actual
+            ),
+            PatchError::PatchChecksum { expected, actual } => write!(
+                f,
+                "修補檔已損毀: 預期 CRC32 {:08X}，實際為 {:08X}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PatchError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            PatchError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for PatchError {
+    fn from(err: std::io::Error) -> Self {
+        PatchError::Io(err)
+    }
+}
+
+/// 與 ROM 同名 (同一目錄、相同 stem) 的修補檔
+pub fn find_sibling_patch(rom_path: &Path) -> Option<PathBuf> {
+    PATCH_EXTENSIONS
+        .iter()
+        .map(|extension| rom_path.with_extension(extension))
+        .find(|path| path.is_file())
+}
+
+/// 依檔頭判斷格式並套用修補，回傳新的 ROM 資料
+pub fn apply(source: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    if patch.starts_with(b"PATCH") {
+        apply_ips(source, patch)
+    } else if patch.starts_with(b"BPS1") {
+        apply_bps(source, patch)
+    } else if patch.starts_with(b"UPS1") {
+        apply_ups(source, patch)
+    } else {
+        Err(PatchError::UnknownFormat)
+    }
+}
+
+// 依序讀取修補檔內容，越界時回傳 Truncated
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8], pos: usize) -> Self {
+        Reader { data, pos }
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], PatchError> {
+        let end = self.pos.checked_add(count).ok_or(PatchError::Truncated)?;
+        let slice = self.data.get(self.pos..end).ok_or(PatchError::Truncated)?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn byte(&mut self) -> Result<u8, PatchError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn big_endian(&mut self, count: usize) -> Result<usize, PatchError> {
+        Ok(self
+            .bytes(count)?
+            .iter()
+            .fold(0, |value, &byte| (value << 8) | byte as usize))
+    }
+
+    // BPS/UPS 的可變長度整數，超出 usize 時回傳 Invalid
+    fn varint(&mut self) -> Result<usize, PatchError> {
+        let mut value = 0usize;
+        let mut shift = 1usize;
+        loop {
+            let byte = self.byte()?;
+            value = ((byte & 0x7F) as usize)
+                .checked_mul(shift)
+                .and_then(|bits| value.checked_add(bits))
+                .ok_or(PatchError::Invalid)?;
+            if byte & 0x80 != 0 {
+                return Ok(value);
+            }
+            // checked_shl 只檢查位移量，位元溢出需以乘法檢查
+            shift = shift.checked_mul(0x80).ok_or(PatchError::Invalid)?;
+            value = value.checked_add(shift).ok_or(PatchError::Invalid)?;
+        }
+    }
+}
+
+fn apply_ips(source: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    const EOF: usize = 0x454F46; // "EOF"
+    let mut target = source.to_vec();
+    let mut reader = Reader::new(patch, 5);
+
+    loop {
+        let offset = reader.big_endian(3)?;
+        if offset == EOF {
+            break;
+        }
+        let size = reader.big_endian(2)?;
+        // 大小為 0 表示 RLE：2 位元組長度 + 1 位元組數值
+        let (length, data) = if size == 0 {
+            let length = reader.big_endian(2)?;
+            (length, None)
+        } else {
+            (size, Some(reader.bytes(size)?))
+        };
+        if target.len() < offset + length {
+            check_target_size(offset + length)?;
+            target.resize(offset + length, 0);
+        }
+        match data {
+            Some(data) => target[offset..offset + length].copy_from_slice(data),
+            None => {
+                let value = reader.byte()?;
+                target[offset..offset + length].fill(value);
+            }
+        }
+    }
+
+    // 部分 IPS 在 EOF 之後附帶截斷長度
+    if let Ok(length) = reader.big_endian(3) {
+        target.truncate(length);
+    }
+    Ok(target)
+}
+
+// 檢查 BPS/UPS 結尾的 12 位元組 CRC：回傳 (來源 CRC, 結果 CRC)
+fn check_footer(source: &[u8], patch: &[u8]) -> Result<(u32, u32), PatchError> {
+    if patch.len() < 16 {
+        return Err(PatchError::Truncated);
+    }
+    let footer = &patch[patch.len() - 12..];
+    let crc_at = |index: usize| u32::from_le_bytes(footer[index..index + 4].try_into().unwrap());
+
+    let actual = crc32(&patch[..patch.len() - 4]);
+    if actual != crc_at(8) {
+        return Err(PatchError::PatchChecksum {
+            expected: crc_at(8),
+            actual,
+        });
+    }
+    let actual = crc32(source);
+    if actual != crc_at(0) {
+        return Err(PatchError::SourceChecksum {
+            expected: crc_at(0),
+            actual,
+        });
+    }
+    Ok((crc_at(0), crc_at(4)))
+}
+
+fn check_target(target: &[u8], expected: u32) -> Result<(), PatchError> {
+    let actual = crc32(target);
+    if actual != expected {
+        return Err(PatchError::TargetChecksum { expected, actual });
+    }
+    Ok(())
+}
+
+// 修補檔宣告的結果大小在配置記憶體前先檢查，避免惡意的大小造成配置失敗
+fn check_target_size(size: usize) -> Result<(), PatchError> {
+    if size as u64 > MAX_ROM_SIZE {
+        return Err(PatchError::TargetTooLarge(size));
+    }
+    Ok(())
+}
+
+fn check_source_size(source: &[u8], expected: usize) -> Result<(), PatchError> {
+    if source.len() != expected {
+        return Err(PatchError::SourceSize {
+            expected,
+            actual: source.len(),
+        });
+    }
+    Ok(())
+}
+
+fn apply_bps(source: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let (_, target_crc) = check_footer(source, patch)?;
+    let end = patch.len() - 12;
+    let mut reader = Reader::new(&patch[..end], 4);
+
+    let source_size = reader.varint()?;
+    let target_size = reader.varint()?;
+    let metadata_size = reader.varint()?;
+    reader.bytes(metadata_size)?;
+    check_source_size(source, source_size)?;
+    check_target_size(target_size)?;
+
+    let mut target = Vec::with_capacity(target_size);
+    let mut source_offset = 0isize;
+    let mut target_offset = 0isize;
+
+    // 相對位移：最低位元為正負號
+    let relative = |reader: &mut Reader, offset: &mut isize| -> Result<(), PatchError> {
+        let data = reader.varint()?;
+        let delta = (data >> 1) as isize;
+        *offset = if data & 1 != 0 {
+            offset.checked_sub(delta)
+        } else {
+            offset.checked_add(delta)
+        }
+        .ok_or(PatchError::Invalid)?;
+        Ok(())
+    };
+
+    while reader.pos < end {
+        let data = reader.varint()?;
+        let length = (data >> 2) + 1;
+        // 每個動作都不能超出宣告的結果大小
+        if length > target_size - target.len() {
+            return Err(PatchError::Invalid);
+        }
+        match data & 3 {
+            // SourceRead：複製來源中相同位置的資料
+            0 => {
+                let start = target.len();
+                target.extend_from_slice(
+                    source
+                        .get(start..start + length)
+                        .ok_or(PatchError::Truncated)?,
+                );
+            }
+            // TargetRead：直接取自修補檔
+            1 => target.extend_from_slice(reader.bytes(length)?),
+            // SourceCopy：從來源的相對位置複製
+            2 => {
+                relative(&mut reader, &mut source_offset)?;
+                let start = usize::try_from(source_offset).map_err(|_| PatchError::Truncated)?;
+                target.extend_from_slice(
+                    source
+                        .get(start..start + length)
+                        .ok_or(PatchError::Truncated)?,
+                );
+                source_offset += length as isize;
+            }
+            // TargetCopy：從已輸出的結果複製 (可與輸出重疊，需逐位元組複製)
+            _ => {
+                relative(&mut reader, &mut target_offset)?;
+                for _ in 0..length {
+                    let index =
+                        usize::try_from(target_offset).map_err(|_| PatchError::Truncated)?;
+                    let byte = *target.get(index).ok_or(PatchError::Truncated)?;
+                    target.push(byte);
+                    target_offset += 1;
+                }
+            }
+        }
+    }
+
+    if target.len() != target_size {
+        return Err(PatchError::Truncated);
+    }
+    check_target(&target, target_crc)?;
+    Ok(target)
+}
+
+fn apply_ups(source: &[u8], patch: &[u8]) -> Result<Vec<u8>, PatchError> {
+    let (_, target_crc) = check_footer(source, patch)?;
+    let end = patch.len() - 12;
+    let mut reader = Reader::new(&patch[..end], 4);
+
+    let source_size = reader.varint()?;
+    let target_size = reader.varint()?;
+    check_source_size(source, source_size)?;
+    check_target_size(target_size)?;
+
+    // 結果先以來源資料填入，超出來源的部分視為 0
+    let mut target = source.to_vec();
+    target.resize(target_size, 0);
+
+    let mut pos = 0usize;
+    while reader.pos < end {
+        pos = pos
+            .checked_add(reader.varint()?)
+            .ok_or(PatchError::Invalid)?;
+        // XOR 區塊以 0x00 結束
+        loop {
+            let byte = reader.byte()?;
+            if byte == 0 {
+                pos = pos.saturating_add(1);
+                break;
+            }
+            if let Some(slot) = target.get_mut(pos) {
+                *slot ^= byte;
+            }
+            pos = pos.saturating_add(1);
+        }
+    }
+
+    check_target(&target, target_crc)?;
+    Ok(target)
+}
+
+/// CRC-32 (IEEE 802.3，與 zip/BPS/UPS 相同)
+pub fn crc32(data: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn varint(mut value: usize, out: &mut Vec<u8>) {
+        loop {
+            let byte = (value & 0x7F) as u8;
+            value >>= 7;
+            if value == 0 {
+                out.push(byte | 0x80);
+                return;
+            }
+            out.push(byte);
+            value -= 1;
+        }
+    }
+
+    fn finish(mut patch: Vec<u8>, source: &[u8], target: &[u8]) -> Vec<u8> {
+        patch.extend_from_slice(&crc32(source).to_le_bytes());
+        patch.extend_from_slice(&crc32(target).to_le_bytes());
+        let crc = crc32(&patch);
+        patch.extend_from_slice(&crc.to_le_bytes());
+        patch
+    }
+
+    #[test]
+    fn crc32_matches_reference_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn applies_ips_records_and_rle() {
+        let source = [0u8; 8];
+        let mut patch = b"PATCH".to_vec();
+        patch.extend_from_slice(&[0x00, 0x00, 0x02, 0x00, 0x02, 0xAA, 0xBB]); // 位移 2 寫入 AA BB
+        patch.extend_from_slice(&[0x00, 0x00, 0x08, 0x00, 0x00, 0x00, 0x03, 0xCC]); // RLE 延伸 3 bytes
+        patch.extend_from_slice(b"EOF");
+
+        let target = apply(&source, &patch).unwrap();
+        assert_eq!(target, [0, 0, 0xAA, 0xBB, 0, 0, 0, 0, 0xCC, 0xCC, 0xCC]);
+        assert!(matches!(
+            apply(&source, b"PATCH\x00\x00"),
+            Err(PatchError::Truncated)
+        ));
+    }
+
+    #[test]
+    fn applies_bps_actions() {
+        let source = b"HELLO WORLD".to_vec();
+        let target = b"HELLO HELLO!!!!".to_vec();
+        let mut patch = b"BPS1".to_vec();
+        varint(source.len(), &mut patch);
+        varint(target.len(), &mut patch);
+        varint(0, &mut patch);
+        varint((6 - 1) << 2, &mut patch); // SourceRead "HELLO "
+        varint(((5 - 1) << 2) | 2, &mut patch); // SourceCopy 來源 0 起 "HELLO"
+        varint(0, &mut patch);
+        varint(1, &mut patch); // TargetRead 長度 1 "!"
+        patch.push(b'!');
+        varint(((3 - 1) << 2) | 3, &mut patch); // TargetCopy 重疊複製 "!!!"
+        varint(11 << 1, &mut patch);
+        let patch = finish(patch, &source, &target);
+
+        assert_eq!(apply(&source, &patch).unwrap(), target);
+    }
+
+    #[test]
+    fn bps_rejects_wrong_source() {
+        let source = b"ABCD".to_vec();
+        let mut patch = b"BPS1".to_vec();
+        varint(4, &mut patch);
+        varint(4, &mut patch);
+        varint(0, &mut patch);
+        varint(3 << 2, &mut patch);
+        let patch = finish(patch, &source, &source);
+
+        let err = apply(b"ABCE", &patch).unwrap_err();
+        assert!(matches!(
+            err,
+            PatchError::SourceChecksum { expected, .. } if expected == crc32(b"ABCD")
+        ));
+
+        let mut corrupt = patch.clone();
+        corrupt[5] ^= 1;
+        assert!(matches!(
+            apply(&source, &corrupt),
+            Err(PatchError::PatchChecksum { .. })
+        ));
+    }
+
+    #[test]
+    fn applies_ups_xor_hunks() {
+        let source = b"ABCDEF".to_vec();
+        let target = b"ABXDEFGH".to_vec();
+        let mut patch = b"UPS1".to_vec();
+        varint(source.len(), &mut patch);
+        varint(target.len(), &mut patch);
+        varint(2, &mut patch);
+        patch.extend_from_slice(&[b'C' ^ b'X', 0x00]);
+        varint(2, &mut patch); // 結束的 0x00 也佔一個位置
+        patch.extend_from_slice(&[b'G', b'H', 0x00]);
+        let patch = finish(patch, &source, &target);
+
+        assert_eq!(apply(&source, &patch).unwrap(), target);
+        assert!(matches!(
+            apply(b"ABCDE", &patch),
+            Err(PatchError::SourceChecksum { .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_varints_and_oversized_targets() {
+        let source = b"ABCD".to_vec();
+        let header = |format: &[u8], sizes: &[u8]| {
+            let mut patch = format.to_vec();
+            patch.extend_from_slice(sizes);
+            finish(patch, &source, &source)
+        };
+
+        // 沒有結束位元的 varint
+        assert!(matches!(
+            apply(&source, &header(b"BPS1", &[0x04, 0x04])),
+            Err(PatchError::Truncated)
+        ));
+        // 超出 usize 的 varint
+        assert!(matches!(
+            apply(&source, &header(b"UPS1", &[0x7F; 12])),
+            Err(PatchError::Invalid)
+        ));
+
+        // 宣告的結果大小超過 ROM 上限時不配置記憶體
+        let mut sizes = Vec::new();
+        varint(source.len(), &mut sizes);
+        varint(1 << 40, &mut sizes);
+        for format in [b"BPS1", b"UPS1"] {
+            let mut patch = sizes.clone();
+            varint(0, &mut patch);
+            assert!(matches!(
+                apply(&source, &header(format, &patch)),
+                Err(PatchError::TargetTooLarge(size)) if size == 1 << 40
+            ));
+        }
+    }
+}
@@ -1,6 +1,8 @@
 // PPU (Picture Processing Unit) - Game Boy 圖形處理器
 
+use crate::interrupt::{InterruptController, InterruptType};
 use crate::mmu::EnableState;
+use serde::{Deserialize, Serialize};
 
 /// 精靈大小
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -11,7 +13,7 @@ pub enum SpriteSize {
     Size8x16,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub struct Sprite {
     pub y_pos: u8,      // Y 位置 (實際位置 = y_pos - 16)
     pub x_pos: u8,      // X 位置 (實際位置 = x_pos - 8)
@@ -35,6 +37,44 @@ impl Sprite {
     }
 }
 
+/// 除錯用的圖層開關
+///
+/// 只在 `render_scanline` 合成畫面時生效：LCDC 讀回的值、視窗行計數器與
+/// 每行 10 個精靈的選取都維持原樣，被隱藏的圖層視為透明 (顏色 0)。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PpuDebugOptions {
+    pub background: bool,
+    pub window: bool,
+    pub sprites: bool,
+    pub hidden_sprites: u64, // 以 OAM 索引為位元的隱藏遮罩 (0-39)
+}
+
+impl Default for PpuDebugOptions {
+    fn default() -> Self {
+        PpuDebugOptions {
+            background: true,
+            window: true,
+            sprites: true,
+            hidden_sprites: 0,
+        }
+    }
+}
+
+impl PpuDebugOptions {
+    pub fn sprite_visible(&self, index: usize) -> bool {
+        self.hidden_sprites & (1 << index) == 0
+    }
+
+    pub fn set_sprite_visible(&mut self, index: usize, visible: bool) {
+        if visible {
+            self.hidden_sprites &= !(1 << index);
+        } else {
+            self.hidden_sprites |= 1 << index;
+        }
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Ppu {
     // LCD 控制寄存器
     pub lcdc: u8, // 0xFF40 - LCD 控制
@@ -50,6 +90,10 @@ pub struct Ppu {
     pub wy: u8,   // 0xFF4A - 視窗 Y 位置
     pub wx: u8,   // 0xFF4B - 視窗 X 位置
 
+    // 視訊記憶體 - 由 PPU 擁有，CPU 經由 MMU 存取並受 mode 限制
+    pub vram: Vec<u8>, // VRAM - 8KB 視訊 RAM (0x8000-0x9FFF)
+    pub oam: Vec<u8>,  // OAM - 160 位元組物件屬性記憶體 (0xFE00-0xFE9F)
+
     // 內部狀態
     pub mode: LcdMode, // 當前 LCD 模式
     pub dots: u16,     // 點計數器
@@ -73,9 +117,13 @@ pub struct Ppu {
 
     // 幀完成旗標：在進入 VBlank 時置位，供外部同步顯示
     frame_ready: bool,
+
+    // 除錯用圖層開關
+    #[serde(skip)]
+    pub debug: PpuDebugOptions,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum LcdMode {
     HBlank = 0,        // 水平空白期
     VBlank = 1,        // 垂直空白期
@@ -98,6 +146,8 @@ impl Ppu {
             obp1: 0xFF, // 預設精靈調色板 1
             wy: 0,
             wx: 0,
+            vram: vec![0; 0x2000],
+            oam: vec![0; 0xA0],
             mode: LcdMode::OamSearch,
             dots: 0,
             oam_sprites: Vec::new(),
@@ -107,6 +157,7 @@ impl Ppu {
             window_line_counter: 0,
             window_triggered: false,
             frame_ready: false,
+            debug: PpuDebugOptions::default(),
         }
     }
 
@@ -121,6 +172,25 @@ impl Ppu {
         &self.present_buffer
     }
 
+    // PPU 內部讀取 VRAM (不受 CPU 存取限制)
+    fn vram_byte(&self, addr: u16) -> u8 {
+        self.vram[(addr - 0x8000) as usize]
+    }
+
+    // CPU 在目前模式下是否被禁止存取此位址
+    // - Mode 3 (PixelTransfer) 時，CPU 不能存取 VRAM
+    // - Mode 2/3 時，CPU 不能存取 OAM
+    pub fn blocks_cpu_access(&self, addr: u16) -> bool {
+        if self.lcd_state() == EnableState::Disabled {
+            return false;
+        }
+        match addr {
+            0x8000..=0x9FFF => self.mode == LcdMode::PixelTransfer,
+            0xFE00..=0xFE9F => matches!(self.mode, LcdMode::OamSearch | LcdMode::PixelTransfer),
+            _ => false,
+        }
+    }
+
     // 讀取 LCD 寄存器
     pub fn read_register(&self, addr: u16) -> u8 {
         match addr {
@@ -141,7 +211,7 @@ impl Ppu {
     }
 
     // 寫入 LCD 寄存器
-    pub fn write_register(&mut self, addr: u16, value: u8, interrupt_flags: &mut u8) {
+    pub fn write_register(&mut self, addr: u16, value: u8, interrupts: &mut InterruptController) {
         match addr {
             0xFF40 => {
                 let old_lcdc = self.lcdc;
@@ -166,14 +236,14 @@ impl Ppu {
             0xFF41 => {
                 // 位 0-2 只讀，位 7 始終為 1
                 self.stat = (self.stat & 0x07) | (value & 0x78) | 0x80;
-                self.update_stat(interrupt_flags);
+                self.update_stat(interrupts);
             }
             0xFF42 => self.scy = value,
             0xFF43 => self.scx = value,
             0xFF44 => {} // LY 是只讀的
             0xFF45 => {
                 self.lyc = value;
-                self.update_stat(interrupt_flags);
+                self.update_stat(interrupts);
             }
             0xFF46 => self.dma = value,
             0xFF47 => self.bgp = value,
@@ -185,7 +255,7 @@ impl Ppu {
         }
     }
 
-    fn update_stat(&mut self, interrupt_flags: &mut u8) {
+    fn update_stat(&mut self, interrupts: &mut InterruptController) {
         // 更新 LYC == LY 標誌 (Bit 2)
         if self.ly == self.lyc {
             self.stat |= 0x04;
@@ -202,33 +272,24 @@ impl Ppu {
         }
 
         // Mode 中斷
-        match self.mode {
-            LcdMode::HBlank => {
-                if (self.stat & 0x08) != 0 {
-                    irq = Some(());
-                }
-            }
-            LcdMode::VBlank => {
-                if (self.stat & 0x10) != 0 {
-                    irq = Some(());
-                }
-            }
-            LcdMode::OamSearch => {
-                if (self.stat & 0x20) != 0 {
-                    irq = Some(());
-                }
-            }
-            _ => {}
+        let mode_irq_enabled = match self.mode {
+            LcdMode::HBlank => (self.stat & 0x08) != 0,
+            LcdMode::VBlank => (self.stat & 0x10) != 0,
+            LcdMode::OamSearch => (self.stat & 0x20) != 0,
+            LcdMode::PixelTransfer => false,
+        };
+        if mode_irq_enabled {
+            irq = Some(());
         }
 
         // 升緣觸發中斷 - 只在從無中斷變為有中斷時觸發
         if irq.is_some() && self.prev_stat_irq.is_none() {
-            *interrupt_flags |= 0x02; // LCD STAT 中斷 (Bit 1)
+            interrupts.request(InterruptType::LcdStat);
         }
         self.prev_stat_irq = irq;
     }
 
-    fn change_mode(&mut self, mode: LcdMode, interrupt_flags: &mut u8) {
+    fn change_mode(&mut self, mode: LcdMode, interrupts: &mut InterruptController) {
         self.mode = mode;
         self.stat = (self.stat & 0xFC) | (mode as u8);
 
@@ -236,27 +297,27 @@ impl Ppu {
             self.oam_sprites.clear();
         }
 
-        self.update_stat(interrupt_flags);
+        self.update_stat(interrupts);
     }
 
     // 從 OAM 讀取精靈資料
-    fn read_sprite_data(&self, mmu: &crate::mmu::Mmu, sprite_index: usize) -> (u8, u8, u8, u8) {
-        let base_addr = 0xFE00 + (sprite_index * 4) as u16;
-        let y_pos = mmu.read_byte_ppu(base_addr);
-        let x_pos = mmu.read_byte_ppu(base_addr + 1);
-        let tile_index = mmu.read_byte_ppu(base_addr + 2);
-        let attributes = mmu.read_byte_ppu(base_addr + 3);
+    fn read_sprite_data(&self, sprite_index: usize) -> (u8, u8, u8, u8) {
+        let base = sprite_index * 4;
+        let y_pos = self.oam[base];
+        let x_pos = self.oam[base + 1];
+        let tile_index = self.oam[base + 2];
+        let attributes = self.oam[base + 3];
 
         (y_pos, x_pos, tile_index, attributes)
     }
 
     // 檢查精靈在當前掃描線上的可見性
-    fn check_sprite_visibility(&mut self, mmu: &crate::mmu::Mmu, sprite_index: usize) {
+    fn check_sprite_visibility(&mut self, sprite_index: usize) {
         if self.oam_sprites.len() >= 10 {
             return; // 每條掃描線最多 10 個精靈
         }
 
-        let (y_pos, x_pos, tile_index, attributes) = self.read_sprite_data(mmu, sprite_index);
+        let (y_pos, x_pos, tile_index, attributes) = self.read_sprite_data(sprite_index);
         let sprite = Sprite::new(y_pos, x_pos, tile_index, attributes);
 
         let sprite_height = match self.get_sprite_size() {
@@ -305,7 +366,7 @@ impl Ppu {
     }
 
     // PPU 主時鐘滴答 - 每個 T-狀態調用一次
-    pub fn tick(&mut self, mmu: &crate::mmu::Mmu, interrupt_flags: &mut u8) {
+    pub fn tick(&mut self, interrupts: &mut InterruptController) {
         if self.lcd_state() == EnableState::Disabled {
             return;
         }
@@ -319,35 +380,35 @@ impl Ppu {
                 if self.dots.is_multiple_of(2) {
                     let sprite_index = (self.dots / 2 - 1) as usize;
                     if sprite_index < 40 {
-                        self.check_sprite_visibility(mmu, sprite_index);
+                        self.check_sprite_visibility(sprite_index);
                     }
                 }
 
                 if self.dots >= 80 {
                     // OAM 搜索結束後排序精靈
                     self.sort_sprites();
-                    self.change_mode(LcdMode::PixelTransfer, interrupt_flags);
+                    self.change_mode(LcdMode::PixelTransfer, interrupts);
                 }
             }
             LcdMode::PixelTransfer => {
                 if self.dots >= 252 {
-                    self.render_scanline(mmu);
-                    self.change_mode(LcdMode::HBlank, interrupt_flags);
+                    self.render_scanline();
+                    self.change_mode(LcdMode::HBlank, interrupts);
                 }
             }
             LcdMode::HBlank => {
                 if self.dots >= 456 {
                     self.dots = 0;
                     self.ly += 1;
-                    self.update_stat(interrupt_flags);
+                    self.update_stat(interrupts);
 
                     if self.ly >= 144 {
-                        self.change_mode(LcdMode::VBlank, interrupt_flags);
-                        *interrupt_flags |= 0x01; // VBlank 中斷
+                        self.change_mode(LcdMode::VBlank, interrupts);
+                        interrupts.request(InterruptType::VBlank);
                         self.present_buffer.copy_from_slice(&self.framebuffer);
                         self.frame_ready = true;
                     } else {
-                        self.change_mode(LcdMode::OamSearch, interrupt_flags);
+                        self.change_mode(LcdMode::OamSearch, interrupts);
                     }
                 }
             }
@@ -355,13 +416,13 @@ impl Ppu {
                 if self.dots >= 456 {
                     self.dots = 0;
                     self.ly += 1;
-                    self.update_stat(interrupt_flags);
+                    self.update_stat(interrupts);
 
                     if self.ly >= 154 {
                         self.ly = 0;
                         self.window_line_counter = 0;
                         self.window_triggered = false;
-                        self.change_mode(LcdMode::OamSearch, interrupt_flags);
+                        self.change_mode(LcdMode::OamSearch, interrupts);
                     }
                 }
             }
@@ -369,7 +430,7 @@ impl Ppu {
     }
 
     // 渲染當前掃描線 - 背景 + 視窗 + 精靈
-    fn render_scanline(&mut self, mmu: &crate::mmu::Mmu) {
+    fn render_scanline(&mut self) {
         if self.ly >= 144 {
             return;
         }
@@ -383,6 +444,7 @@ impl Ppu {
         let bgp = self.bgp;
         let obp0 = self.obp0;
         let obp1 = self.obp1;
+        let debug = self.debug;
 
         let bg_enabled = (lcdc & 0x01) != 0;
         let sprite_enabled = (lcdc & 0x02) != 0;
@@ -412,11 +474,13 @@ impl Ppu {
         for x in 0..160u8 {
             let mut bg_color_idx: u8 = 0;
             let mut final_color: u8 = 0;
-            let mut used_window = false;
+
+            // 視窗行計數器依實際的 LCDC 推進，不受除錯開關影響
+            let in_window = render_window && (x as i16 >= wx as i16 - 7);
 
             if bg_enabled {
-                // 檢查此像素是否在視窗內
-                if render_window && (x as i16 >= wx as i16 - 7) {
+                // 檢查此像素是否在視窗內 (隱藏視窗時改顯示背景)
+                if in_window && debug.window {
                     // 視窗像素
                     let win_x = (x as i16 - (wx as i16 - 7)) as u8;
                     let win_y = window_line;
@@ -424,7 +488,7 @@ impl Ppu {
                     let tile_x = win_x / 8;
                     let tile_y = win_y / 8;
                     let tile_addr = win_map_base + (tile_y as u16 * 32) + tile_x as u16;
-                    let tile_index = mmu.read_byte_ppu(tile_addr);
+                    let tile_index = self.vram_byte(tile_addr);
 
                     let tile_data_addr = if tile_data_8000 {
                         0x8000u16 + (tile_index as u16 * 16)
@@ -435,8 +499,8 @@ impl Ppu {
 
                     let tile_line = (win_y % 8) as u16;
                     let line_addr = tile_data_addr + (tile_line * 2);
-                    let low_byte = mmu.read_byte_ppu(line_addr);
-                    let high_byte = mmu.read_byte_ppu(line_addr + 1);
+                    let low_byte = self.vram_byte(line_addr);
+                    let high_byte = self.vram_byte(line_addr + 1);
 
                     let pixel_x = win_x % 8;
                     let bit_index = 7 - pixel_x;
@@ -446,8 +510,7 @@ impl Ppu {
 
                     let shift = bg_color_idx * 2;
                     final_color = (bgp >> shift) & 0x03;
-                    used_window = true;
-                } else {
+                } else if debug.background {
                     // 背景像素
                     let bg_x = x.wrapping_add(scx);
                     let bg_y = ly.wrapping_add(scy);
@@ -455,7 +518,7 @@ impl Ppu {
                     let tile_x = (bg_x / 8) as u16;
                     let tile_y = (bg_y / 8) as u16;
                     let tile_addr = bg_map_base + (tile_y * 32) + tile_x;
-                    let tile_index = mmu.read_byte_ppu(tile_addr);
+                    let tile_index = self.vram_byte(tile_addr);
 
                     let tile_data_addr = if tile_data_8000 {
                         0x8000u16 + (tile_index as u16 * 16)
@@ -466,8 +529,8 @@ impl Ppu {
 
                     let tile_line = (bg_y % 8) as u16;
                     let line_addr = tile_data_addr + (tile_line * 2);
-                    let low_byte = mmu.read_byte_ppu(line_addr);
-                    let high_byte = mmu.read_byte_ppu(line_addr + 1);
+                    let low_byte = self.vram_byte(line_addr);
+                    let high_byte = self.vram_byte(line_addr + 1);
 
                     let pixel_x = bg_x % 8;
                     let bit_index = 7 - pixel_x;
@@ -480,15 +543,20 @@ impl Ppu {
                 }
             }
 
-            if used_window {
+            if in_window && bg_enabled {
                 window_used_this_line = true;
             }
 
             // 精靈渲染
-            if sprite_enabled {
+            if sprite_enabled && debug.sprites {
                 let sprite_height: i16 = if sprite_size_16 { 16 } else { 8 };
 
-                for (_, sprite) in sprites.iter() {
+                for (index, sprite) in sprites.iter() {
+                    // 隱藏的精靈不顯示，但仍佔用此行的精靈名額並保留優先順序
+                    if !debug.sprite_visible(*index) {
+                        continue;
+                    }
+
                     let sprite_x = sprite.x_pos as i16 - 8;
 
                     // 檢查像素是否在精靈 X 範圍內
@@ -524,8 +592,8 @@ impl Ppu {
                     // 獲取圖塊資料（精靈總是使用 0x8000 定址）
                     let tile_line = (rel_y % 8) as u16;
                     let tile_addr = 0x8000u16 + (tile_index as u16 * 16) + (tile_line * 2);
-                    let low_byte = mmu.read_byte_ppu(tile_addr);
-                    let high_byte = mmu.read_byte_ppu(tile_addr + 1);
+                    let low_byte = self.vram_byte(tile_addr);
+                    let high_byte = self.vram_byte(tile_addr + 1);
 
                     // 提取像素顏色 - 處理水平翻轉
                     let bit_index = if (sprite.attributes & 0x20) != 0 {
@@ -573,3 +641,73 @@ impl Ppu {
         &self.framebuffer
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 圖塊 1 全為顏色 3，圖塊 2 全為顏色 1；背景圖全部使用圖塊 1
+    fn test_ppu() -> Ppu {
+        let mut ppu = Ppu::new();
+        ppu.lcdc = 0x93; // LCD、BG、OBJ 開啟，圖塊定址 0x8000
+        ppu.vram[16..32].fill(0xFF);
+        for row in 0..8 {
+            ppu.vram[32 + row * 2] = 0xFF;
+        }
+        ppu.vram[0x1800..0x1C00].fill(1);
+        ppu.obp0 = 0b11_10_01_00;
+        ppu
+    }
+
+    fn add_sprite(ppu: &mut Ppu, index: usize, x_pos: u8, tile_index: u8) {
+        let sprite = Sprite::new(16, x_pos, tile_index, 0);
+        ppu.oam[index * 4..index * 4 + 4].copy_from_slice(&[16, x_pos, tile_index, 0]);
+        ppu.oam_sprites.push((index, sprite));
+    }
+
+    #[test]
+    fn hidden_background_does_not_change_lcdc() {
+        let mut ppu = test_ppu();
+        ppu.debug.background = false;
+        ppu.render_scanline();
+
+        assert_eq!(ppu.framebuffer[0], 0);
+        assert_eq!(ppu.read_register(0xFF40), 0x93);
+
+        ppu.debug.background = true;
+        ppu.render_scanline();
+        assert_eq!(ppu.framebuffer[0], 3);
+    }
+
+    #[test]
+    fn hidden_sprite_reveals_next_sprite() {
+        let mut ppu = test_ppu();
+        ppu.debug.background = false;
+        add_sprite(&mut ppu, 0, 8, 1); // 顏色 3
+        add_sprite(&mut ppu, 1, 8, 2); // 顏色 1
+
+        ppu.render_scanline();
+        assert_eq!(ppu.framebuffer[0], 3);
+
+        ppu.debug.set_sprite_visible(0, false);
+        ppu.render_scanline();
+        assert_eq!(ppu.framebuffer[0], 1);
+
+        ppu.debug.sprites = false;
+        ppu.render_scanline();
+        assert_eq!(ppu.framebuffer[0], 0);
+    }
+
+    #[test]
+    fn hidden_window_still_advances_line_counter() {
+        let mut ppu = test_ppu();
+        ppu.lcdc |= 0x60; // 視窗開啟，使用 0x9C00 (全為圖塊 0)
+        ppu.wx = 7;
+        ppu.debug.window = false;
+        ppu.render_scanline();
+
+        // 視窗被隱藏：顯示底下的背景，但視窗行計數器照常推進
+        assert_eq!(ppu.framebuffer[0], 3);
+        assert_eq!(ppu.window_line_counter, 1);
+    }
+}
@@ -0,0 +1,344 @@
+//! 效能分析器
+//!
+//! 在每次 `Cpu::step` 前後取樣，將週期歸屬到 (bank, PC) 與呼叫堆疊。
+//! CALL/RST/中斷分派視為進入新框架，RET/RETI 視為返回。
+//! 可輸出 flame graph 工具使用的 folded stack 格式，以及每幀 HALT 與執行中的週期報告。
+
+use crate::cpu::{Cpu, CpuState};
+use crate::mmu::Mmu;
+use crate::symbols::SymbolTable;
+use std::collections::HashMap;
+
+/// 每幀的週期數 (154 行 × 456 週期)
+pub const FRAME_CYCLES: u64 = 70224;
+
+// 堆疊深度上限：遊戲手動調整 SP 而未返回時避免無限成長
+const MAX_DEPTH: usize = 256;
+
+/// 程式位置 (ROM 區域以外 bank 為 None)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct CodeLocation {
+    pub bank: Option<u16>,
+    pub address: u16,
+}
+
+impl CodeLocation {
+    fn name(&self, mmu: &Mmu, symbols: &SymbolTable) -> String {
+        let label = match self.bank {
+            Some(bank) => symbols.label_in_bank(bank, self.address),
+            None => symbols.label_at(mmu, self.address),
+        };
+        match (label, self.bank) {
+            (Some(label), _) => label.to_string(),
+            (None, Some(bank)) => format!("{:02X}:{:04X}", bank, self.address),
+            (None, None) => format!("{:04X}", self.address),
+        }
+    }
+}
+
+/// 每幀的週期統計
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct FrameStats {
+    pub active: u64,
+    pub halted: u64,
+    pub interrupts: u64, // 中斷分派本身的週期
+}
+
+impl FrameStats {
+    pub fn total(&self) -> u64 {
+        self.active + self.halted + self.interrupts
+    }
+}
+
+/// 執行一步之前的 CPU 狀態
+#[derive(Debug, Clone, Copy)]
+pub struct StepStart {
+    location: CodeLocation,
+    sp: u16,
+    opcode: u8,
+    halted: bool,
+    instructions: u64,
+    ly: u8,
+}
+
+impl StepStart {
+    pub fn capture(cpu: &Cpu, mmu: &Mmu) -> Self {
+        StepStart {
+            location: CodeLocation {
+                bank: mmu.rom_bank_at(cpu.pc),
+                address: cpu.pc,
+            },
+            sp: cpu.sp,
+            opcode: mmu.read_byte_ppu(cpu.pc),
+            halted: cpu.state == CpuState::Halted,
+            instructions: cpu.instr_count,
+            ly: mmu.read_byte_ppu(0xFF44),
+        }
+    }
+}
+
+// 呼叫樹節點；以節點編號代表一條完整的呼叫堆疊，避免每步複製堆疊
+#[derive(Debug, Clone)]
+struct Node {
+    parent: usize,
+    frame: Option<CodeLocation>, // 根節點為 None
+    children: HashMap<CodeLocation, usize>,
+    cycles: u64,
+    halted: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct Profiler {
+    nodes: Vec<Node>,
+    current: usize,
+    depth: usize,
+    overflow: usize, // 超過 MAX_DEPTH 而未記錄的框架數
+    by_location: HashMap<CodeLocation, u64>,
+    frames: Vec<FrameStats>,
+    frame: FrameStats,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Profiler {
+            nodes: vec![Node {
+                parent: 0,
+                frame: None,
+                children: HashMap::new(),
+                cycles: 0,
+                halted: 0,
+            }],
+            current: 0,
+            depth: 0,
+            overflow: 0,
+            by_location: HashMap::new(),
+            frames: Vec::new(),
+            frame: FrameStats::default(),
+        }
+    }
+
+    /// 記錄一步的結果
+    pub fn record(&mut self, start: StepStart, cpu: &Cpu, mmu: &Mmu, cycles: u32) {
+        let cycles = cycles as u64;
+        let executed = cpu.instr_count != start.instructions;
+
+        if start.halted {
+            self.frame.halted += cycles;
+            self.nodes[self.current].halted += cycles;
+        } else if !executed {
+            // 沒有執行指令也不是 HALT：中斷分派
+            self.frame.interrupts += cycles;
+            self.nodes[self.current].cycles += cycles;
+            self.push(CodeLocation {
+                bank: mmu.rom_bank_at(cpu.pc),
+                address: cpu.pc,
+            });
+        } else {
+            self.frame.active += cycles;
+            self.nodes[self.current].cycles += cycles;
+            *self.by_location.entry(start.location).or_default() += cycles;
+            self.track_calls(start, cpu, mmu);
+        }
+
+        // 進入 VBlank (LY 變為 144) 時結束一幀
+        let ly = mmu.read_byte_ppu(0xFF44);
+        if start.ly != 144 && ly == 144 {
+            self.frames.push(std::mem::take(&mut self.frame));
+        }
+    }
+
+    fn track_calls(&mut self, start: StepStart, cpu: &Cpu, mmu: &Mmu) {
+        let pushed = cpu.sp == start.sp.wrapping_sub(2);
+        let popped = cpu.sp == start.sp.wrapping_add(2);
+        match start.opcode {
+            // CALL / CALL cc (條件成立時 SP 減 2)
+            0xCD | 0xC4 | 0xCC | 0xD4 | 0xDC if pushed => self.push(CodeLocation {
+                bank: mmu.rom_bank_at(cpu.pc),
+                address: cpu.pc,
+            }),
+            // RST
+            op if op & 0xC7 == 0xC7 && pushed => self.push(CodeLocation {
+                bank: mmu.rom_bank_at(cpu.pc),
+                address: cpu.pc,
+            }),
+            // RET / RETI / RET cc
+            0xC9 | 0xD9 | 0xC0 | 0xC8 | 0xD0 | 0xD8 if popped => self.pop(),
+            _ => {}
+        }
+    }
+
+    fn push(&mut self, frame: CodeLocation) {
+        if self.depth >= MAX_DEPTH {
+            self.overflow += 1;
+            return;
+        }
+        let next_id = self.nodes.len();
+        let parent = self.current;
+        let id = *self.nodes[parent].children.entry(frame).or_insert(next_id);
+        if id == next_id {
+            self.nodes.push(Node {
+                parent,
+                frame: Some(frame),
+                children: HashMap::new(),
+                cycles: 0,
+                halted: 0,
+            });
+        }
+        self.current = id;
+        self.depth += 1;
+    }
+
+    fn pop(&mut self) {
+        if self.overflow > 0 {
+            self.overflow -= 1;
+        } else if self.depth > 0 {
+            self.current = self.nodes[self.current].parent;
+            self.depth -= 1;
+        }
+    }
+
+    #[cfg(test)]
+    pub fn frames(&self) -> &[FrameStats] {
+        &self.frames
+    }
+
+    /// flame graph 用的 folded stack 格式：`root;Main;Update 1234`
+    pub fn folded_stacks(&self, mmu: &Mmu, symbols: &SymbolTable) -> String {
+        let mut lines = Vec::new();
+        for (id, node) in self.nodes.iter().enumerate() {
+            if node.cycles == 0 && node.halted == 0 {
+                continue;
+            }
+            let mut names = Vec::new();
+            let mut cursor = id;
+            while let Some(frame) = self.nodes[cursor].frame {
+                names.push(frame.name(mmu, symbols));
+                cursor = self.nodes[cursor].parent;
+            }
+            names.push("root".to_string());
+            names.reverse();
+            let stack = names.join(";");
+
+            if node.cycles > 0 {
+                lines.push(format!("{} {}", stack, node.cycles));
+            }
+            if node.halted > 0 {
+                lines.push(format!("{};[HALT] {}", stack, node.halted));
+            }
+        }
+        lines.sort();
+        lines.join("\n") + "\n"
+    }
+
+    /// 每幀週期預算報告與最耗時的位置
+    pub fn report(&self, mmu: &Mmu, symbols: &SymbolTable) -> String {
+        let mut out = String::from("frame    active    halted  interrupt     total  active%\n");
+        for (index, frame) in self.frames.iter().enumerate() {
+            out.push_str(&format!(
+                "{:5} {:9} {:9} {:10} {:9} {:7.1}%\n",
+                index,
+                frame.active,
+                frame.halted,
+                frame.interrupts,
+                frame.total(),
+                frame.active as f64 * 100.0 / FRAME_CYCLES as f64
+            ));
+        }
+
+        if !self.frames.is_empty() {
+            let count = self.frames.len() as u64;
+            let active: u64 = self.frames.iter().map(|f| f.active).sum();
+            let halted: u64 = self.frames.iter().map(|f| f.halted).sum();
+            out.push_str(&format!(
+                "\n平均每幀: 執行 {} 週期 ({:.1}%)，HALT {} 週期，共 {} 幀\n",
+                active / count,
+                active as f64 * 100.0 / (count * FRAME_CYCLES) as f64,
+                halted / count,
+                count
+            ));
+        }
+
+        let mut hotspots: Vec<_> = self.by_location.iter().collect();
+        hotspots.sort_by(|a, b| b.1.cmp(a.1).then(a.0.cmp(b.0)));
+        out.push_str("\n最耗時的位置:\n");
+        for (location, cycles) in hotspots.into_iter().take(20) {
+            out.push_str(&format!(
+                "{:>24} {:12}\n",
+                location.name(mmu, symbols),
+                cycles
+            ));
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::gameboy::GameBoy;
+    use crate::symbols::SymbolTable;
+
+    fn profiled_gameboy(program: &[u8]) -> GameBoy {
+        let mut gb = GameBoy::with_program(program);
+        gb.enable_profiler();
+        gb
+    }
+
+    #[test]
+    fn attributes_cycles_to_call_stack() {
+        // C000: CALL C010; JR -2 ... C010: NOP; RET
+        let mut program = vec![0xCD, 0x10, 0xC0, 0x18, 0xFE];
+        program.resize(0x10, 0x00);
+        program.extend_from_slice(&[0x00, 0xC9]);
+        let mut gb = profiled_gameboy(&program);
+        for _ in 0..4 {
+            gb.step_cpu_with_timing();
+        }
+
+        let profiler = gb.profiler.as_ref().unwrap();
+        let folded = profiler.folded_stacks(&gb.mmu, &SymbolTable::default());
+        // CALL (24) 與 JR (12) 屬於 root，NOP (4) 與 RET (16) 屬於被呼叫的函式
+        assert_eq!(folded, "root 36\nroot;C010 20\n");
+    }
+
+    #[test]
+    fn folded_stacks_use_symbol_labels() {
+        let mut program = vec![0xCD, 0x10, 0xC0, 0x18, 0xFE];
+        program.resize(0x10, 0x00);
+        program.extend_from_slice(&[0x00, 0xC9]);
+        let mut gb = profiled_gameboy(&program);
+        for _ in 0..3 {
+            gb.step_cpu_with_timing();
+        }
+
+        let symbols = SymbolTable::parse("00:c010 UpdateSprites\n").unwrap();
+        let folded = gb
+            .profiler
+            .as_ref()
+            .unwrap()
+            .folded_stacks(&gb.mmu, &symbols);
+        assert!(folded.contains("root;UpdateSprites 20"));
+    }
+
+    #[test]
+    fn reports_halt_time_per_frame() {
+        // HALT 且沒有啟用任何中斷：整幀都在 HALT
+        let mut gb = profiled_gameboy(&[0x76]);
+        gb.run_frame();
+        gb.run_frame();
+
+        let profiler = gb.profiler.as_ref().unwrap();
+        let frame = profiler.frames()[1];
+        assert_eq!(frame.active, 0);
+        assert_eq!(frame.total(), super::FRAME_CYCLES);
+        assert!(
+            profiler
+                .folded_stacks(&gb.mmu, &SymbolTable::default())
+                .contains("root;[HALT]")
+        );
+        assert!(
+            profiler
+                .report(&gb.mmu, &SymbolTable::default())
+                .contains("C000")
+        );
+    }
+}
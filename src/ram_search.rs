@@ -0,0 +1,204 @@
+//! RAM 搜尋
+//!
+//! 對 WRAM、HRAM 與卡帶 RAM (目前映射的 bank) 建立快照，之後每次過濾都與上一次的值比較，
+//! 逐步縮小候選位址。找到的位址可轉成每次 VBlank 寫回原值的 GameShark 凍結金手指。
+//! 只在幀與幀之間讀取 `Mmu` 狀態，不影響模擬。
+
+use crate::cheats::Cheat;
+use crate::mmu::Mmu;
+
+// 搜尋的位址範圍
+const REGIONS: [(u16, u16); 3] = [(0xA000, 0xBFFF), (0xC000, 0xDFFF), (0xFF80, 0xFFFE)];
+
+/// 比較的資料寬度 (16 位元為 little-endian)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Width {
+    Byte,
+    Word,
+}
+
+/// 過濾條件：除了 `Value` 以外都與上一次快照比較
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    Equal,
+    Changed,
+    Increased,
+    Decreased,
+    Value(u16),
+}
+
+impl Filter {
+    fn matches(self, previous: u16, current: u16) -> bool {
+        match self {
+            Filter::Equal => current == previous,
+            Filter::Changed => current != previous,
+            Filter::Increased => current > previous,
+            Filter::Decreased => current < previous,
+            Filter::Value(value) => current == value,
+        }
+    }
+}
+
+/// 搜尋候選：位址與上一次讀到的值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Candidate {
+    pub address: u16,
+    pub value: u16,
+}
+
+#[derive(Debug, Clone)]
+pub struct RamSearch {
+    width: Width,
+    candidates: Vec<Candidate>,
+}
+
+impl RamSearch {
+    /// 對所有可搜尋的位址建立快照
+    pub fn new(mmu: &Mmu, width: Width) -> Self {
+        let span = match width {
+            Width::Byte => 0,
+            Width::Word => 1,
+        };
+        let candidates = REGIONS
+            .iter()
+            .filter(|&&(start, _)| start != 0xA000 || !mmu.ext_ram.is_empty())
+            .flat_map(|&(start, end)| start..=end - span)
+            .map(|address| Candidate {
+                address,
+                value: read(mmu, address, width),
+            })
+            .collect();
+        RamSearch { width, candidates }
+    }
+
+    pub fn width(&self) -> Width {
+        self.width
+    }
+
+    pub fn candidates(&self) -> &[Candidate] {
+        &self.candidates
+    }
+
+    /// 保留符合條件的位址，並以目前的值作為下一次比較的基準
+    pub fn filter(&mut self, mmu: &Mmu, filter: Filter) {
+        let width = self.width;
+        self.candidates.retain_mut(|candidate| {
+            let current = read(mmu, candidate.address, width);
+            let keep = filter.matches(candidate.value, current);
+            candidate.value = current;
+            keep
+        });
+    }
+
+    /// 將候選位址目前的值轉成凍結用的 GameShark 金手指 (16 位元為兩個代碼)
+    pub fn freeze(&self, mmu: &Mmu, index: usize, name: &str) -> Option<Vec<Cheat>> {
+        let address = self.candidates.get(index)?.address;
+        let value = read(mmu, address, self.width);
+        let bytes: &[u8] = match self.width {
+            Width::Byte => &[value as u8],
+            Width::Word => &value.to_le_bytes(),
+        };
+        let cheats = bytes
+            .iter()
+            .zip(0u16..)
+            .map(|(&byte, offset)| {
+                let [low, high] = address.wrapping_add(offset).to_le_bytes();
+                let code = format!("01{:02X}{:02X}{:02X}", byte, low, high);
+                Cheat::new(&code, name).expect("產生的 GameShark 代碼必定有效")
+            })
+            .collect();
+        Some(cheats)
+    }
+}
+
+fn read(mmu: &Mmu, address: u16, width: Width) -> u16 {
+    match width {
+        Width::Byte => peek(mmu, address) as u16,
+        Width::Word => u16::from_le_bytes([peek(mmu, address), peek(mmu, address.wrapping_add(1))]),
+    }
+}
+
+// 卡帶 RAM 直接讀取目前的 bank，不受 RAM 啟用狀態影響
+fn peek(mmu: &Mmu, address: u16) -> u8 {
+    match address {
+        0xA000..=0xBFFF => {
+            let offset = mmu.ram_bank as usize * 0x2000 + (address - 0xA000) as usize;
+            mmu.ext_ram[offset % mmu.ext_ram.len()]
+        }
+        _ => mmu.read_byte_ppu(address),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cheats::CheatCode;
+
+    #[test]
+    fn narrows_candidates_across_snapshots() {
+        let mut mmu = Mmu::new();
+        mmu.write_byte(0xC100, 3);
+        mmu.write_byte(0xFF90, 3);
+        let mut search = RamSearch::new(&mmu, Width::Byte);
+        assert_eq!(search.candidates().len(), 0x2000 + 0x7F);
+
+        search.filter(&mmu, Filter::Value(3));
+        assert_eq!(search.candidates().len(), 2);
+
+        mmu.write_byte(0xC100, 2);
+        search.filter(&mmu, Filter::Decreased);
+        assert_eq!(
+            search.candidates(),
+            &[Candidate {
+                address: 0xC100,
+                value: 2
+            }]
+        );
+
+        search.filter(&mmu, Filter::Equal);
+        assert_eq!(search.candidates().len(), 1);
+        search.filter(&mmu, Filter::Changed);
+        assert!(search.candidates().is_empty());
+    }
+
+    #[test]
+    fn searches_cartridge_ram_when_present() {
+        let mut mmu = Mmu::new();
+        mmu.ext_ram = vec![0; 0x4000];
+        mmu.ram_bank = 1;
+        mmu.ext_ram[0x2010] = 0x42;
+
+        let mut search = RamSearch::new(&mmu, Width::Byte);
+        search.filter(&mmu, Filter::Value(0x42));
+        assert_eq!(search.candidates()[0].address, 0xA010);
+    }
+
+    #[test]
+    fn word_search_and_freeze() {
+        let mut mmu = Mmu::new();
+        mmu.write_byte(0xC200, 0xE8);
+        mmu.write_byte(0xC201, 0x03);
+        let mut search = RamSearch::new(&mmu, Width::Word);
+        search.filter(&mmu, Filter::Value(1000));
+        assert_eq!(search.candidates().len(), 1);
+
+        let cheats = search.freeze(&mmu, 0, "score").unwrap();
+        let codes: Vec<_> = cheats.iter().map(|cheat| cheat.code).collect();
+        assert_eq!(
+            codes,
+            [
+                CheatCode::GameShark {
+                    kind: 0x01,
+                    address: 0xC200,
+                    value: 0xE8
+                },
+                CheatCode::GameShark {
+                    kind: 0x01,
+                    address: 0xC201,
+                    value: 0x03
+                }
+            ]
+        );
+        assert!(search.freeze(&mmu, 1, "").is_none());
+    }
+}
@@ -1,14 +1,372 @@
+use crate::patch;
 use std::fs;
-use std::io::Read;
-use std::path::Path;
+use std::io::{Read, Seek};
+use std::path::{Path, PathBuf};
 
-pub fn read_rom_file(path: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
-    if Path::new(path).exists() {
-        let mut file = fs::File::open(path)?;
-        let mut rom_data = Vec::new();
-        file.read_to_end(&mut rom_data)?;
-        return Ok(rom_data);
+/// ROM 大小上限 (MBC5 最大 8 MiB)：解壓縮時超過即停止，避免壓縮炸彈耗盡記憶體
+pub const MAX_ROM_SIZE: u64 = 8 * 1024 * 1024;
+
+/// 載入 ROM 的選項
+#[derive(Debug, Clone, Default)]
+pub struct RomOptions {
+    pub entry: Option<String>, // 壓縮檔內指定的檔名 (未指定時取第一個 .gb/.gbc)
+    pub patch_path: Option<PathBuf>, // 修補檔 (未指定時使用同名的 .bps/.ups/.ips)
+    pub save_dir: Option<PathBuf>,   // 外部 RAM 存檔目錄 (未指定時與 ROM 同目錄)
+}
+
+#[derive(Debug)]
+pub enum RomError {
+    Io(std::io::Error),
+    NotFound(String),
+    NoRomInArchive(String),
+    EntryNotFound {
+        archive: String,
+        entry: String,
+    },
+    TooLarge(u64),
+    Archive(String),
+    Patch {
+        path: PathBuf,
+        source: patch::PatchError,
+    },
+}
+
+impl std::fmt::Display for RomError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RomError::Io(err) => write!(f, "無法讀取 ROM: {}", err),
+            RomError::NotFound(path) => write!(f, "ROM file not found: {}", path),
+            RomError::NoRomInArchive(path) => write!(f, "壓縮檔中沒有 .gb/.gbc 檔案: {}", path),
+            RomError::EntryNotFound { archive, entry } => {
+                write!(f, "壓縮檔 {} 中找不到 {}", archive, entry)
+            }
+            RomError::TooLarge(limit) => write!(f, "ROM 超過 {} bytes 上限", limit),
+            RomError::Archive(msg) => write!(f, "無法解壓縮: {}", msg),
+            RomError::Patch { path, source } => write!(f, "{}: {}", path.display(), source),
+        }
+    }
+}
+
+impl std::error::Error for RomError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            RomError::Io(err) => Some(err),
+            RomError::Patch { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for RomError {
+    fn from(err: std::io::Error) -> Self {
+        RomError::Io(err)
+    }
+}
+
+// 依檔頭判斷的容器格式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Container {
+    Raw,
+    Zip,
+    Gzip,
+    SevenZip,
+}
+
+impl Container {
+    fn detect(magic: &[u8]) -> Self {
+        if magic.starts_with(b"PK\x03\x04") || magic.starts_with(b"PK\x05\x06") {
+            Container::Zip
+        } else if magic.starts_with(&[0x1F, 0x8B]) {
+            Container::Gzip
+        } else if magic.starts_with(&[0x37, 0x7A, 0xBC, 0xAF, 0x27, 0x1C]) {
+            Container::SevenZip
+        } else {
+            Container::Raw
+        }
+    }
+}
+
+/// 讀取 ROM 檔案 (可為 zip/gz/7z 壓縮檔)，並套用修補檔
+///
+/// 壓縮檔以串流方式解壓縮，只保留選中的項目；修補只作用在回傳的資料上，原始檔案不會被修改。
+pub fn read_rom_file(path: &str, options: &RomOptions) -> Result<Vec<u8>, RomError> {
+    if !Path::new(path).exists() {
+        return Err(RomError::NotFound(path.to_string()));
+    }
+
+    let mut file = fs::File::open(path)?;
+    let mut magic = [0u8; 6];
+    let magic_len = read_prefix(&mut file, &mut magic)?;
+    file.rewind()?;
+
+    let entry = options.entry.as_deref();
+    let mut rom_data = match Container::detect(&magic[..magic_len]) {
+        Container::Raw => read_limited(file)?,
+        Container::Zip => read_zip(file, path, entry)?,
+        Container::Gzip => read_gzip(file, path)?,
+        Container::SevenZip => read_7z(file, path, entry)?,
+    };
+
+    let patch_path = match options.patch_path.as_ref() {
+        Some(patch_path) => Some(patch_path.clone()),
+        None => patch::find_sibling_patch(Path::new(path)),
+    };
+    if let Some(patch_path) = patch_path {
+        let result = fs::read(&patch_path)
+            .map_err(patch::PatchError::Io)
+            .and_then(|patch_data| patch::apply(&rom_data, &patch_data));
+        rom_data = result.map_err(|source| RomError::Patch {
+            path: patch_path.clone(),
+            source,
+        })?;
+        println!("已套用修補檔: {}", patch_path.display());
+    }
+
+    Ok(rom_data)
+}
+
+fn read_prefix(reader: &mut impl Read, buffer: &mut [u8]) -> std::io::Result<usize> {
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..])? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+    Ok(filled)
+}
+
+// 最多讀取 MAX_ROM_SIZE，超過時回傳錯誤而不是繼續配置記憶體
+fn read_limited(reader: impl Read) -> Result<Vec<u8>, RomError> {
+    let mut data = Vec::new();
+    reader.take(MAX_ROM_SIZE + 1).read_to_end(&mut data)?;
+    if data.len() as u64 > MAX_ROM_SIZE {
+        return Err(RomError::TooLarge(MAX_ROM_SIZE));
+    }
+    Ok(data)
+}
+
+fn is_rom_name(name: &str) -> bool {
+    let lower = name.to_ascii_lowercase();
+    lower.ends_with(".gb") || lower.ends_with(".gbc")
+}
+
+// 指定的項目可以是完整路徑或只有檔名
+fn entry_matches(name: &str, wanted: Option<&str>) -> bool {
+    match wanted {
+        Some(wanted) => name == wanted || name.rsplit(['/', '\\']).next() == Some(wanted),
+        None => is_rom_name(name),
+    }
+}
+
+fn missing_entry(path: &str, entry: Option<&str>) -> RomError {
+    match entry {
+        Some(entry) => RomError::EntryNotFound {
+            archive: path.to_string(),
+            entry: entry.to_string(),
+        },
+        None => RomError::NoRomInArchive(path.to_string()),
+    }
+}
+
+fn read_zip(file: fs::File, path: &str, entry: Option<&str>) -> Result<Vec<u8>, RomError> {
+    let archive_error = |e: zip::result::ZipError| RomError::Archive(e.to_string());
+    let mut archive = zip::ZipArchive::new(file).map_err(archive_error)?;
+    for index in 0..archive.len() {
+        let item = archive.by_index(index).map_err(archive_error)?;
+        if !item.is_dir() && entry_matches(item.name(), entry) {
+            if item.size() > MAX_ROM_SIZE {
+                return Err(RomError::TooLarge(MAX_ROM_SIZE));
+            }
+            return read_limited(item);
+        }
+    }
+    Err(missing_entry(path, entry))
+}
+
+// gzip 只有單一檔案：檔頭帶有原始檔名且不是 .gb/.gbc 時視為沒有 ROM
+fn read_gzip(file: fs::File, path: &str) -> Result<Vec<u8>, RomError> {
+    let mut decoder = flate2::read::GzDecoder::new(file);
+    let data = read_limited(&mut decoder)?;
+    let original_name = decoder
+        .header()
+        .and_then(|header| header.filename())
+        .map(|name| String::from_utf8_lossy(name).into_owned());
+    match original_name {
+        Some(name) if !is_rom_name(&name) => Err(RomError::NoRomInArchive(path.to_string())),
+        _ => Ok(data),
+    }
+}
+
+fn read_7z(mut file: fs::File, path: &str, entry: Option<&str>) -> Result<Vec<u8>, RomError> {
+    let archive_error = |e: sevenz_rust::Error| RomError::Archive(e.to_string());
+    let length = file.seek(std::io::SeekFrom::End(0))?;
+    file.rewind()?;
+    let mut archive = sevenz_rust::SevenZReader::new(file, length, sevenz_rust::Password::empty())
+        .map_err(archive_error)?;
+
+    let mut found: Option<Result<Vec<u8>, RomError>> = None;
+    archive
+        .for_each_entries(|item, reader| {
+            if item.is_directory() || !entry_matches(item.name(), entry) {
+                // solid 壓縮檔需依序解碼：略過的項目直接丟棄，不保留在記憶體中
+                std::io::copy(reader, &mut std::io::sink())?;
+                return Ok(true);
+            }
+            found = Some(if item.size() > MAX_ROM_SIZE {
+                Err(RomError::TooLarge(MAX_ROM_SIZE))
+            } else {
+                read_limited(reader)
+            });
+            Ok(false)
+        })
+        .map_err(archive_error)?;
+    found.unwrap_or_else(|| Err(missing_entry(path, entry)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    // 每個測試使用獨立的暫存目錄
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("rust_gb_{}_{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn write_zip(path: &Path, entries: &[(&str, &[u8])]) {
+        let mut writer = zip::ZipWriter::new(fs::File::create(path).unwrap());
+        for (name, data) in entries {
+            writer
+                .start_file(*name, zip::write::SimpleFileOptions::default())
+                .unwrap();
+            writer.write_all(data).unwrap();
+        }
+        writer.finish().unwrap();
     }
 
-    Err(format!("ROM file not found: {}", path).into())
+    #[test]
+    fn applies_sibling_patch_without_touching_rom_file() {
+        let dir = temp_dir("patch");
+        let rom_path = dir.join("hack.gb");
+        fs::write(&rom_path, [0u8; 4]).unwrap();
+        let mut ips = b"PATCH\x00\x00\x01\x00\x01\x7F".to_vec();
+        ips.extend_from_slice(b"EOF");
+        fs::write(dir.join("hack.ips"), &ips).unwrap();
+
+        let rom_str = rom_path.to_str().unwrap();
+        let defaults = RomOptions::default();
+        assert_eq!(read_rom_file(rom_str, &defaults).unwrap(), [0, 0x7F, 0, 0]);
+        assert_eq!(fs::read(&rom_path).unwrap(), [0u8; 4]);
+
+        // 明確指定的修補檔優先於同名檔案
+        let explicit = dir.join("other.ips");
+        fs::write(&explicit, b"PATCH\x00\x00\x00\x00\x01\x11EOF").unwrap();
+        let options = RomOptions {
+            patch_path: Some(explicit.clone()),
+            ..Default::default()
+        };
+        assert_eq!(read_rom_file(rom_str, &options).unwrap(), [0x11, 0, 0, 0]);
+
+        fs::write(&explicit, b"NOTAPATCH").unwrap();
+        assert!(matches!(
+            read_rom_file(rom_str, &options),
+            Err(RomError::Patch { .. })
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn extracts_first_or_named_rom_from_zip() {
+        let dir = temp_dir("zip");
+        let zip_path = dir.join("games.zip");
+        write_zip(
+            &zip_path,
+            &[
+                ("readme.txt", b"hello"),
+                ("roms/tetris.gb", &[1, 2, 3]),
+                ("roms/zelda.gbc", &[4, 5]),
+            ],
+        );
+        let zip_str = zip_path.to_str().unwrap();
+
+        assert_eq!(
+            read_rom_file(zip_str, &RomOptions::default()).unwrap(),
+            [1, 2, 3]
+        );
+        let named = RomOptions {
+            entry: Some("zelda.gbc".into()),
+            ..Default::default()
+        };
+        assert_eq!(read_rom_file(zip_str, &named).unwrap(), [4, 5]);
+        let missing = RomOptions {
+            entry: Some("metroid.gb".into()),
+            ..Default::default()
+        };
+        assert!(matches!(
+            read_rom_file(zip_str, &missing),
+            Err(RomError::EntryNotFound { .. })
+        ));
+
+        let empty_path = dir.join("docs.zip");
+        write_zip(&empty_path, &[("readme.txt", b"hello")]);
+        assert!(matches!(
+            read_rom_file(empty_path.to_str().unwrap(), &RomOptions::default()),
+            Err(RomError::NoRomInArchive(_))
+        ));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn decompresses_gzip_and_7z() {
+        let dir = temp_dir("gz7z");
+        let gz_path = dir.join("tetris.gb.gz");
+        let mut encoder = flate2::GzBuilder::new().filename("tetris.gb").write(
+            fs::File::create(&gz_path).unwrap(),
+            flate2::Compression::default(),
+        );
+        encoder.write_all(&[9; 100]).unwrap();
+        encoder.finish().unwrap();
+        assert_eq!(
+            read_rom_file(gz_path.to_str().unwrap(), &RomOptions::default()).unwrap(),
+            [9; 100]
+        );
+
+        let source = dir.join("src");
+        fs::create_dir_all(&source).unwrap();
+        fs::write(source.join("notes.txt"), b"notes").unwrap();
+        fs::write(source.join("game.gb"), [7u8; 64]).unwrap();
+        let seven_path = dir.join("game.7z");
+        sevenz_rust::compress_to_path(&source, &seven_path).unwrap();
+        assert_eq!(
+            read_rom_file(seven_path.to_str().unwrap(), &RomOptions::default()).unwrap(),
+            [7u8; 64]
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rejects_oversized_rom() {
+        let dir = temp_dir("large");
+        let gz_path = dir.join("huge.gb.gz");
+        let mut encoder = flate2::write::GzEncoder::new(
+            fs::File::create(&gz_path).unwrap(),
+            flate2::Compression::fast(),
+        );
+        encoder
+            .write_all(&vec![0u8; MAX_ROM_SIZE as usize + 1])
+            .unwrap();
+        encoder.finish().unwrap();
+
+        assert!(matches!(
+            read_rom_file(gz_path.to_str().unwrap(), &RomOptions::default()),
+            Err(RomError::TooLarge(_))
+        ));
+        fs::remove_dir_all(&dir).unwrap();
+    }
 }
@@ -0,0 +1,191 @@
+//! 即時存檔 (save state)
+//!
+//! 檔案格式：`GBST` 標記、版本 (u16 LE)、ROM 的 CRC32 (u32 LE)，接著是以 bincode 編碼的 CPU 與 MMU 狀態。
+//! ROM、卡帶標頭、金手指、監看點與圖層開關不寫入存檔，載入時沿用目前的內容，
+//! 因此只能載入同一個 ROM (以 CRC32 比對) 建立的存檔。
+
+use crate::cpu::Cpu;
+use crate::gameboy::GameBoy;
+use crate::mmu::Mmu;
+use crate::patch::crc32;
+use bincode::Options;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+const MAGIC: &[u8; 4] = b"GBST";
+const VERSION: u16 = 2;
+const HEADER_LEN: usize = 10;
+// 解碼大小上限，避免損毀的長度欄位造成大量配置 (卡帶 RAM 最多 128 KiB)
+const MAX_STATE_SIZE: u64 = 4 * 1024 * 1024;
+
+#[derive(Debug)]
+pub enum StateError {
+    Io(std::io::Error),
+    InvalidFormat,
+    Version(u16),
+    RomMismatch { expected: u32, found: u32 },
+    Decode(bincode::Error),
+}
+
+impl std::fmt::Display for StateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StateError::Io(err) => write!(f, "無法讀寫即時存檔: {}", err),
+            StateError::InvalidFormat => write!(f, "不是即時存檔"),
+            StateError::Version(version) => write!(f, "不支援的即時存檔版本 {}", version),
+            StateError::RomMismatch { expected, found } => write!(
+                f,
+                "即時存檔屬於其他 ROM (CRC32 {:08X}，目前 ROM 為 {:08X})",
+                found, expected
+            ),
+            StateError::Decode(err) => write!(f, "即時存檔內容損毀: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for StateError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            StateError::Io(err) => Some(err),
+            StateError::Decode(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for StateError {
+    fn from(err: std::io::Error) -> Self {
+        StateError::Io(err)
+    }
+}
+
+impl From<bincode::Error> for StateError {
+    fn from(err: bincode::Error) -> Self {
+        StateError::Decode(err)
+    }
+}
+
+#[derive(Serialize)]
+struct StateRef<'a> {
+    cpu: &'a Cpu,
+    mmu: &'a Mmu,
+    cycles: u64,
+}
+
+#[derive(Deserialize)]
+struct State {
+    cpu: Cpu,
+    mmu: Mmu,
+    cycles: u64,
+}
+
+/// 將目前的模擬狀態編碼成即時存檔
+pub fn save(gb: &GameBoy) -> Vec<u8> {
+    let mut data = Vec::with_capacity(HEADER_LEN);
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(&VERSION.to_le_bytes());
+    data.extend_from_slice(&crc32(&gb.mmu.rom).to_le_bytes());
+    let state = StateRef {
+        cpu: &gb.cpu,
+        mmu: &gb.mmu,
+        cycles: gb.cycles,
+    };
+    bincode::serialize_into(&mut data, &state).expect("寫入記憶體不會失敗");
+    data
+}
+
+/// 載入即時存檔；失敗時模擬狀態保持不變
+pub fn load(gb: &mut GameBoy, data: &[u8]) -> Result<(), StateError> {
+    if data.len() < HEADER_LEN || &data[..4] != MAGIC {
+        return Err(StateError::InvalidFormat);
+    }
+    let version = u16::from_le_bytes([data[4], data[5]]);
+    if version != VERSION {
+        return Err(StateError::Version(version));
+    }
+    let found = u32::from_le_bytes([data[6], data[7], data[8], data[9]]);
+    let expected = crc32(&gb.mmu.rom);
+    if found != expected {
+        return Err(StateError::RomMismatch { expected, found });
+    }
+
+    let State {
+        cpu,
+        mut mmu,
+        cycles,
+    } = bincode::DefaultOptions::new()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(MAX_STATE_SIZE)
+        .deserialize(&data[HEADER_LEN..])?;
+
+    // 沿用不屬於硬體狀態的部分
+    let current = &mut gb.mmu;
+    mmu.rom = std::mem::take(&mut current.rom);
+    mmu.header = current.header.take();
+    mmu.save_path = current.save_path.take();
+    mmu.watchpoints = std::mem::take(&mut current.watchpoints);
+    mmu.cheats = std::mem::take(&mut current.cheats);
+    mmu.ppu.debug = current.ppu.debug;
+
+    gb.cpu = cpu;
+    gb.mmu = mmu;
+    gb.cycles = cycles;
+    Ok(())
+}
+
+pub fn save_file(gb: &GameBoy, path: &Path) -> Result<(), StateError> {
+    std::fs::write(path, save(gb))?;
+    Ok(())
+}
+
+pub fn load_file(gb: &mut GameBoy, path: &Path) -> Result<(), StateError> {
+    load(gb, &std::fs::read(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cheats::Cheat;
+
+    #[test]
+    fn restores_state_and_keeps_rom_and_cheats() {
+        let mut gb = GameBoy::new();
+        gb.mmu.rom[0x0100] = 0x3C; // INC A
+        gb.mmu.write_byte(0xC000, 0x11);
+        let state = save(&gb);
+
+        gb.step_cpu_with_timing();
+        gb.mmu.write_byte(0xC000, 0x22);
+        gb.mmu.cheats.add(Cheat::new("016338CD", "lives").unwrap());
+
+        load(&mut gb, &state).unwrap();
+        assert_eq!(gb.cpu.pc, 0x0100);
+        assert_eq!(gb.cpu.a(), 0x01);
+        assert_eq!(gb.mmu.read_byte(0xC000), 0x11);
+        assert_eq!(gb.mmu.rom[0x0100], 0x3C);
+        assert_eq!(gb.mmu.cheats.iter().count(), 1);
+
+        // 載入後的執行結果與存檔前一致
+        gb.step_cpu_with_timing();
+        assert_eq!(gb.cpu.a(), 0x02);
+    }
+
+    #[test]
+    fn rejects_states_from_other_roms() {
+        let mut gb = GameBoy::new();
+        let state = save(&gb);
+        gb.mmu.rom[0x0134] = b'X';
+        gb.mmu.write_byte(0xC000, 0x22);
+
+        assert!(matches!(
+            load(&mut gb, &state),
+            Err(StateError::RomMismatch { .. })
+        ));
+        assert!(matches!(
+            load(&mut gb, b"GBSS\x01\x00"),
+            Err(StateError::InvalidFormat)
+        ));
+        assert_eq!(gb.mmu.read_byte(0xC000), 0x22);
+    }
+}
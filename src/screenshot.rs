@@ -0,0 +1,78 @@
+//! 螢幕截圖：將 RGBA 畫面編碼成 PNG (未壓縮濾波，IDAT 以 zlib 壓縮)
+
+use crate::patch::crc32;
+use flate2::Compression;
+use flate2::write::ZlibEncoder;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const PNG_SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A];
+
+pub fn encode_png(width: u32, height: u32, rgba: &[u8]) -> Vec<u8> {
+    let mut ihdr = Vec::with_capacity(13);
+    ihdr.extend_from_slice(&width.to_be_bytes());
+    ihdr.extend_from_slice(&height.to_be_bytes());
+    ihdr.extend_from_slice(&[8, 6, 0, 0, 0]); // 8 位元 RGBA，無交錯
+
+    // 每列前加上濾波類型 0 (None)
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    for row in rgba.chunks(width as usize * 4) {
+        let _ = encoder.write_all(&[0]);
+        let _ = encoder.write_all(row);
+    }
+    let idat = encoder.finish().expect("寫入記憶體不會失敗");
+
+    let mut png = PNG_SIGNATURE.to_vec();
+    for (kind, data) in [(b"IHDR", &ihdr[..]), (b"IDAT", &idat[..]), (b"IEND", &[][..])] {
+        png.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let start = png.len();
+        png.extend_from_slice(kind);
+        png.extend_from_slice(data);
+        let crc = crc32(&png[start..]);
+        png.extend_from_slice(&crc.to_be_bytes());
+    }
+    png
+}
+
+/// 以 `<名稱>-<unix 毫秒>.png` 寫入指定目錄，回傳檔案路徑
+pub fn save(
+    dir: &Path,
+    name: &str,
+    width: u32,
+    height: u32,
+    rgba: &[u8],
+) -> std::io::Result<PathBuf> {
+    let millis = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_millis();
+    std::fs::create_dir_all(dir)?;
+    let path = dir.join(format!("{}-{}.png", name, millis));
+    std::fs::write(&path, encode_png(width, height, rgba))?;
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Read;
+
+    #[test]
+    fn encodes_chunks_with_filtered_rows() {
+        let rgba = [1, 2, 3, 255, 4, 5, 6, 255];
+        let png = encode_png(1, 2, &rgba);
+        assert_eq!(png[..8], PNG_SIGNATURE);
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(png[16..24], [0, 0, 0, 1, 0, 0, 0, 2]);
+        assert_eq!(crc32(&png[12..29]).to_be_bytes(), png[29..33]);
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+
+        let idat_len = u32::from_be_bytes(png[33..37].try_into().unwrap()) as usize;
+        assert_eq!(&png[37..41], b"IDAT");
+        let mut raw = Vec::new();
+        flate2::read::ZlibDecoder::new(&png[41..41 + idat_len])
+            .read_to_end(&mut raw)
+            .unwrap();
+        assert_eq!(raw, [0, 1, 2, 3, 255, 0, 4, 5, 6, 255]);
+    }
+}
@@ -1,8 +1,18 @@
 extern crate sdl3;
 
-use crate::gameboy::GameBoy;
+use crate::cli::Options;
+use crate::config::{ConfigError, GamepadConfig, KeyBindings, TurboConfig};
+use crate::debug_view;
+use crate::debugger::{self, Command, Debugger, StopReason};
+use crate::gameboy::{GameBoy, GameBoyError};
+use crate::gdbstub::GdbServer;
+use crate::input::{Binding, InputLayer};
 use crate::joypad::JoypadKey;
-use sdl3::event::Event;
+use crate::movie::{MovieError, MovieSession};
+use crate::savestate::{self, StateError};
+use crate::screenshot;
+use crate::speed::{self, SpeedControl, TimeStretch};
+use sdl3::event::{Event, WindowEvent};
 use sdl3::keyboard::Keycode;
 use sdl3::pixels::PixelFormat;
 use sdl3::rect::Rect;
@@ -10,9 +20,12 @@ use sdl3::rect::Rect;
 // Input Manager Module - Non-blocking SDL3 event processing and input management
 // Provides event queue management, overflow handling
 
+use sdl3::GamepadSubsystem;
+use sdl3::gamepad::{Axis, Button, Gamepad};
 use sdl3::keyboard::Scancode;
-use std::collections::VecDeque;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::default::Default;
+use std::io::Write;
 use std::time::{Duration, Instant};
 
 fn sleep_until(deadline: Instant) {
@@ -36,21 +49,22 @@ fn sleep_until(deadline: Instant) {
 
 /// 錯誤類型
 #[derive(Debug)]
-#[allow(dead_code)]
 pub enum EmulatorError {
     SdlInit(String),
     VideoSubsystem(String),
     AudioSubsystem(String),
     AudioStream(String),
     WindowCreation(String),
-    CanvasCreation(String),
     TextureCreation(String),
-    RomLoad(String),
     EventPump(String),
-    TextureUpdate(String),
-    CanvasCopy(String),
     InvalidPath(String),
     OpcodesLoad(String),
+    TraceFile(String),
+    GdbBind(String),
+    GameBoy(GameBoyError),
+    State { path: String, source: StateError },
+    Movie { path: String, source: MovieError },
+    Config(ConfigError),
 }
 
 impl std::fmt::Display for EmulatorError {
@@ -61,52 +75,167 @@ impl std::fmt::Display for EmulatorError {
             EmulatorError::AudioSubsystem(msg) => write!(f, "音訊子系統初始化失敗: {}", msg),
             EmulatorError::AudioStream(msg) => write!(f, "音訊串流創建失敗: {}", msg),
             EmulatorError::WindowCreation(msg) => write!(f, "視窗創建失敗: {}", msg),
-            EmulatorError::CanvasCreation(msg) => write!(f, "畫布創建失敗: {}", msg),
             EmulatorError::TextureCreation(msg) => write!(f, "紋理創建失敗: {}", msg),
-            EmulatorError::RomLoad(msg) => write!(f, "ROM 載入失敗: {}", msg),
             EmulatorError::EventPump(msg) => write!(f, "事件泵初始化失敗: {}", msg),
-            EmulatorError::TextureUpdate(msg) => write!(f, "紋理更新失敗: {}", msg),
-            EmulatorError::CanvasCopy(msg) => write!(f, "畫布複製失敗: {}", msg),
             EmulatorError::InvalidPath(msg) => write!(f, "無效路徑: {}", msg),
             EmulatorError::OpcodesLoad(msg) => write!(f, "操作碼載入失敗: {}", msg),
+            EmulatorError::TraceFile(msg) => write!(f, "追蹤檔案建立失敗: {}", msg),
+            EmulatorError::GdbBind(msg) => write!(f, "GDB 連接埠綁定失敗: {}", msg),
+            EmulatorError::GameBoy(err) => write!(f, "{}", err),
+            EmulatorError::State { path, source } => write!(f, "{}: {}", path, source),
+            EmulatorError::Movie { path, source } => write!(f, "{}: {}", path, source),
+            EmulatorError::Config(err) => write!(f, "{}", err),
         }
     }
 }
 
-impl std::error::Error for EmulatorError {}
+impl std::error::Error for EmulatorError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EmulatorError::GameBoy(err) => Some(err),
+            EmulatorError::State { source, .. } => Some(source),
+            EmulatorError::Movie { source, .. } => Some(source),
+            EmulatorError::Config(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<ConfigError> for EmulatorError {
+    fn from(err: ConfigError) -> Self {
+        EmulatorError::Config(err)
+    }
+}
+
+impl From<GameBoyError> for EmulatorError {
+    fn from(err: GameBoyError) -> Self {
+        EmulatorError::GameBoy(err)
+    }
+}
+
+/// 結束代碼：0 = 正常結束，2 = 命令列參數或設定檔錯誤
+pub const EXIT_USAGE: u8 = 2;
+
+impl EmulatorError {
+    /// 依錯誤類別區分的結束代碼，方便腳本判斷失敗原因
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            EmulatorError::Config(_) => EXIT_USAGE,
+            EmulatorError::InvalidPath(_)
+            | EmulatorError::GameBoy(_)
+            | EmulatorError::State { .. }
+            | EmulatorError::Movie { .. } => 3,
+            EmulatorError::OpcodesLoad(_) => 4,
+            EmulatorError::SdlInit(_)
+            | EmulatorError::VideoSubsystem(_)
+            | EmulatorError::AudioSubsystem(_)
+            | EmulatorError::AudioStream(_)
+            | EmulatorError::WindowCreation(_)
+            | EmulatorError::TextureCreation(_)
+            | EmulatorError::EventPump(_) => 5,
+            EmulatorError::TraceFile(_) | EmulatorError::GdbBind(_) => 6,
+        }
+    }
+}
 
 /// Configuration for input processing
 #[derive(Debug, Clone)]
 pub struct InputConfig {
     pub max_queue_size: usize,
-    pub game_specific_mapping: Option<String>,
     pub key_mappings: KeyMappings,
 }
 
-/// Custom key mappings
+/// Custom key mappings (鍵盤與手把)
 #[derive(Debug, Clone)]
 pub struct KeyMappings {
-    pub scancode_to_key: std::collections::HashMap<sdl3::keyboard::Scancode, JoypadKey>,
-    pub alternative_mappings: std::collections::HashMap<
-        String,
-        std::collections::HashMap<sdl3::keyboard::Scancode, JoypadKey>,
-    >,
+    pub scancode_to_key: HashMap<Scancode, JoypadKey>,
+    pub button_to_key: HashMap<Button, JoypadKey>,
+    pub scancode_to_turbo: HashMap<Scancode, JoypadKey>,
+    pub button_to_turbo: HashMap<Button, JoypadKey>,
+    pub analog_stick: bool,
+    pub stick_threshold: i16, // 搖桿軸的絕對值超過此值才算按下
 }
 
 impl Default for KeyMappings {
     fn default() -> Self {
         Self {
             scancode_to_key: JoypadKey::get_keyboard_mapping(),
-            alternative_mappings: std::collections::HashMap::new(),
+            button_to_key: default_gamepad_mapping(),
+            scancode_to_turbo: HashMap::new(),
+            button_to_turbo: HashMap::new(),
+            analog_stick: true,
+            stick_threshold: stick_threshold(GamepadConfig::default().deadzone),
         }
     }
 }
 
+/// 預設手把映射 (依按鈕位置：右邊的按鈕為 A、下面的按鈕為 B，與 Game Boy 相同)
+fn default_gamepad_mapping() -> HashMap<Button, JoypadKey> {
+    HashMap::from([
+        (Button::DPadUp, JoypadKey::Up),
+        (Button::DPadDown, JoypadKey::Down),
+        (Button::DPadLeft, JoypadKey::Left),
+        (Button::DPadRight, JoypadKey::Right),
+        (Button::East, JoypadKey::A),
+        (Button::South, JoypadKey::B),
+        (Button::Start, JoypadKey::Start),
+        (Button::Back, JoypadKey::Select),
+    ])
+}
+
+// 死區比例換算成軸的門檻值
+fn stick_threshold(deadzone: f32) -> i16 {
+    (deadzone * i16::MAX as f32) as i16
+}
+
+impl KeyMappings {
+    /// 預設綁定加上設定檔的綁定：設定檔列出的 Game Boy 按鍵取代該鍵所有的預設按鍵 (或按鈕)
+    pub fn with_bindings(
+        bindings: &KeyBindings,
+        gamepad: &GamepadConfig,
+        turbo: &TurboConfig,
+    ) -> Result<Self, ConfigError> {
+        let mut mappings = Self::default();
+        for (&key, names) in bindings {
+            mappings.scancode_to_key.retain(|_, mapped| *mapped != key);
+            for name in names {
+                let scancode = Scancode::from_name(name)
+                    .ok_or_else(|| ConfigError::UnknownKey(name.clone()))?;
+                mappings.scancode_to_key.insert(scancode, key);
+            }
+        }
+        for (&key, names) in &gamepad.buttons {
+            mappings.button_to_key.retain(|_, mapped| *mapped != key);
+            for name in names {
+                let button = Button::from_string(name)
+                    .ok_or_else(|| ConfigError::UnknownButton(name.clone()))?;
+                mappings.button_to_key.insert(button, key);
+            }
+        }
+        for (&key, names) in &turbo.keys {
+            for name in names {
+                let scancode = Scancode::from_name(name)
+                    .ok_or_else(|| ConfigError::UnknownKey(name.clone()))?;
+                mappings.scancode_to_turbo.insert(scancode, key);
+            }
+        }
+        for (&key, names) in &turbo.buttons {
+            for name in names {
+                let button = Button::from_string(name)
+                    .ok_or_else(|| ConfigError::UnknownButton(name.clone()))?;
+                mappings.button_to_turbo.insert(button, key);
+            }
+        }
+        mappings.analog_stick = gamepad.analog;
+        mappings.stick_threshold = stick_threshold(gamepad.deadzone);
+        Ok(mappings)
+    }
+}
+
 impl Default for InputConfig {
     fn default() -> Self {
         Self {
             max_queue_size: 1000,
-            game_specific_mapping: None,
             key_mappings: KeyMappings::default(),
         }
     }
@@ -120,12 +249,27 @@ pub struct QueuedEvent {
     pub processed: bool,
 }
 
+/// 按下 Game Boy 按鍵的來源：同一個按鍵可由鍵盤與多個手把同時按住，
+/// 全部放開後才送出放開事件
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum InputSource {
+    Keyboard(Scancode),
+    Button(u32, Button), // (手把 ID, 按鈕)
+    Stick(u32),
+}
+
+// 一次事件處理產生的 (綁定, 是否按下)
+type Actions = Vec<(Binding, bool)>;
+
 /// Main input manager for handling SDL3 events
 pub struct InputManager {
     event_queue: VecDeque<QueuedEvent>,
     config: InputConfig,
     last_poll_time: Instant,
     overflow_count: u64,
+    gamepad_subsystem: Option<GamepadSubsystem>,
+    gamepads: HashMap<u32, Gamepad>, // 以 joystick ID 為鍵，開啟後 SDL 才會送出按鈕事件
+    held: HashSet<(InputSource, Binding)>,
 }
 
 impl InputManager {
@@ -135,6 +279,98 @@ impl InputManager {
             config,
             last_poll_time: Instant::now(),
             overflow_count: 0,
+            gamepad_subsystem: None,
+            gamepads: HashMap::new(),
+            held: HashSet::new(),
+        }
+    }
+
+    /// 啟用手把：開啟目前已連接的手把，之後的插拔由 ControllerDevice 事件處理
+    pub fn enable_gamepads(&mut self, subsystem: GamepadSubsystem) {
+        let connected = subsystem.gamepads().unwrap_or_default();
+        self.gamepad_subsystem = Some(subsystem);
+        for id in connected {
+            self.open_gamepad(id.0);
+        }
+    }
+
+    fn open_gamepad(&mut self, which: u32) {
+        let Some(subsystem) = self.gamepad_subsystem.as_ref() else {
+            return;
+        };
+        if self.gamepads.contains_key(&which) {
+            return;
+        }
+        match subsystem.open(sdl3_sys::joystick::SDL_JoystickID(which)) {
+            Ok(gamepad) => {
+                println!(
+                    "已連接手把: {}",
+                    gamepad.name().unwrap_or_else(|| which.to_string())
+                );
+                self.gamepads.insert(which, gamepad);
+            }
+            Err(e) => eprintln!("無法開啟手把 {}: {}", which, e),
+        }
+    }
+
+    /// 拔除手把：關閉並放開它按住的所有按鍵
+    fn close_gamepad(&mut self, which: u32, actions: &mut Actions) {
+        if let Some(gamepad) = self.gamepads.remove(&which) {
+            println!(
+                "已移除手把: {}",
+                gamepad.name().unwrap_or_else(|| which.to_string())
+            );
+        }
+        let sources: Vec<_> = self
+            .held
+            .iter()
+            .filter(|(source, _)| match source {
+                InputSource::Button(id, _) | InputSource::Stick(id) => *id == which,
+                InputSource::Keyboard(_) => false,
+            })
+            .copied()
+            .collect();
+        for (source, key) in sources {
+            self.release(source, key, actions);
+        }
+    }
+
+    fn press(&mut self, source: InputSource, binding: Binding, actions: &mut Actions) {
+        let already_held = self.is_held(binding);
+        if self.held.insert((source, binding)) && !already_held {
+            actions.push((binding, true));
+        }
+    }
+
+    fn release(&mut self, source: InputSource, binding: Binding, actions: &mut Actions) {
+        if self.held.remove(&(source, binding)) && !self.is_held(binding) {
+            actions.push((binding, false));
+        }
+    }
+
+    fn is_held(&self, binding: Binding) -> bool {
+        self.held.iter().any(|&(_, held)| held == binding)
+    }
+
+    // 類比搖桿：超過門檻時按下該方向並放開反方向，回到死區內時兩個方向都放開
+    fn stick_axis(
+        &mut self,
+        which: u32,
+        (negative, positive): (JoypadKey, JoypadKey),
+        value: i16,
+        actions: &mut Actions,
+    ) {
+        let threshold = self.config.key_mappings.stick_threshold;
+        let source = InputSource::Stick(which);
+        for (key, pressed) in [
+            (Binding::Key(negative), value < -threshold),
+            (Binding::Key(positive), value > threshold),
+        ] {
+            if pressed {
+                self.press(source, key, actions);
+            } else {
+                self.release(source, key, actions);
+            }
         }
     }
 
@@ -177,7 +413,7 @@ impl InputManager {
     /// Returns a vector of (key, pressed) tuples
     /// Special handling for Start button to ensure immediate response at startup
     /// Ensures non-blocking processing with timeout protection
-    pub fn process_events(&mut self) -> Vec<(JoypadKey, bool)> {
+    pub fn process_events(&mut self) -> Vec<(Binding, bool)> {
         let mut actions = Vec::new();
         let process_start = Instant::now();
         let max_processing_time = Duration::from_millis(1); // Max 1ms for input processing
@@ -202,11 +438,10 @@ impl InputManager {
             if should_process {
                 let event_clone = self.event_queue[i].event.clone();
                 let timestamp = self.event_queue[i].timestamp;
-                let action = self.process_single_event(&event_clone, timestamp);
 
-                if let Some((key, pressed)) = action {
+                for (key, pressed) in self.process_single_event(&event_clone, timestamp) {
                     // Special handling for Start button - ensure immediate response at startup
-                    if key == JoypadKey::Start && pressed {
+                    if key == Binding::Key(JoypadKey::Start) && pressed {
                         // For Start button presses, prioritize immediate processing
                         // This ensures startup responsiveness without queuing delays
                         actions.insert(0, (key, pressed)); // Insert at front for priority
@@ -234,38 +469,87 @@ impl InputManager {
         actions
     }
 
-    /// Process a single SDL event and return input action if applicable
-    fn process_single_event(
-        &self,
-        event: &Event,
-        _timestamp: Instant,
-    ) -> Option<(JoypadKey, bool)> {
-        match event {
+    /// Process a single SDL event and return input actions if applicable
+    fn process_single_event(&mut self, event: &Event, _timestamp: Instant) -> Actions {
+        let mut actions = Actions::new();
+        match *event {
             Event::KeyDown {
                 scancode: Some(sc), ..
-            } => self.map_scancode(*sc).map(|key| (key, true)),
+            } => {
+                if let Some(key) = self.map_scancode(sc) {
+                    self.press(InputSource::Keyboard(sc), key, &mut actions);
+                }
+            }
             Event::KeyUp {
                 scancode: Some(sc), ..
-            } => self.map_scancode(*sc).map(|key| (key, false)),
-            _ => None,
+            } => {
+                if let Some(key) = self.map_scancode(sc) {
+                    self.release(InputSource::Keyboard(sc), key, &mut actions);
+                }
+            }
+            Event::ControllerButtonDown { which, button, .. } => {
+                if let Some(binding) = self.map_button(button) {
+                    self.press(InputSource::Button(which, button), binding, &mut actions);
+                }
+            }
+            Event::ControllerButtonUp { which, button, .. } => {
+                if let Some(binding) = self.map_button(button) {
+                    self.release(InputSource::Button(which, button), binding, &mut actions);
+                }
+            }
+            Event::ControllerAxisMotion {
+                which, axis, value, ..
+            } if self.config.key_mappings.analog_stick => match axis {
+                Axis::LeftX => {
+                    let keys = (JoypadKey::Left, JoypadKey::Right);
+                    self.stick_axis(which, keys, value, &mut actions)
+                }
+                Axis::LeftY => {
+                    let keys = (JoypadKey::Up, JoypadKey::Down);
+                    self.stick_axis(which, keys, value, &mut actions)
+                }
+                _ => {}
+            },
+            Event::ControllerDeviceAdded { which, .. } => self.open_gamepad(which),
+            Event::ControllerDeviceRemoved { which, .. } => self.close_gamepad(which, &mut actions),
+            _ => {}
         }
+        actions
     }
 
-    /// Map SDL scancode to JoypadKey with game-specific adjustments
-    fn map_scancode(&self, scancode: Scancode) -> Option<JoypadKey> {
-        // Apply game-specific mapping if configured
-        if let Some(game) = &self.config.game_specific_mapping
-            && let Some(alt_mapping) = self.config.key_mappings.alternative_mappings.get(game)
-        {
-            return alt_mapping.get(&scancode).copied();
-        }
-
-        // Use default mapping
-        self.config
-            .key_mappings
+    /// Map SDL scancode to a binding (個別遊戲的綁定已在設定檔載入時合併，一般按鍵優先於連發)
+    fn map_scancode(&self, scancode: Scancode) -> Option<Binding> {
+        let mappings = &self.config.key_mappings;
+        mappings
             .scancode_to_key
             .get(&scancode)
-            .copied()
+            .map(|&key| Binding::Key(key))
+            .or_else(|| {
+                mappings
+                    .scancode_to_turbo
+                    .get(&scancode)
+                    .map(|&key| Binding::Turbo(key))
+            })
+    }
+
+    fn map_button(&self, button: Button) -> Option<Binding> {
+        let mappings = &self.config.key_mappings;
+        mappings
+            .button_to_key
+            .get(&button)
+            .map(|&key| Binding::Key(key))
+            .or_else(|| {
+                mappings
+                    .button_to_turbo
+                    .get(&button)
+                    .map(|&key| Binding::Turbo(key))
+            })
+    }
+
+    /// 最近 `count` 個輪詢到的事件 (由舊到新)
+    pub fn recent_events(&self, count: usize) -> impl Iterator<Item = &Event> {
+        let skip = self.event_queue.len().saturating_sub(count);
+        self.event_queue.iter().skip(skip).map(|e| &e.event)
     }
 
     /// Check if the last event was a quit event
@@ -289,8 +573,8 @@ impl InputManager {
     }
 }
 
-use crossbeam::channel::Receiver;
-use sdl3::audio::{AudioCallback, AudioFormat, AudioSpec, AudioStream};
+use crossbeam::channel::{Receiver, Sender};
+use sdl3::audio::{AudioCallback, AudioFormat, AudioSpec, AudioStream, AudioStreamWithCallback};
 
 /// Configure SDL3 for stable rendering
 fn configure_sdl3_low_latency() {
@@ -316,13 +600,279 @@ impl AudioCallback<f32> for GbAudio {
     }
 }
 
-pub fn main(rom_path: String) {
-    let sdl_context = sdl3::init().expect("SDL 初始化失敗");
-    let video_subsystem = sdl_context.video().expect("視訊子系統初始化失敗");
-    let audio_subsystem = sdl_context.audio().expect("音訊子系統初始化失敗");
+/// 除錯相關的啟動選項
+#[derive(Debug, Default)]
+pub struct DebugOptions {
+    pub repl: bool,                   // --debug：stdin 除錯命令
+    pub gdb_port: Option<u16>,        // --gdb：GDB 遠端除錯連接埠
+    pub trace_path: Option<String>,   // --trace：Gameboy Doctor 追蹤紀錄
+    pub trace_labels: bool,           // --trace-labels：追蹤紀錄插入符號標籤
+    pub profile_path: Option<String>, // --profile：效能分析輸出檔名前綴
+    pub debug_windows: bool,          // --debug-windows：記憶體與 VRAM 檢視視窗
+}
 
-    // Configure SDL3 for low-latency input processing
-    configure_sdl3_low_latency();
+// 追蹤模式：以 Gameboy Doctor 格式逐指令寫入檔案
+pub fn open_trace(
+    gb: &mut GameBoy,
+    debug: &DebugOptions,
+) -> Result<Option<std::io::BufWriter<std::fs::File>>, EmulatorError> {
+    let Some(path) = debug.trace_path.as_deref() else {
+        return Ok(None);
+    };
+    let file = std::fs::File::create(path)
+        .map_err(|e| EmulatorError::TraceFile(format!("{}: {}", path, e)))?;
+    gb.enable_trace();
+    gb.trace_labels = debug.trace_labels;
+    Ok(Some(std::io::BufWriter::new(file)))
+}
+
+/// 依 --record / --play 開始錄影或播放影片 (需在載入 ROM 與即時存檔之後呼叫)
+pub fn open_movie(
+    gb: &mut GameBoy,
+    options: &Options,
+) -> Result<Option<MovieSession>, EmulatorError> {
+    let movie_error = |path: &std::path::Path, source| EmulatorError::Movie {
+        path: path.display().to_string(),
+        source,
+    };
+    if let Some(path) = options.play.as_deref() {
+        let session = MovieSession::play(gb, path).map_err(|e| movie_error(path, e))?;
+        println!("播放影片: {}", path.display());
+        return Ok(Some(session));
+    }
+    Ok(options.record.as_deref().map(|path| {
+        // 從開機錄影時只需記錄機種，有即時存檔或開機 ROM 時整份狀態寫入影片
+        let power_on = options.state.is_none() && options.boot_rom.is_none();
+        println!("開始錄影: {}", path.display());
+        MovieSession::record(gb, path, power_on, options.model.unwrap_or_default())
+    }))
+}
+
+/// 結束錄影並寫入檔案
+pub fn close_movie(movie: Option<MovieSession>) {
+    match movie.map(MovieSession::finish) {
+        Some(Ok(Some(path))) => println!("已儲存影片: {}", path.display()),
+        Some(Err(e)) => eprintln!("無法儲存影片: {}", e),
+        _ => {}
+    }
+}
+
+/// 輸入層與影片只在完整的幀開始與結束時推進：除錯器在幀中間停下或暫停時，
+/// 主迴圈照常轉動，但不會多記錄或多消耗一幀，也不會推進連發與巨集
+#[derive(Debug, Default)]
+struct FrameInput {
+    in_progress: bool,
+}
+
+impl FrameInput {
+    // 新的一幀開始前推進連發與巨集；錄影或播放影片時一次寫入整幀的按鍵狀態
+    fn begin(
+        &mut self,
+        gb: &mut GameBoy,
+        input_layer: &mut InputLayer,
+        movie: Option<&mut MovieSession>,
+    ) {
+        if std::mem::replace(&mut self.in_progress, true) {
+            return;
+        }
+        let changes = input_layer.next_frame();
+        match movie {
+            Some(movie) if !movie.finished() => movie.begin_frame(gb, input_layer.mask()),
+            _ => {
+                for (key, pressed) in changes {
+                    gb.set_key(key, pressed);
+                }
+            }
+        }
+    }
+
+    fn end(
+        &mut self,
+        gb: &mut GameBoy,
+        input_layer: &InputLayer,
+        movie: Option<&mut MovieSession>,
+    ) {
+        self.in_progress = false;
+        if end_movie_frame(movie, gb) {
+            gb.set_joypad(input_layer.mask());
+        }
+    }
+
+    // 在除錯器下執行到幀結束或停止；暫停時不執行也不推進輸入
+    fn run_debugged(
+        &mut self,
+        dbg: &mut Debugger,
+        gb: &mut GameBoy,
+        input_layer: &mut InputLayer,
+        movie: &mut Option<MovieSession>,
+    ) -> Option<StopReason> {
+        if dbg.is_paused() {
+            return None;
+        }
+        self.begin(gb, input_layer, movie.as_mut());
+        let reason = dbg.run_frame(gb);
+        if matches!(reason, None | Some(StopReason::VBlank)) {
+            self.end(gb, input_layer, movie.as_mut());
+        }
+        reason
+    }
+}
+
+/// 每幀結束後更新影片，回傳是否剛好播放完畢
+pub fn end_movie_frame(movie: Option<&mut MovieSession>, gb: &GameBoy) -> bool {
+    let Some(movie) = movie else {
+        return false;
+    };
+    let was_finished = movie.finished();
+    if let Some(frame) = movie.end_frame(gb) {
+        eprintln!("警告: 影片在第 {} 幀不同步", frame);
+    }
+    if !was_finished && movie.finished() {
+        println!("影片播放完畢");
+        return true;
+    }
+    false
+}
+
+// 記憶體檢視顯示的列數
+const HEX_ROWS: usize = 32;
+
+// 除錯視窗：每幀重新產生影像並建立一次性的紋理
+struct DebugWindow {
+    canvas: sdl3::render::Canvas<sdl3::video::Window>,
+    texture_creator: sdl3::render::TextureCreator<sdl3::video::WindowContext>,
+}
+
+impl DebugWindow {
+    fn open(
+        video: &sdl3::VideoSubsystem,
+        title: &str,
+        width: u32,
+        height: u32,
+    ) -> Result<Self, EmulatorError> {
+        let window = video
+            .window(title, width, height)
+            .resizable()
+            .build()
+            .map_err(|e| EmulatorError::WindowCreation(e.to_string()))?;
+        let canvas = window.into_canvas();
+        let texture_creator = canvas.texture_creator();
+        Ok(DebugWindow {
+            canvas,
+            texture_creator,
+        })
+    }
+
+    fn id(&self) -> u32 {
+        self.canvas.window().id()
+    }
+
+    fn present(&mut self, image: &debug_view::Image) {
+        let Ok(mut texture) = self.texture_creator.create_texture_streaming(
+            PixelFormat::ABGR8888,
+            image.width as u32,
+            image.height as u32,
+        ) else {
+            return;
+        };
+        texture.update(None, &image.pixels, image.pitch()).ok();
+        self.canvas.clear();
+        self.canvas.copy(&texture, None, None).ok();
+        self.canvas.present();
+    }
+}
+
+/// 記憶體十六進位檢視與 VRAM/OAM 檢視視窗
+struct DebugWindows {
+    memory: Option<DebugWindow>,
+    vram: Option<DebugWindow>,
+    hex_start: u16,
+}
+
+impl DebugWindows {
+    fn open(video: &sdl3::VideoSubsystem) -> Result<Self, EmulatorError> {
+        Ok(DebugWindows {
+            memory: Some(DebugWindow::open(video, "Memory", 640, 600)?),
+            vram: Some(DebugWindow::open(video, "VRAM", 1296, 780)?),
+            hex_start: 0xC000,
+        })
+    }
+
+    // 捲動記憶體檢視；關閉個別視窗
+    fn handle_event(&mut self, event: &Event) {
+        let memory_id = self.memory.as_ref().map(DebugWindow::id);
+        let vram_id = self.vram.as_ref().map(DebugWindow::id);
+        let row = debug_view::HEX_BYTES_PER_ROW;
+        let page = row * HEX_ROWS as u16;
+        match *event {
+            Event::MouseWheel { window_id, y, .. } if Some(window_id) == memory_id => {
+                let rows = (y.abs().ceil() as u16).max(1) * row;
+                self.hex_start = if y > 0.0 {
+                    self.hex_start.wrapping_sub(rows)
+                } else {
+                    self.hex_start.wrapping_add(rows)
+                };
+            }
+            Event::KeyDown {
+                window_id,
+                keycode: Some(keycode),
+                ..
+            } if Some(window_id) == memory_id => match keycode {
+                Keycode::PageUp => self.hex_start = self.hex_start.wrapping_sub(page),
+                Keycode::PageDown => self.hex_start = self.hex_start.wrapping_add(page),
+                _ => {}
+            },
+            Event::Window {
+                window_id,
+                win_event: WindowEvent::CloseRequested,
+                ..
+            } => {
+                if Some(window_id) == memory_id {
+                    self.memory = None;
+                } else if Some(window_id) == vram_id {
+                    self.vram = None;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn render(&mut self, gb: &GameBoy) {
+        if let Some(window) = self.memory.as_mut() {
+            let lines = debug_view::hex_lines(&gb.mmu, self.hex_start, HEX_ROWS);
+            window.present(&debug_view::text_image(&lines));
+        }
+        if let Some(window) = self.vram.as_mut() {
+            window.present(&debug_view::vram_view(&gb.mmu.ppu));
+        }
+    }
+}
+
+// 結束時輸出效能分析結果：<prefix>.folded 與 <prefix>.txt
+pub fn write_profile(gb: &GameBoy, prefix: &str) {
+    let Some(profiler) = gb.profiler.as_ref() else {
+        return;
+    };
+    let folded = profiler.folded_stacks(&gb.mmu, &gb.symbols);
+    let report = profiler.report(&gb.mmu, &gb.symbols);
+    for (path, content) in [
+        (format!("{}.folded", prefix), folded),
+        (format!("{}.txt", prefix), report),
+    ] {
+        match std::fs::write(&path, content) {
+            Ok(()) => println!("已寫入效能分析: {}", path),
+            Err(e) => eprintln!("無法寫入 {}: {}", path, e),
+        }
+    }
+}
+
+// 開啟音訊輸出，回傳串流 (需保持存活) 與樣本的傳送端
+fn open_audio(
+    sdl_context: &sdl3::Sdl,
+) -> Result<(AudioStreamWithCallback<GbAudio>, Sender<f32>), EmulatorError> {
+    let audio_subsystem = sdl_context
+        .audio()
+        .map_err(|e| EmulatorError::AudioSubsystem(e.to_string()))?;
 
     let (tx, rx) = crossbeam::channel::bounded::<f32>(16384);
 
@@ -334,16 +884,134 @@ pub fn main(rom_path: String) {
 
     let stream = audio_subsystem
         .open_playback_stream(&spec, GbAudio { receiver: rx })
-        .expect("音訊串流創建失敗");
-    stream.resume().expect("音訊串流恢復失敗");
+        .map_err(|e| EmulatorError::AudioStream(e.to_string()))?;
+    stream
+        .resume()
+        .map_err(|e| EmulatorError::AudioStream(e.to_string()))?;
+    Ok((stream, tx))
+}
+
+// F5 / F8：儲存或載入即時存檔
+fn handle_state_hotkey(gb: &mut GameBoy, keycode: Keycode, path: &std::path::Path) {
+    let result = match keycode {
+        Keycode::F5 => savestate::save_file(gb, path).map(|()| "已儲存即時存檔"),
+        Keycode::F8 => savestate::load_file(gb, path).map(|()| "已載入即時存檔"),
+        _ => return,
+    };
+    match result {
+        Ok(message) => println!("{}: {}", message, path.display()),
+        Err(e) => eprintln!("{}: {}", path.display(), e),
+    }
+}
+
+const W: u32 = 160;
+const H: u32 = 144;
+
+// 將 RGBA 畫面以整數倍率置中繪製到視窗
+fn present_frame(
+    canvas: &mut sdl3::render::Canvas<sdl3::video::Window>,
+    stream_tex: &mut sdl3::render::Texture,
+    rgba: &[u8],
+) {
+    stream_tex.update(None, rgba, (W * 4) as usize).ok();
+
+    canvas.clear();
+    let (win_w, win_h) = canvas.window().size();
+    let scale = (win_w as f32 / W as f32)
+        .min(win_h as f32 / H as f32)
+        .floor()
+        .max(1.0);
+    let dest_w = (W as f32 * scale) as u32;
+    let dest_h = (H as f32 * scale) as u32;
+    let dest = Rect::new(
+        ((win_w - dest_w) / 2) as i32,
+        ((win_h - dest_h) / 2) as i32,
+        dest_w,
+        dest_h,
+    );
+    canvas.copy(stream_tex, None, dest).ok();
+    canvas.present();
+}
+
+// 設定檔中的按鍵名稱
+fn keycode(name: &str) -> Result<Keycode, ConfigError> {
+    Keycode::from_name(name).ok_or_else(|| ConfigError::UnknownKey(name.to_string()))
+}
+
+// 音訊佇列最多累積 200ms，超過時丟棄新的一幀 (經由 TimeStretch 交叉淡化)
+const MAX_QUEUED_SAMPLES: usize = 44100 / 5;
+
+// F12：存到 --screenshot-dir (預設與 ROM 同目錄)，以 ROM 檔名命名
+fn save_screenshot(options: &Options, rgba: &[u8]) {
+    let rom_path = std::path::Path::new(&options.rom_path);
+    let dir = options.screenshot_dir.clone().unwrap_or_else(|| {
+        rom_path
+            .parent()
+            .map(|p| p.to_path_buf())
+            .unwrap_or_default()
+    });
+    let name = rom_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "screenshot".to_string());
+    match screenshot::save(&dir, &name, W, H, rgba) {
+        Ok(path) => println!("已儲存螢幕截圖: {}", path.display()),
+        Err(e) => eprintln!("{}: {}", dir.display(), e),
+    }
+}
+
+// 巨集按鍵：Ctrl+按鍵開始/停止錄製，單按播放
+fn handle_macro_hotkey(input: &mut InputLayer, slot: usize, keymod: sdl3::keyboard::Mod) {
+    use sdl3::keyboard::Mod;
+
+    if keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD) {
+        match input.toggle_recording(slot) {
+            Some(frames) => println!("巨集 {} 已錄製 {} 幀", slot + 1, frames),
+            None => println!("開始錄製巨集 {} (再按 Ctrl+按鍵停止)", slot + 1),
+        }
+    } else if !input.is_recording() && !input.play(slot) {
+        println!("巨集 {} 尚未錄製", slot + 1);
+    }
+}
+
+pub fn main(mut gb: GameBoy, options: Options) -> Result<(), EmulatorError> {
+    let debug = options.debug.repl;
+    let state_path = options.state_path();
+
+    let sdl_context = sdl3::init().map_err(|e| EmulatorError::SdlInit(e.to_string()))?;
+    let video_subsystem = sdl_context
+        .video()
+        .map_err(|e| EmulatorError::VideoSubsystem(e.to_string()))?;
+
+    // Configure SDL3 for low-latency input processing
+    configure_sdl3_low_latency();
+
+    // 沒有音訊裝置時改為靜音執行
+    let audio = if options.mute == Some(true) {
+        None
+    } else {
+        match open_audio(&sdl_context) {
+            Ok(audio) => Some(audio),
+            Err(e) => {
+                eprintln!("{}，改為靜音模式", e);
+                None
+            }
+        }
+    };
 
-    let window = video_subsystem
-        .window("GameBoy", 800, 600)
-        .position_centered()
-        .resizable()
+    let (window_w, window_h) = options
+        .scale
+        .map_or((800, 600), |scale| (160 * scale, 144 * scale));
+    let mut window_builder = video_subsystem.window("GameBoy", window_w, window_h);
+    window_builder.position_centered().resizable();
+    if options.fullscreen == Some(true) {
+        window_builder.fullscreen();
+    }
+    let window = window_builder
         .build()
-        .expect("視窗創建失敗");
+        .map_err(|e| EmulatorError::WindowCreation(e.to_string()))?;
 
+    let main_window_id = window.id();
     let mut canvas = window.into_canvas();
     canvas.set_draw_color(sdl3::pixels::Color::RGB(0, 0, 0));
     canvas.clear();
@@ -352,98 +1020,245 @@ pub fn main(rom_path: String) {
     let texture_creator = canvas.texture_creator();
     let mut stream_tex = texture_creator
         .create_texture_streaming(PixelFormat::ABGR8888, 160, 144)
-        .expect("紋理創建失敗");
+        .map_err(|e| EmulatorError::TextureCreation(e.to_string()))?;
 
     // 預先分配 RGBA 緩衝區，避免每幀重複分配
-    const W: u32 = 160;
-    const H: u32 = 144;
     let mut rgba = vec![0u8; (W * H * 4) as usize];
 
-    // emulator instance
-    let mut gb = GameBoy::new();
-    gb.load_rom(&rom_path).expect("ROM 載入失敗");
-
     // Create input manager
-    let mut input_config = InputConfig {
-        game_specific_mapping: Some("tetris".to_string()), // Configure for Tetris
+    let input_config = InputConfig {
+        key_mappings: KeyMappings::with_bindings(
+            &options.key_bindings,
+            &options.gamepad,
+            &options.turbo,
+        )?,
         ..Default::default()
     };
+    let mut input_manager = InputManager::with_config(input_config);
+    let macro_keys = options
+        .macros
+        .keys
+        .iter()
+        .map(|name| keycode(name))
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut input_layer = InputLayer::new(options.turbo.rate, macro_keys.len());
+    if options.gamepad.enabled {
+        match sdl_context.gamepad() {
+            Ok(subsystem) => input_manager.enable_gamepads(subsystem),
+            Err(e) => eprintln!("無法初始化手把: {}", e),
+        }
+    }
+
+    let mut trace_file = open_trace(&mut gb, &options.debug)?;
+    let mut movie = open_movie(&mut gb, &options)?;
+    let mut frame_input = FrameInput::default();
 
-    // Set up Tetris-specific key mappings
-    let tetris_mapping = JoypadKey::get_keyboard_mapping();
+    let mut debug_windows = if options.debug.debug_windows {
+        Some(DebugWindows::open(&video_subsystem)?)
+    } else {
+        None
+    };
 
-    input_config
-        .key_mappings
-        .alternative_mappings
-        .insert("tetris".to_string(), tetris_mapping);
+    let mut event_pump = sdl_context
+        .event_pump()
+        .map_err(|e| EmulatorError::EventPump(e.to_string()))?;
+
+    // 59.7275 FPS，依 --speed、快轉與慢動作縮放
+    let fast_forward = options.speed_control.fast_forward;
+    let mut speed = SpeedControl::new(
+        options.speed.unwrap_or(1.0),
+        (fast_forward > 0.0).then_some(fast_forward),
+    );
+    let hold_key = keycode(&options.speed_control.hold_key)?;
+    let toggle_key = keycode(&options.speed_control.toggle_key)?;
+    let slow_key = keycode(&options.speed_control.slow_key)?;
+    let mut last_present = Instant::now();
+    let palette = options.palette.unwrap_or_default();
+    let volume = options.volume.unwrap_or(1.0);
+    let mut frames_run = 0u64;
+    let mut screenshot_requested = false;
+    let mut time_stretch = TimeStretch::new();
+    let mut next_frame = Instant::now();
 
-    let mut input_manager = InputManager::with_config(input_config);
+    // 除錯模式：從 stdin 讀取命令，啟動後先暫停
+    let repl = debug.then(|| {
+        println!("除錯模式已啟用，輸入 help 查看命令");
+        debugger::spawn_stdin_reader()
+    });
+
+    // GDB 模式：等待客戶端連線前照常執行遊戲
+    let mut gdb_server = match options.debug.gdb_port {
+        Some(port) => {
+            let server = GdbServer::bind(port)
+                .map_err(|e| EmulatorError::GdbBind(format!("{}: {}", port, e)))?;
+            println!("GDB 伺服器監聽於 127.0.0.1:{}", port);
+            Some(server)
+        }
+        None => None,
+    };
 
-    let mut event_pump = sdl_context.event_pump().expect("事件泵初始化失敗");
+    let mut debugger = (debug || gdb_server.is_some()).then(|| {
+        let mut dbg = Debugger::new();
+        if !debug {
+            dbg.execute(&mut gb, Command::Continue);
+        }
+        dbg
+    });
 
-    let frame_duration = Duration::from_micros(16743); // 59.7275 FPS = 16.743ms
-    let mut next_frame = Instant::now();
+    if options.debug.profile_path.is_some() {
+        gb.enable_profiler();
+    }
 
-    loop {
+    'running: loop {
         // Poll events
-        input_manager.poll_events(&mut event_pump);
+        let polled = input_manager.poll_events(&mut event_pump);
+        for event in input_manager.recent_events(polled) {
+            // 開啟多個視窗時，關閉主視窗不會產生 Quit 事件
+            if let Event::Window {
+                window_id,
+                win_event: WindowEvent::CloseRequested,
+                ..
+            } = *event
+                && window_id == main_window_id
+            {
+                break 'running;
+            }
+            if let Event::KeyDown {
+                keycode: Some(keycode),
+                keymod,
+                repeat: false,
+                ..
+            } = *event
+            {
+                handle_state_hotkey(&mut gb, keycode, &state_path);
+                screenshot_requested |= keycode == Keycode::F12;
+                if let Some(slot) = macro_keys.iter().position(|&key| key == keycode) {
+                    handle_macro_hotkey(&mut input_layer, slot, keymod);
+                }
+                let speed_changed = match keycode {
+                    k if k == hold_key => speed.set_held(true),
+                    k if k == toggle_key => speed.toggle_fast_forward(),
+                    k if k == slow_key => speed.cycle_slow_motion(),
+                    _ => false,
+                };
+                if speed_changed {
+                    println!("{}", speed.describe());
+                    next_frame = Instant::now();
+                }
+            }
+            if let Event::KeyUp {
+                keycode: Some(keycode),
+                ..
+            } = *event
+                && keycode == hold_key
+                && speed.set_held(false)
+            {
+                println!("{}", speed.describe());
+                next_frame = Instant::now();
+            }
+            if let Some(windows) = debug_windows.as_mut() {
+                windows.handle_event(event);
+            }
+        }
         let input_actions = input_manager.process_events();
 
-        for (key, pressed) in input_actions {
-            if gb.joypad.set_key(key, pressed) && pressed {
-                gb.mmu.if_reg |= 0x10;
-            }
+        for (binding, pressed) in input_actions {
+            input_layer.set(binding, pressed);
         }
 
         if input_manager.should_quit() || input_manager.escape_pressed() {
-            gb.mmu.save_external_ram();
-            return;
+            break;
         }
 
         // Run emulation (sync to VBlank so we always present whole frames)
-        gb.run_frame();
+        if let Some(dbg) = debugger.as_mut() {
+            for line in repl.iter().flat_map(|commands| commands.try_iter()) {
+                match line.parse::<Command>() {
+                    Ok(Command::Quit) => break 'running,
+                    Ok(command) => {
+                        let output = dbg.execute(&mut gb, command);
+                        if !output.is_empty() {
+                            println!("{}", output);
+                        }
+                    }
+                    Err(debugger::ParseError::Empty) => {}
+                    Err(e) => println!("{}", e),
+                }
+            }
+            if let Some(server) = gdb_server.as_mut() {
+                server.poll(dbg, &mut gb);
+            }
+            if let Some(reason) =
+                frame_input.run_debugged(dbg, &mut gb, &mut input_layer, &mut movie)
+            {
+                if repl.is_some() {
+                    println!("{}", dbg.describe_stop(&gb, reason));
+                }
+                if let Some(server) = gdb_server.as_mut() {
+                    server.report_stop(&gb, reason);
+                }
+            }
+        } else {
+            frame_input.begin(&mut gb, &mut input_layer, movie.as_mut());
+            gb.run_frame();
+            frame_input.end(&mut gb, &input_layer, movie.as_mut());
+        }
 
-        // Audio
-        let samples = gb.apu.drain_samples();
-        for s in samples {
-            let _ = tx.try_send(s);
+        if let Some(file) = trace_file.as_mut() {
+            let _ = file.write_all(gb.take_trace().as_bytes());
+        }
+
+        // Audio：依速度伸縮；不限速或佇列已經累積過多時丟棄整幀，避免延遲越來越大
+        let samples = gb.mmu.apu.drain_samples();
+        if let Some((_, tx)) = audio.as_ref() {
+            match speed.multiplier() {
+                Some(multiplier) if tx.len() < MAX_QUEUED_SAMPLES => {
+                    // 慢動作時一幀可能超過數千個樣本，放不下時整幀丟棄而不是從中截斷
+                    let room = tx.capacity().map_or(usize::MAX, |cap| cap - tx.len());
+                    for s in time_stretch.process_within(&samples, multiplier, room) {
+                        // 已確認佇列空間，只有音訊執行緒結束時才會失敗
+                        if tx.try_send(s * volume).is_err() {
+                            break;
+                        }
+                    }
+                }
+                _ => time_stretch.skip(),
+            }
         }
 
         // Render
         let ppu_fb = gb.get_present_framebuffer();
-        const PALETTE: [[u8; 4]; 4] = [
-            [255, 255, 255, 255],
-            [170, 170, 170, 255],
-            [85, 85, 85, 255],
-            [0, 0, 0, 255],
-        ];
-
         for (i, &idx) in ppu_fb.iter().enumerate() {
-            let color = PALETTE[(idx & 0x03) as usize];
+            let color = palette.color(idx);
             let dst = i * 4;
             rgba[dst..dst + 4].copy_from_slice(&color);
         }
 
-        stream_tex.update(None, &rgba, (W * 4) as usize).ok();
-
-        canvas.clear();
-        let (win_w, win_h) = canvas.window().size();
-        let scale = (win_w as f32 / W as f32)
-            .min(win_h as f32 / H as f32)
-            .floor()
-            .max(1.0);
-        let dest_w = (W as f32 * scale) as u32;
-        let dest_h = (H as f32 * scale) as u32;
-        let dest = Rect::new(
-            ((win_w - dest_w) / 2) as i32,
-            ((win_h - dest_h) / 2) as i32,
-            dest_w,
-            dest_h,
-        );
-        canvas.copy(&stream_tex, None, dest).ok();
-        canvas.present();
+        if std::mem::take(&mut screenshot_requested) {
+            save_screenshot(&options, &rgba);
+        }
+
+        // 快轉時每個實際的幀時間只顯示一次
+        let present = !speed.is_fast_forward() || last_present.elapsed() >= speed::FRAME_DURATION;
+        if present {
+            last_present = Instant::now();
+            present_frame(&mut canvas, &mut stream_tex, &rgba);
+            if let Some(windows) = debug_windows.as_mut() {
+                windows.render(&gb);
+            }
+        }
+
+        // --frames：執行指定幀數後結束
+        frames_run += 1;
+        if options.frames.is_some_and(|frames| frames_run >= frames) {
+            break;
+        }
 
         // Frame pacing: 累加 deadline + sleep-then-spin，避免忽快忽慢
+        let Some(frame_duration) = speed.frame_duration() else {
+            next_frame = Instant::now();
+            continue;
+        };
         next_frame += frame_duration;
         let now = Instant::now();
         if next_frame > now {
@@ -456,4 +1271,164 @@ pub fn main(rom_path: String) {
             }
         }
     }
+
+    close_movie(movie);
+    gb.mmu.save_external_ram();
+    gb.save_cheats();
+    if let Some(file) = trace_file.as_mut() {
+        let _ = file.flush();
+    }
+    if let Some(prefix) = options.debug.profile_path.as_deref() {
+        write_profile(&gb, prefix);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(input: &mut InputManager, event: Event) -> Actions {
+        input.process_single_event(&event, Instant::now())
+    }
+
+    fn button(which: u32, button: Button, pressed: bool) -> Event {
+        if pressed {
+            Event::ControllerButtonDown {
+                timestamp: 0,
+                which,
+                button,
+            }
+        } else {
+            Event::ControllerButtonUp {
+                timestamp: 0,
+                which,
+                button,
+            }
+        }
+    }
+
+    #[test]
+    fn debugger_stops_do_not_advance_movie_frames() {
+        // NOP; JR -3
+        let mut gb = GameBoy::with_program(&[0x00, 0x18, 0xFD]);
+        let path = std::env::temp_dir().join("rust_gb_debug_movie.gbm");
+        let mut movie = Some(MovieSession::record(
+            &gb,
+            &path,
+            false,
+            crate::gameboy::Model::Dmg,
+        ));
+        let mut input_layer = InputLayer::new(2, 0);
+        let mut frame_input = FrameInput::default();
+        let mut dbg = Debugger::new();
+        dbg.execute(&mut gb, Command::Continue);
+        let id = dbg.add_breakpoint(debugger::Breakpoint {
+            address: 0xC001,
+            bank: None,
+        });
+
+        // 在幀中間停在中斷點，之後暫停的迴圈都不推進影片
+        let reason = frame_input.run_debugged(&mut dbg, &mut gb, &mut input_layer, &mut movie);
+        assert_eq!(reason, Some(StopReason::Breakpoint(id)));
+        for _ in 0..5 {
+            assert_eq!(
+                frame_input.run_debugged(&mut dbg, &mut gb, &mut input_layer, &mut movie),
+                None
+            );
+        }
+        assert_eq!(movie.as_ref().unwrap().progress(), (0, 1));
+
+        // 繼續執行完這一幀才算一幀
+        dbg.execute(&mut gb, Command::Delete(id));
+        dbg.execute(&mut gb, Command::Continue);
+        let reason = frame_input.run_debugged(&mut dbg, &mut gb, &mut input_layer, &mut movie);
+        assert_eq!(reason, None);
+        assert_eq!(movie.as_ref().unwrap().progress(), (1, 1));
+    }
+
+    #[test]
+    fn exit_codes_follow_error_category() {
+        let config = EmulatorError::Config(ConfigError::Invalid("x".to_string()));
+        assert_eq!(config.exit_code(), EXIT_USAGE);
+        assert_eq!(EmulatorError::InvalidPath("x".to_string()).exit_code(), 3);
+        let rom = EmulatorError::GameBoy(GameBoyError::BootRom {
+            path: "x".to_string(),
+            source: std::io::ErrorKind::NotFound.into(),
+        });
+        assert_eq!(rom.exit_code(), 3);
+        assert_eq!(EmulatorError::OpcodesLoad("x".to_string()).exit_code(), 4);
+        assert_eq!(EmulatorError::SdlInit("x".to_string()).exit_code(), 5);
+        assert_eq!(EmulatorError::AudioStream("x".to_string()).exit_code(), 5);
+        assert_eq!(EmulatorError::TraceFile("x".to_string()).exit_code(), 6);
+        assert_eq!(EmulatorError::GdbBind("x".to_string()).exit_code(), 6);
+    }
+
+    #[test]
+    fn key_is_released_after_every_source_lets_go() {
+        let mut input = InputManager::with_config(InputConfig::default());
+        let key_down = Event::KeyDown {
+            timestamp: 0,
+            window_id: 0,
+            keycode: None,
+            scancode: Some(Scancode::Z),
+            keymod: sdl3::keyboard::Mod::NOMOD,
+            repeat: false,
+            which: 0,
+            raw: 0,
+        };
+        assert_eq!(
+            process(&mut input, key_down.clone()),
+            [(Binding::Key(JoypadKey::A), true)]
+        );
+        assert!(process(&mut input, key_down).is_empty()); // 按鍵重複
+        assert!(process(&mut input, button(1, Button::East, true)).is_empty());
+        assert!(process(&mut input, button(2, Button::East, true)).is_empty());
+
+        let key_up = Event::KeyUp {
+            timestamp: 0,
+            window_id: 0,
+            keycode: None,
+            scancode: Some(Scancode::Z),
+            keymod: sdl3::keyboard::Mod::NOMOD,
+            repeat: false,
+            which: 0,
+            raw: 0,
+        };
+        assert!(process(&mut input, key_up).is_empty());
+        assert!(process(&mut input, button(1, Button::East, false)).is_empty());
+        // 拔除手把等同放開它按住的按鈕
+        let removed = Event::ControllerDeviceRemoved {
+            timestamp: 0,
+            which: 2,
+        };
+        assert_eq!(
+            process(&mut input, removed),
+            [(Binding::Key(JoypadKey::A), false)]
+        );
+    }
+
+    #[test]
+    fn analog_stick_acts_as_dpad_outside_deadzone() {
+        let mut input = InputManager::with_config(InputConfig::default());
+        let mut stick = |value| {
+            let event = Event::ControllerAxisMotion {
+                timestamp: 0,
+                which: 1,
+                axis: Axis::LeftX,
+                value,
+            };
+            process(&mut input, event)
+        };
+        assert!(stick(-5000).is_empty());
+        assert_eq!(stick(-20000), [(Binding::Key(JoypadKey::Left), true)]);
+        assert_eq!(
+            stick(20000),
+            [
+                (Binding::Key(JoypadKey::Left), false),
+                (Binding::Key(JoypadKey::Right), true)
+            ]
+        );
+        assert_eq!(stick(0), [(Binding::Key(JoypadKey::Right), false)]);
+    }
 }
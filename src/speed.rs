@@ -0,0 +1,274 @@
+//! 模擬速度：快轉 (按住或切換) 與慢動作
+//!
+//! 優先順序：快轉 > 慢動作 > `--speed`。快轉倍率為 `None` 時不限速，不播放音訊；
+//! 其他速度以整幀丟棄或重複音訊 ([`TimeStretch`]) 讓輸出長度符合實際經過的時間，不改變音高，
+//! 也不會在音訊佇列中堆積。
+
+use std::time::Duration;
+
+// 59.7275 FPS = 16.743ms
+pub const FRAME_DURATION: Duration = Duration::from_micros(16_743);
+
+/// 慢動作依序切換的倍率
+const SLOW_STEPS: [f64; 2] = [0.5, 0.25];
+
+#[derive(Debug, Clone)]
+pub struct SpeedControl {
+    base: f64,                 // --speed
+    fast_forward: Option<f64>, // None = 不限速
+    held: bool,
+    toggled: bool,
+    slow_step: Option<usize>, // SLOW_STEPS 的索引
+}
+
+impl SpeedControl {
+    pub fn new(base: f64, fast_forward: Option<f64>) -> Self {
+        SpeedControl {
+            base,
+            fast_forward,
+            held: false,
+            toggled: false,
+            slow_step: None,
+        }
+    }
+
+    // 以下三個操作回傳速度是否改變，方便前端決定是否提示
+
+    pub fn set_held(&mut self, held: bool) -> bool {
+        let changed = self.held != held;
+        self.held = held;
+        changed
+    }
+
+    pub fn toggle_fast_forward(&mut self) -> bool {
+        self.toggled = !self.toggled;
+        true
+    }
+
+    /// 關閉 → 50% → 25% → 關閉
+    pub fn cycle_slow_motion(&mut self) -> bool {
+        self.slow_step = match self.slow_step {
+            None => Some(0),
+            Some(step) if step + 1 < SLOW_STEPS.len() => Some(step + 1),
+            Some(_) => None,
+        };
+        true
+    }
+
+    pub fn is_fast_forward(&self) -> bool {
+        self.held || self.toggled
+    }
+
+    /// 目前的速度倍率，`None` 表示不限速
+    pub fn multiplier(&self) -> Option<f64> {
+        if self.is_fast_forward() {
+            return self.fast_forward;
+        }
+        Some(match self.slow_step {
+            Some(step) => self.base * SLOW_STEPS[step],
+            None => self.base,
+        })
+    }
+
+    /// 每幀應佔用的實際時間，不限速時為 `None`
+    pub fn frame_duration(&self) -> Option<Duration> {
+        self.multiplier()
+            .map(|multiplier| FRAME_DURATION.div_f64(multiplier))
+    }
+
+    /// 顯示用的說明，例如「快轉 4x」、「慢動作 25%」
+    pub fn describe(&self) -> String {
+        match self.multiplier() {
+            None => "快轉 (不限速)".to_string(),
+            Some(_) if self.is_fast_forward() => {
+                format!("快轉 {}x", self.fast_forward.unwrap_or_default())
+            }
+            Some(multiplier) if self.slow_step.is_some() => {
+                format!("慢動作 {}%", (multiplier * 100.0).round())
+            }
+            Some(multiplier) => format!("正常速度 {}x", multiplier),
+        }
+    }
+}
+
+/// 接縫處交叉淡化的樣本數 (44.1 kHz 約 1.5ms)
+const CROSSFADE: usize = 64;
+
+/// 音訊時間伸縮：以每幀的樣本為一塊，快轉時丟棄、慢動作時重複，音高不變
+///
+/// 每塊的最後 [`CROSSFADE`] 個樣本先保留，下一塊與它不連續 (中間有丟棄的塊或重複同一塊) 時
+/// 兩者交叉淡化，避免波形跳動造成爆音；連續時原樣輸出。
+#[derive(Debug, Default, Clone)]
+pub struct TimeStretch {
+    held: Vec<f32>, // 上一塊保留的結尾
+    owed: f64,      // 依速度應輸出但尚未輸出的樣本數
+    skipped: bool,  // 保留的結尾之後有樣本被丟棄
+}
+
+impl TimeStretch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 處理一幀的樣本：倍率 2 時約一半的幀被丟棄，0.5 時每幀輸出兩次
+    pub fn process(&mut self, samples: &[f32], multiplier: f64) -> Vec<f32> {
+        let mut out = Vec::new();
+        if samples.is_empty() {
+            return out;
+        }
+        self.owed += samples.len() as f64 / multiplier;
+        let mut continuous = !self.skipped;
+        let mut emitted = false;
+        while self.owed >= samples.len() as f64 / 2.0 {
+            let before = out.len();
+            self.emit(samples, continuous, &mut out);
+            self.owed -= (out.len() - before) as f64;
+            continuous = false;
+            emitted = true;
+        }
+        self.skipped = !emitted;
+        out
+    }
+
+    /// 同 `process`，但輸出超過 `room` 個樣本時改為整幀丟棄，避免送進佇列時被截斷而爆音
+    pub fn process_within(&mut self, samples: &[f32], multiplier: f64, room: usize) -> Vec<f32> {
+        let before = self.clone();
+        let out = self.process(samples, multiplier);
+        if out.len() <= room {
+            return out;
+        }
+        *self = before;
+        self.skip();
+        Vec::new()
+    }
+
+    /// 整幀丟棄 (佇列累積過多或不限速)，下一次輸出時與目前的結尾交叉淡化
+    pub fn skip(&mut self) {
+        self.skipped = true;
+        self.owed = 0.0;
+    }
+
+    fn emit(&mut self, block: &[f32], continuous: bool, out: &mut Vec<f32>) {
+        let fade = if continuous {
+            0
+        } else {
+            CROSSFADE.min(block.len() / 2).min(self.held.len())
+        };
+        let keep = self.held.len() - fade;
+        out.extend_from_slice(&self.held[..keep]);
+        for (i, (&old, &new)) in self.held[keep..].iter().zip(block).enumerate() {
+            let t = (i + 1) as f32 / (fade + 1) as f32;
+            out.push(old * (1.0 - t) + new * t);
+        }
+        let body = &block[fade..];
+        let split = body.len().saturating_sub(CROSSFADE);
+        out.extend_from_slice(&body[..split]);
+        self.held = body[split..].to_vec();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fast_forward_overrides_slow_motion() {
+        let mut speed = SpeedControl::new(1.0, Some(4.0));
+        assert_eq!(speed.frame_duration(), Some(FRAME_DURATION));
+
+        speed.cycle_slow_motion();
+        speed.cycle_slow_motion();
+        assert_eq!(speed.multiplier(), Some(0.25));
+        speed.set_held(true);
+        assert_eq!(speed.multiplier(), Some(4.0));
+        speed.set_held(false);
+        speed.cycle_slow_motion();
+        assert_eq!(speed.multiplier(), Some(1.0));
+
+        let mut speed = SpeedControl::new(1.0, None);
+        speed.toggle_fast_forward();
+        assert_eq!(speed.frame_duration(), None);
+        speed.toggle_fast_forward();
+        assert!(!speed.is_fast_forward());
+    }
+
+    // 220 Hz 正弦波，切成每幀 735 個樣本
+    fn sine_frames(frames: usize) -> Vec<Vec<f32>> {
+        let wave: Vec<f32> = (0..frames * 735)
+            .map(|i| (i as f32 * 2.0 * std::f32::consts::PI * 220.0 / 44100.0).sin())
+            .collect();
+        wave.chunks(735).map(<[f32]>::to_vec).collect()
+    }
+
+    fn stretch(multiplier: f64, skip_every: Option<usize>) -> Vec<f32> {
+        let mut stretch = TimeStretch::new();
+        let mut out = Vec::new();
+        for (i, frame) in sine_frames(120).iter().enumerate() {
+            if skip_every.is_some_and(|n| i % n == n - 1) {
+                stretch.skip();
+            } else {
+                out.extend(stretch.process(frame, multiplier));
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn frames_that_do_not_fit_are_skipped_whole() {
+        // 0.25x 時每幀約 2940 個樣本，佇列只剩 2000 個空間時整幀丟棄
+        let mut stretch = TimeStretch::new();
+        let mut out = Vec::new();
+        for (i, frame) in sine_frames(40).iter().enumerate() {
+            let room = if i % 4 == 3 { 2000 } else { usize::MAX };
+            let samples = stretch.process_within(frame, 0.25, room);
+            assert!(samples.len() <= room);
+            out.extend(samples);
+        }
+        assert!(out.len() < 40 * 735 * 4);
+        assert!(max_step(&out) < 0.1);
+    }
+
+    fn max_step(samples: &[f32]) -> f32 {
+        samples
+            .windows(2)
+            .map(|pair| (pair[1] - pair[0]).abs())
+            .fold(0.0, f32::max)
+    }
+
+    fn zero_crossings_per_sample(samples: &[f32]) -> f64 {
+        let crossings = samples
+            .windows(2)
+            .filter(|pair| (pair[0] < 0.0) != (pair[1] < 0.0))
+            .count();
+        crossings as f64 / samples.len() as f64
+    }
+
+    #[test]
+    fn time_stretch_keeps_pitch_and_avoids_clicks() {
+        let input: Vec<f32> = sine_frames(120).concat();
+        // 正常速度時原樣輸出 (只保留最後一段等待交叉淡化)
+        assert_eq!(stretch(1.0, None), input[..input.len() - CROSSFADE]);
+
+        let expected = zero_crossings_per_sample(&input);
+        for (multiplier, skip_every) in [(2.0, None), (4.0, None), (0.5, None), (1.0, Some(3))] {
+            let out = stretch(multiplier, skip_every);
+            let played = input.len() as f64 * if skip_every.is_some() { 2.0 / 3.0 } else { 1.0 };
+            let ratio = out.len() as f64 / (played / multiplier);
+            assert!(
+                (0.9..1.1).contains(&ratio),
+                "{}x 輸出長度比例 {}",
+                multiplier,
+                ratio
+            );
+            assert!(max_step(&out) < 0.1, "{}x 接縫不連續", multiplier);
+            // 重新取樣會讓過零率變成倍率倍；交叉淡化的接縫只會帶來少量誤差
+            let pitch = zero_crossings_per_sample(&out) / expected;
+            assert!(
+                (0.9..1.1).contains(&pitch),
+                "{}x 音高改變 {}",
+                multiplier,
+                pitch
+            );
+        }
+    }
+}
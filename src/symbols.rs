@@ -0,0 +1,161 @@
+//! RGBDS 符號檔 (.sym) 支援
+//!
+//! 每行格式為 `bank:addr label`，`;` 之後為註解。
+//! 查詢位址時依 `Mmu` 目前映射的 bank 解析，同一位址在不同 ROM bank 可有不同標籤。
+
+use crate::mmu::Mmu;
+use std::collections::HashMap;
+use std::path::Path;
+
+/// 符號的 bank 與位址
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Symbol {
+    pub bank: u16,
+    pub address: u16,
+}
+
+#[derive(Debug)]
+pub enum SymbolError {
+    Io(std::io::Error),
+    Parse { line: usize, text: String },
+}
+
+impl std::fmt::Display for SymbolError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SymbolError::Io(err) => write!(f, "無法讀取符號檔: {}", err),
+            SymbolError::Parse { line, text } => {
+                write!(f, "符號檔第 {} 行格式錯誤: {}", line, text)
+            }
+        }
+    }
+}
+
+impl std::error::Error for SymbolError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SymbolError::Io(err) => Some(err),
+            SymbolError::Parse { .. } => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for SymbolError {
+    fn from(err: std::io::Error) -> Self {
+        SymbolError::Io(err)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    by_name: HashMap<String, Symbol>,
+    by_symbol: HashMap<Symbol, String>,
+}
+
+impl SymbolTable {
+    pub fn load(path: &Path) -> Result<Self, SymbolError> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self, SymbolError> {
+        let mut table = SymbolTable::default();
+        for (index, raw) in text.lines().enumerate() {
+            let line = raw.split(';').next().unwrap_or("").trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = || SymbolError::Parse {
+                line: index + 1,
+                text: raw.to_string(),
+            };
+
+            let (location, name) = line.split_once(char::is_whitespace).ok_or_else(error)?;
+            let (bank, address) = location.split_once(':').ok_or_else(error)?;
+            let symbol = Symbol {
+                bank: u16::from_str_radix(bank, 16).map_err(|_| error())?,
+                address: u16::from_str_radix(address, 16).map_err(|_| error())?,
+            };
+            table.insert(name.trim().to_string(), symbol);
+        }
+        Ok(table)
+    }
+
+    pub fn insert(&mut self, name: String, symbol: Symbol) {
+        // 同一位址有多個標籤時保留第一個 (通常是全域標籤)
+        self.by_symbol.entry(symbol).or_insert_with(|| name.clone());
+        self.by_name.insert(name, symbol);
+    }
+
+    pub fn lookup(&self, name: &str) -> Option<Symbol> {
+        self.by_name.get(name).copied()
+    }
+
+    /// 依目前映射的 bank 查詢位址上的標籤
+    pub fn label_at(&self, mmu: &Mmu, address: u16) -> Option<&str> {
+        self.label_in_bank(mapped_bank(mmu, address), address)
+    }
+
+    pub fn label_in_bank(&self, bank: u16, address: u16) -> Option<&str> {
+        self.by_symbol
+            .get(&Symbol { bank, address })
+            .map(String::as_str)
+    }
+}
+
+// RGBDS 的 bank 編號：ROMX 與 SRAM 依 MBC 目前的 bank，WRAMX (D000-DFFF) 為 1，其他為 0
+pub fn mapped_bank(mmu: &Mmu, address: u16) -> u16 {
+    match address {
+        0x0000..=0x7FFF => mmu.rom_bank_at(address).unwrap_or(0),
+        0xA000..=0xBFFF => mmu.ram_bank as u16,
+        0xD000..=0xDFFF => 1,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SYM: &str = "\
+; File generated by rgblink
+00:0150 Main
+00:0158 Main.loop
+02:4000 Level.init
+03:4000 Music.play
+00:c000 wPlayerX ; WRAM0
+";
+
+    #[test]
+    fn parses_rgbds_sym_file() {
+        let table = SymbolTable::parse(SYM).unwrap();
+        assert_eq!(
+            table.lookup("Main.loop"),
+            Some(Symbol {
+                bank: 0,
+                address: 0x0158
+            })
+        );
+        assert_eq!(table.lookup("wPlayerX").map(|s| s.address), Some(0xC000));
+        assert_eq!(table.lookup("Missing"), None);
+    }
+
+    #[test]
+    fn resolves_labels_against_mapped_rom_bank() {
+        let table = SymbolTable::parse(SYM).unwrap();
+        let mut mmu = Mmu::new();
+        mmu.mbc_type = 1;
+
+        mmu.rom_bank = 2;
+        assert_eq!(table.label_at(&mmu, 0x4000), Some("Level.init"));
+        mmu.rom_bank = 3;
+        assert_eq!(table.label_at(&mmu, 0x4000), Some("Music.play"));
+        assert_eq!(table.label_at(&mmu, 0x0150), Some("Main"));
+        assert_eq!(table.label_at(&mmu, 0xC000), Some("wPlayerX"));
+    }
+
+    #[test]
+    fn reports_malformed_lines() {
+        let err = SymbolTable::parse("00:0150 Main\nnot a symbol\n").unwrap_err();
+        assert!(matches!(err, SymbolError::Parse { line: 2, .. }));
+    }
+}
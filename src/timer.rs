@@ -1,5 +1,9 @@
 // Timer (計時器) - 負責處理 Game Boy 的定時中斷
 
+use crate::interrupt::{InterruptController, InterruptType};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Timer {
     pub div: u16, // 內部分頻器 (高 8 位元即為 0xFF04 的 DIV 寄存器)
     pub tima: u8, // 0xFF05 - Timer Counter
@@ -36,10 +40,10 @@ impl Timer {
     }
 
     // 檢測 falling edge 並更新 TIMA
-    fn check_falling_edge(&mut self, old_bit: u16, new_bit: u16, interrupt_flags: &mut u8) -> bool {
+    fn check_falling_edge(&mut self, old_bit: u16, new_bit: u16) -> bool {
         // Timer 必須啟用且發生 falling edge (1 -> 0)
         if old_bit == 1 && new_bit == 0 {
-            self.increment_tima(interrupt_flags);
+            self.increment_tima();
             true
         } else {
             false
@@ -47,7 +51,7 @@ impl Timer {
     }
 
     // 增加 TIMA
-    fn increment_tima(&mut self, _interrupt_flags: &mut u8) {
+    fn increment_tima(&mut self) {
         let (new_tima, overflow) = self.tima.overflowing_add(1);
         if overflow {
             // TIMA 溢出，設置為 0x00 並標記待處理
@@ -61,7 +65,7 @@ impl Timer {
     }
 
     // 每個 T-狀態 (4.194MHz) 調用一次
-    pub fn tick(&mut self, interrupt_flags: &mut u8) -> bool {
+    pub fn tick(&mut self, interrupts: &mut InterruptController) -> bool {
         let mut tima_incremented = false;
 
         // 處理溢出延遲
@@ -70,7 +74,7 @@ impl Timer {
             if self.overflow_cycles == 0 {
                 self.pending_overflow = false;
                 // 觸發 Timer 中斷並重載 TIMA
-                *interrupt_flags |= 0x04;
+                interrupts.request(InterruptType::Timer);
                 self.tima = self.tma;
             }
         }
@@ -87,7 +91,7 @@ impl Timer {
 
         // 檢測 falling edge
         if timer_enabled {
-            tima_incremented = self.check_falling_edge(old_bit, new_bit, interrupt_flags);
+            tima_incremented = self.check_falling_edge(old_bit, new_bit);
         }
 
         tima_incremented
@@ -103,7 +107,7 @@ impl Timer {
         }
     }
 
-    pub fn write_register(&mut self, addr: u16, value: u8, interrupt_flags: &mut u8) {
+    pub fn write_register(&mut self, addr: u16, value: u8) {
         match addr {
             0xFF04 => {
                 // 寫入 DIV 時需要檢測 falling edge
@@ -115,7 +119,7 @@ impl Timer {
                 
                 // 清零後位元變為 0
                 if timer_enabled && old_bit == 1 {
-                    self.increment_tima(interrupt_flags);
+                    self.increment_tima();
                 }
             }
             0xFF05 => {
@@ -145,7 +149,7 @@ impl Timer {
                 let new_effective = if new_enabled { new_bit } else { 0 };
                 
                 if old_effective == 1 && new_effective == 0 {
-                    self.increment_tima(interrupt_flags);
+                    self.increment_tima();
                 }
             }
             _ => {}